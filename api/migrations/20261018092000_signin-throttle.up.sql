@@ -0,0 +1,8 @@
+-- failed signins are counted per account and per client ip
+-- key is either 'account:<lowercased email>' or 'ip:<address>', accounts that do not exist are tracked too
+CREATE TABLE signin_failures (
+    key TEXT PRIMARY KEY,
+    failure_count INTEGER NOT NULL,
+    last_failed_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP
+);
@@ -0,0 +1,6 @@
+-- token buckets for the postgres rate limit backend, only used when RATE_LIMIT_BACKEND=postgres
+CREATE TABLE rate_limit_buckets (
+    key TEXT PRIMARY KEY,
+    tokens DOUBLE PRECISION NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
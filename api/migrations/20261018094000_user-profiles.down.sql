@@ -0,0 +1,9 @@
+DROP TABLE IF EXISTS user_avatars;
+
+DROP INDEX IF EXISTS users_handle_key;
+
+ALTER TABLE users
+    DROP COLUMN IF EXISTS handle,
+    DROP COLUMN IF EXISTS display_name,
+    DROP COLUMN IF EXISTS bio,
+    DROP COLUMN IF EXISTS status_text;
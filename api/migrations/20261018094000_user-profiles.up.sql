@@ -0,0 +1,15 @@
+ALTER TABLE users
+    ADD COLUMN handle TEXT,
+    ADD COLUMN display_name TEXT,
+    ADD COLUMN bio TEXT,
+    ADD COLUMN status_text TEXT;
+
+-- handles are stored lowercase, the index also guards against different casing
+CREATE UNIQUE INDEX users_handle_key ON users (LOWER(handle));
+
+CREATE TABLE user_avatars (
+    user_id UUID PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    content_type TEXT NOT NULL,
+    data BYTEA NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
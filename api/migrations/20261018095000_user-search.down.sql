@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS users_email_lower_idx;
+DROP INDEX IF EXISTS users_display_name_trgm_idx;
+DROP INDEX IF EXISTS users_handle_trgm_idx;
+
+ALTER TABLE users
+    DROP COLUMN IF EXISTS discoverable_by_email,
+    DROP COLUMN IF EXISTS searchable;
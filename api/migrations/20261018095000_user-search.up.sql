@@ -0,0 +1,13 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- users can opt out of showing up in the directory search and of being found by their email
+ALTER TABLE users
+    ADD COLUMN searchable BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN discoverable_by_email BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- trigram indexes so that substring searches on handles and display names do not scan the whole table
+CREATE INDEX users_handle_trgm_idx ON users USING GIN (LOWER(handle) gin_trgm_ops);
+CREATE INDEX users_display_name_trgm_idx ON users USING GIN (LOWER(display_name) gin_trgm_ops);
+
+-- exact email lookups ignore casing
+CREATE INDEX users_email_lower_idx ON users (LOWER(email));
@@ -0,0 +1,3 @@
+ALTER TABLE conversations DROP COLUMN IF EXISTS accepted_at;
+
+DROP TABLE IF EXISTS contacts;
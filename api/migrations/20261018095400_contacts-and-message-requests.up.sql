@@ -0,0 +1,11 @@
+CREATE TABLE contacts (
+    owner_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    contact_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (owner_id, contact_id)
+);
+
+-- conversations started by someone that is not in the receivers contacts are message requests
+-- until the receiver accepts them, NULL means the request is still pending
+ALTER TABLE conversations ADD COLUMN accepted_at TIMESTAMP;
+UPDATE conversations SET accepted_at = started_at;
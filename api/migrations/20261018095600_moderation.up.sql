@@ -0,0 +1,36 @@
+-- only admins can use the moderation api
+ALTER TABLE users
+    ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN suspended_at TIMESTAMP,
+    ADD COLUMN suspension_reason TEXT;
+
+CREATE TABLE reports (
+    id UUID PRIMARY KEY,
+    -- reports are kept when the users or the message are deleted
+    reporter_id UUID REFERENCES users (id) ON DELETE SET NULL,
+    reported_user_id UUID REFERENCES users (id) ON DELETE SET NULL,
+    message_id UUID REFERENCES messages (id) ON DELETE SET NULL,
+    -- a copy of the reported message so moderators can still see it after it is deleted
+    message_content TEXT,
+    reason TEXT NOT NULL,
+    details TEXT,
+    status TEXT NOT NULL DEFAULT 'open',
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX reports_status_idx ON reports (status, created_at);
+
+-- every moderation action is recorded, rows are never updated or deleted
+CREATE TABLE moderation_actions (
+    id UUID PRIMARY KEY,
+    report_id UUID REFERENCES reports (id) ON DELETE SET NULL,
+    moderator_id UUID REFERENCES users (id) ON DELETE SET NULL,
+    action TEXT NOT NULL,
+    target_user_id UUID,
+    target_message_id UUID,
+    note TEXT,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX moderation_actions_report_id_idx ON moderation_actions (report_id);
@@ -0,0 +1,7 @@
+-- active, deactivated (by the user themselves) or suspended (by an admin, optionally until a date)
+ALTER TABLE users
+    ADD COLUMN account_status TEXT NOT NULL DEFAULT 'active',
+    ADD COLUMN suspended_until TIMESTAMP,
+    ADD COLUMN deactivated_at TIMESTAMP;
+
+UPDATE users SET account_status = 'suspended' WHERE suspended_at IS NOT NULL;
@@ -0,0 +1,26 @@
+-- append only, rows are never updated and only deleted by the retention job
+-- user_id has no foreign key so events outlive the account they belong to
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    user_id UUID,
+    actor_id UUID,
+    ip TEXT,
+    user_agent TEXT,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX audit_events_user_id_idx ON audit_events (user_id, created_at);
+CREATE INDEX audit_events_event_type_idx ON audit_events (event_type, created_at);
+CREATE INDEX audit_events_created_at_idx ON audit_events (created_at);
+
+CREATE FUNCTION audit_events_reject_update() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_no_update
+    BEFORE UPDATE ON audit_events
+    FOR EACH ROW EXECUTE FUNCTION audit_events_reject_update();
@@ -0,0 +1,6 @@
+-- when a bucket is full again, from then on the row can be deleted without changing any limit
+ALTER TABLE rate_limit_buckets ADD COLUMN full_at TIMESTAMP;
+-- the period of the existing buckets is not known, a day is longer than any built in policy
+UPDATE rate_limit_buckets SET full_at = updated_at + INTERVAL '1 day';
+ALTER TABLE rate_limit_buckets ALTER COLUMN full_at SET NOT NULL;
+CREATE INDEX rate_limit_buckets_full_at_idx ON rate_limit_buckets (full_at);
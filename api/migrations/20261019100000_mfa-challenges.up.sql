@@ -0,0 +1,11 @@
+-- one row per pending mfa_token, keyed by the `jti` of the token
+-- the row is deleted when the signin completes so a token can only be used once, and every code
+-- that is checked counts as an attempt so the second factor cannot be guessed within the lifetime
+CREATE TABLE mfa_challenges (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX mfa_challenges_expires_at_idx ON mfa_challenges (expires_at);
@@ -0,0 +1,44 @@
+use crate::auth_service::password_reset::error::PasswordResetError;
+use crate::server::error::{ApiError, ErrorCode};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use derive_more::From;
+
+#[derive(Debug, From)]
+pub enum AdminError {
+    UserNotFound,
+    // admins cannot suspend their own account
+    CannotTargetSelf,
+
+    #[from]
+    PasswordReset(PasswordResetError),
+
+    #[from]
+    Database(sqlx::Error),
+}
+
+impl IntoResponse for AdminError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::UserNotFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::UserNotFound,
+                "User not found.",
+            )
+            .into_response(),
+            Self::CannotTargetSelf => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::CannotTargetSelf,
+                "You cannot do this to your own account.",
+            )
+            .into_response(),
+            Self::PasswordReset(e) => e.into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error in admin api {:?}", e);
+                ApiError::internal().into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,195 @@
+pub mod error;
+pub mod router;
+
+use crate::auth_service::{
+    password_reset::PasswordReset,
+    session::Session,
+    user::{account_state::AccountStatus, Role, User},
+};
+use crate::mail_service::Mailer;
+use chrono::NaiveDateTime;
+use error::AdminError;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// everything an admin can see about an account, unlike `PublicUserData` this includes the email
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct AdminUserView {
+    pub id: Uuid,
+    pub email: String,
+    pub role: Role,
+    pub handle: Option<String>,
+    pub display_name: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub account_status: AccountStatus,
+    pub suspended_at: Option<NaiveDateTime>,
+    pub suspension_reason: Option<String>,
+    pub suspended_until: Option<NaiveDateTime>,
+    pub deactivated_at: Option<NaiveDateTime>,
+}
+
+// query string of GET /admin/users, `q` matches the start of the email or handle
+#[derive(Debug, Default, Serialize, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserFilter {
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct SystemStats {
+    pub users: i64,
+    pub admins: i64,
+    pub suspended_users: i64,
+    pub deactivated_users: i64,
+    pub conversations: i64,
+    pub messages: i64,
+    pub active_sessions: i64,
+    pub open_reports: i64,
+}
+
+pub struct Admin;
+
+impl Admin {
+    pub const DEFAULT_LIMIT: i64 = 50;
+    pub const MAX_LIMIT: i64 = 200;
+
+    // newest accounts first
+    pub async fn list_users(
+        pool: &PgPool,
+        filter: &UserFilter,
+    ) -> Result<Vec<AdminUserView>, AdminError> {
+        let limit = filter
+            .limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT);
+        let offset = filter.offset.unwrap_or(0).max(0);
+        let prefix = filter
+            .q
+            .as_deref()
+            .map(str::trim)
+            .filter(|q| !q.is_empty())
+            .map(|q| {
+                let escaped = q
+                    .to_lowercase()
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                format!("{}%", escaped)
+            });
+
+        let users = sqlx::query_as!(
+            AdminUserView,
+            r#"
+            SELECT
+                id, email, role AS "role: Role", handle, display_name,
+                created_at, account_status AS "account_status: AccountStatus",
+                suspended_at, suspension_reason, suspended_until, deactivated_at
+            FROM users
+            WHERE $1::TEXT IS NULL OR LOWER(email) LIKE $1 OR LOWER(handle) LIKE $1
+            ORDER BY created_at DESC, id
+            LIMIT $2 OFFSET $3
+            "#,
+            prefix,
+            limit,
+            offset,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(users)
+    }
+
+    pub async fn get_user(pool: &PgPool, user_id: Uuid) -> Result<AdminUserView, AdminError> {
+        sqlx::query_as!(
+            AdminUserView,
+            r#"
+            SELECT
+                id, email, role AS "role: Role", handle, display_name,
+                created_at, account_status AS "account_status: AccountStatus",
+                suspended_at, suspension_reason, suspended_until, deactivated_at
+            FROM users
+            WHERE id = $1
+            "#,
+            user_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AdminError::UserNotFound)
+    }
+
+    // admins cannot suspend themselves so there is always someone left to undo it
+    // without `until` the suspension lasts until an admin reactivates the account
+    pub async fn suspend_user(
+        pool: &PgPool,
+        admin_id: Uuid,
+        user_id: Uuid,
+        reason: &str,
+        until: Option<NaiveDateTime>,
+    ) -> Result<AdminUserView, AdminError> {
+        if admin_id == user_id {
+            return Err(AdminError::CannotTargetSelf);
+        }
+        Self::get_user(pool, user_id).await?;
+
+        let mut tx = pool.begin().await?;
+        User::suspend(&mut tx, user_id, reason.trim(), until).await?;
+        tx.commit().await?;
+
+        Self::get_user(pool, user_id).await
+    }
+
+    pub async fn reactivate_user(
+        pool: &PgPool,
+        user_id: Uuid,
+    ) -> Result<AdminUserView, AdminError> {
+        Self::get_user(pool, user_id).await?;
+        User::reactivate(pool, user_id).await?;
+
+        Self::get_user(pool, user_id).await
+    }
+
+    pub async fn force_password_reset(
+        pool: &PgPool,
+        mailer: &dyn Mailer,
+        reset_url: &str,
+        user_id: Uuid,
+    ) -> Result<(), AdminError> {
+        Self::get_user(pool, user_id).await?;
+        PasswordReset::force(pool, mailer, reset_url, user_id).await?;
+
+        Ok(())
+    }
+
+    pub async fn user_sessions(pool: &PgPool, user_id: Uuid) -> Result<Vec<Session>, AdminError> {
+        Self::get_user(pool, user_id).await?;
+
+        Ok(Session::list_for_user(pool, user_id).await?)
+    }
+
+    pub async fn stats(pool: &PgPool) -> Result<SystemStats, AdminError> {
+        let stats = sqlx::query_as!(
+            SystemStats,
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM users) AS "users!",
+                (SELECT COUNT(*) FROM users WHERE role = 'admin') AS "admins!",
+                (SELECT COUNT(*) FROM users WHERE account_status = 'suspended') AS "suspended_users!",
+                (SELECT COUNT(*) FROM users WHERE account_status = 'deactivated')
+                    AS "deactivated_users!",
+                (SELECT COUNT(*) FROM conversations) AS "conversations!",
+                (SELECT COUNT(*) FROM messages) AS "messages!",
+                (SELECT COUNT(*) FROM sessions WHERE revoked_at IS NULL AND expires_at > $1)
+                    AS "active_sessions!",
+                (SELECT COUNT(*) FROM reports WHERE status = 'open') AS "open_reports!"
+            "#,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(stats)
+    }
+}
@@ -0,0 +1,319 @@
+use super::{Admin, AdminUserView, SystemStats, UserFilter};
+use crate::{
+    audit_service::{
+        AuditContext, AuditEvent, AuditEventKind, AuditFilter, AuditLog, NewAuditEvent,
+    },
+    auth_service::{
+        claims::{require_admin, AdminClaims},
+        session::Session,
+    },
+    server::{
+        error::ErrorBody,
+        extract::{Json, Path, Query},
+        AppState,
+    },
+};
+use axum::{extract::State, http::StatusCode, middleware, response::IntoResponse, Extension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+// every route requires an admin, the check is a route layer so new routes cannot forget it
+pub fn admin_routes(state: AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(stats_service))
+        .routes(routes!(list_users_service))
+        .routes(routes!(get_user_service))
+        .routes(routes!(suspend_user_service))
+        .routes(routes!(reactivate_user_service))
+        .routes(routes!(force_password_reset_service))
+        .routes(routes!(user_sessions_service))
+        .routes(routes!(audit_events_service))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats",
+    tag = "admin",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, body = SystemStats),
+        (status = 401, description = "missing_token, invalid_token, token_expired or session_revoked", body = ErrorBody),
+        (status = 403, description = "forbidden", body = ErrorBody),
+    )
+)]
+pub async fn stats_service(State(state): State<AppState>) -> impl IntoResponse {
+    match Admin::stats(&state.pool).await {
+        Ok(stats) => (StatusCode::OK, Json(stats)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "admin",
+    security(("jwt" = [])),
+    params(UserFilter),
+    responses(
+        (status = 200, description = "Newest accounts first", body = Vec<AdminUserView>),
+        (status = 401, description = "missing_token, invalid_token, token_expired or session_revoked", body = ErrorBody),
+        (status = 403, description = "forbidden", body = ErrorBody),
+    )
+)]
+pub async fn list_users_service(
+    State(state): State<AppState>,
+    Query(filter): Query<UserFilter>,
+) -> impl IntoResponse {
+    match Admin::list_users(&state.pool, &filter).await {
+        Ok(users) => (StatusCode::OK, Json(users)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}",
+    tag = "admin",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the user")),
+    responses(
+        (status = 200, body = AdminUserView),
+        (status = 401, description = "missing_token, invalid_token, token_expired or session_revoked", body = ErrorBody),
+        (status = 403, description = "forbidden", body = ErrorBody),
+        (status = 404, description = "user_not_found", body = ErrorBody),
+    )
+)]
+pub async fn get_user_service(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match Admin::get_user(&state.pool, id).await {
+        Ok(user) => (StatusCode::OK, Json(user)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct SuspendRequest {
+    pub reason: String,
+    // utc, the suspension is lifted automatically after this
+    pub until: Option<chrono::NaiveDateTime>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/suspend",
+    tag = "admin",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the user")),
+    request_body = SuspendRequest,
+    responses(
+        (status = 200, description = "The suspended account, every session of it is signed out", body = AdminUserView),
+        (status = 400, description = "cannot_target_self", body = ErrorBody),
+        (status = 401, description = "missing_token, invalid_token, token_expired or session_revoked", body = ErrorBody),
+        (status = 403, description = "forbidden", body = ErrorBody),
+        (status = 404, description = "user_not_found", body = ErrorBody),
+    )
+)]
+pub async fn suspend_user_service(
+    State(state): State<AppState>,
+    Extension(AdminClaims(claims)): Extension<AdminClaims>,
+    context: AuditContext,
+    Path(id): Path<Uuid>,
+    Json(request): Json<SuspendRequest>,
+) -> impl IntoResponse {
+    match Admin::suspend_user(
+        &state.pool,
+        claims.user_id,
+        id,
+        &request.reason,
+        request.until,
+    )
+    .await
+    {
+        Ok(user) => {
+            audit_admin_action(
+                &state,
+                &context,
+                claims.user_id,
+                id,
+                json!({ "action": "suspend_user", "reason": request.reason, "until": request.until }),
+            )
+            .await;
+            audit_sessions_revoked(&state, &context, claims.user_id, id, "suspended").await;
+
+            (StatusCode::OK, Json(user)).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/reactivate",
+    tag = "admin",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the user")),
+    responses(
+        (status = 200, body = AdminUserView),
+        (status = 401, description = "missing_token, invalid_token, token_expired or session_revoked", body = ErrorBody),
+        (status = 403, description = "forbidden", body = ErrorBody),
+        (status = 404, description = "user_not_found", body = ErrorBody),
+    )
+)]
+pub async fn reactivate_user_service(
+    State(state): State<AppState>,
+    Extension(AdminClaims(claims)): Extension<AdminClaims>,
+    context: AuditContext,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match Admin::reactivate_user(&state.pool, id).await {
+        Ok(user) => {
+            audit_admin_action(
+                &state,
+                &context,
+                claims.user_id,
+                id,
+                json!({ "action": "reactivate_user" }),
+            )
+            .await;
+
+            (StatusCode::OK, Json(user)).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/password-reset",
+    tag = "admin",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the user")),
+    responses(
+        (status = 200, description = "The user was signed out and sent a password reset link", body = String),
+        (status = 401, description = "missing_token, invalid_token, token_expired or session_revoked", body = ErrorBody),
+        (status = 403, description = "forbidden", body = ErrorBody),
+        (status = 404, description = "user_not_found", body = ErrorBody),
+    )
+)]
+pub async fn force_password_reset_service(
+    State(state): State<AppState>,
+    Extension(AdminClaims(claims)): Extension<AdminClaims>,
+    context: AuditContext,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match Admin::force_password_reset(
+        &state.pool,
+        state.mailer.as_ref(),
+        &state.password_reset_url,
+        id,
+    )
+    .await
+    {
+        Ok(()) => {
+            audit_admin_action(
+                &state,
+                &context,
+                claims.user_id,
+                id,
+                json!({ "action": "force_password_reset" }),
+            )
+            .await;
+            audit_sessions_revoked(
+                &state,
+                &context,
+                claims.user_id,
+                id,
+                "password_reset_forced",
+            )
+            .await;
+
+            (
+                StatusCode::OK,
+                "The user has been signed out and sent a password reset link.",
+            )
+                .into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn audit_admin_action(
+    state: &AppState,
+    context: &AuditContext,
+    admin_id: Uuid,
+    user_id: Uuid,
+    details: serde_json::Value,
+) {
+    let event = NewAuditEvent::new(AuditEventKind::AdminAction, context)
+        .user(user_id)
+        .actor(admin_id)
+        .details(details);
+    AuditLog::record(&state.pool, event).await;
+}
+
+async fn audit_sessions_revoked(
+    state: &AppState,
+    context: &AuditContext,
+    admin_id: Uuid,
+    user_id: Uuid,
+    reason: &str,
+) {
+    let event = NewAuditEvent::new(AuditEventKind::SessionsRevoked, context)
+        .user(user_id)
+        .actor(admin_id)
+        .details(json!({ "reason": reason }));
+    AuditLog::record(&state.pool, event).await;
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/sessions",
+    tag = "admin",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the user")),
+    responses(
+        (status = 200, body = Vec<Session>),
+        (status = 401, description = "missing_token, invalid_token, token_expired or session_revoked", body = ErrorBody),
+        (status = 403, description = "forbidden", body = ErrorBody),
+        (status = 404, description = "user_not_found", body = ErrorBody),
+    )
+)]
+pub async fn user_sessions_service(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match Admin::user_sessions(&state.pool, id).await {
+        Ok(sessions) => (StatusCode::OK, Json(sessions)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit-events",
+    tag = "admin",
+    security(("jwt" = [])),
+    params(AuditFilter),
+    responses(
+        (status = 200, description = "Newest events first", body = Vec<AuditEvent>),
+        (status = 400, description = "invalid_time_range", body = ErrorBody),
+        (status = 401, description = "missing_token, invalid_token, token_expired or session_revoked", body = ErrorBody),
+        (status = 403, description = "forbidden", body = ErrorBody),
+    )
+)]
+// filters by `user_id` (the account or the admin that acted), `event_type` and a `from`/`to` time range
+pub async fn audit_events_service(
+    State(state): State<AppState>,
+    Query(filter): Query<AuditFilter>,
+) -> impl IntoResponse {
+    match AuditLog::query(&state.pool, &filter).await {
+        Ok(events) => (StatusCode::OK, Json(events)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
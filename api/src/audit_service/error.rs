@@ -0,0 +1,32 @@
+use crate::server::error::{ApiError, ErrorCode};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use derive_more::From;
+
+#[derive(Debug, From)]
+pub enum AuditError {
+    // `from` is not before `to`
+    InvalidTimeRange,
+
+    #[from]
+    Database(sqlx::Error),
+}
+
+impl IntoResponse for AuditError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::InvalidTimeRange => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidTimeRange,
+                "The start of the time range must be before its end.",
+            )
+            .into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error in audit log {:?}", e);
+                ApiError::internal().into_response()
+            }
+        }
+    }
+}
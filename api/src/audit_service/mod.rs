@@ -0,0 +1,239 @@
+pub mod error;
+
+use crate::server::{client_ip::ClientIp, shutdown::Shutdown};
+use axum::{
+    extract::FromRequestParts,
+    http::{header::USER_AGENT, request::Parts},
+};
+use chrono::NaiveDateTime;
+use error::AuditError;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::{convert::Infallible, net::IpAddr, time::Duration};
+use uuid::Uuid;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum AuditEventKind {
+    SigninSucceeded,
+    SigninFailed,
+    Signup,
+    PasswordChanged,
+    PasswordReset,
+    SessionsRevoked,
+    MfaEnabled,
+    MfaDisabled,
+    AccountDeactivated,
+    AccountReactivated,
+    AccountDeleted,
+    // anything an admin or moderator did to another account or a report, see `details`
+    AdminAction,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub event_type: AuditEventKind,
+    // the account the event is about
+    pub user_id: Option<Uuid>,
+    // who caused it when that is not the user themselves, e.g. an admin
+    pub actor_id: Option<Uuid>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: serde_json::Value,
+    pub created_at: NaiveDateTime,
+}
+
+// ip and user agent of the request that caused an event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditContext {
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuditContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ClientIp(ip) = ClientIp::from_request_parts(parts, state).await?;
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|header_value| header_value.to_str().ok())
+            .map(|user_agent| {
+                user_agent
+                    .chars()
+                    .take(AuditLog::MAX_USER_AGENT_LENGTH)
+                    .collect()
+            });
+
+        Ok(Self { ip, user_agent })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NewAuditEvent {
+    pub kind: AuditEventKind,
+    pub user_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>,
+    pub context: AuditContext,
+    pub details: serde_json::Value,
+}
+
+impl NewAuditEvent {
+    pub fn new(kind: AuditEventKind, context: &AuditContext) -> Self {
+        Self {
+            kind,
+            user_id: None,
+            actor_id: None,
+            context: context.clone(),
+            details: serde_json::json!({}),
+        }
+    }
+
+    pub fn user(mut self, user_id: impl Into<Option<Uuid>>) -> Self {
+        self.user_id = user_id.into();
+        self
+    }
+
+    pub fn actor(mut self, actor_id: Uuid) -> Self {
+        self.actor_id = Some(actor_id);
+        self
+    }
+
+    pub fn details(mut self, details: serde_json::Value) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+// query string of GET /admin/audit-events, `from` is inclusive and `to` exclusive
+#[derive(Debug, Default, Serialize, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditFilter {
+    pub user_id: Option<Uuid>,
+    pub event_type: Option<AuditEventKind>,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+pub struct AuditLog;
+
+impl AuditLog {
+    pub const DEFAULT_LIMIT: i64 = 100;
+    pub const MAX_LIMIT: i64 = 500;
+    pub const MAX_USER_AGENT_LENGTH: usize = 512;
+    pub const RETENTION_JOB_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+    pub async fn try_record(pool: &PgPool, event: NewAuditEvent) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_events (
+                id, event_type, user_id, actor_id, ip, user_agent, details, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            Uuid::new_v4(),
+            event.kind as AuditEventKind,
+            event.user_id,
+            event.actor_id,
+            event.context.ip.map(|ip| ip.to_string()),
+            event.context.user_agent,
+            event.details,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // the action the event is about already happened, so a failed write is logged instead of failing the request
+    pub async fn record(pool: &PgPool, event: NewAuditEvent) {
+        let kind = event.kind;
+        if let Err(e) = Self::try_record(pool, event).await {
+            tracing::error!("could not record audit event {:?}: {:?}", kind, e);
+        }
+    }
+
+    // newest events first
+    pub async fn query(pool: &PgPool, filter: &AuditFilter) -> Result<Vec<AuditEvent>, AuditError> {
+        if let (Some(from), Some(to)) = (filter.from, filter.to) {
+            if from >= to {
+                return Err(AuditError::InvalidTimeRange);
+            }
+        }
+
+        let limit = filter
+            .limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT);
+        let offset = filter.offset.unwrap_or(0).max(0);
+
+        let events = sqlx::query_as!(
+            AuditEvent,
+            r#"
+            SELECT
+                id, event_type AS "event_type: AuditEventKind", user_id, actor_id,
+                ip, user_agent, details, created_at
+            FROM audit_events
+            WHERE ($1::UUID IS NULL OR user_id = $1 OR actor_id = $1)
+                AND ($2::TEXT IS NULL OR event_type = $2)
+                AND ($3::TIMESTAMP IS NULL OR created_at >= $3)
+                AND ($4::TIMESTAMP IS NULL OR created_at < $4)
+            ORDER BY created_at DESC, id
+            LIMIT $5 OFFSET $6
+            "#,
+            filter.user_id,
+            filter.event_type as Option<AuditEventKind>,
+            filter.from,
+            filter.to,
+            limit,
+            offset,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    // returns how many events were deleted
+    pub async fn purge_older_than(pool: &PgPool, retention_days: u32) -> Result<u64, sqlx::Error> {
+        let cutoff = sqlx::types::chrono::Utc::now().naive_utc()
+            - chrono::Duration::days(retention_days as i64);
+
+        let res = sqlx::query!("DELETE FROM audit_events WHERE created_at < $1", cutoff)
+            .execute(pool)
+            .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    // deletes events older than the retention period once an hour until `shutdown` is triggered
+    // a purge that already started is finished first, await the handle before closing the pool
+    pub fn spawn_retention_job(
+        pool: PgPool,
+        retention_days: u32,
+        shutdown: Shutdown,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Self::RETENTION_JOB_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = shutdown.triggered() => break,
+                    _ = interval.tick() => {}
+                }
+                match Self::purge_older_than(&pool, retention_days).await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!("purged {} expired audit events", deleted),
+                    Err(e) => tracing::error!("could not purge audit events: {:?}", e),
+                }
+            }
+        })
+    }
+}
@@ -1,8 +1,7 @@
-use axum::{
-    http::StatusCode,
-    response::{IntoResponse, Redirect},
-};
+use crate::server::error::{ApiError, ErrorCode};
+use axum::{http::StatusCode, response::IntoResponse};
 use derive_more::From;
+use jsonwebtoken::errors::ErrorKind;
 
 #[derive(Debug, From)]
 pub enum ClaimsError {
@@ -13,26 +12,91 @@ pub enum ClaimsError {
         exp: usize,
         current_time: usize,
     },
+
+    MissingToken,
+
+    SessionRevoked,
+
+    // the account was deactivated, suspended or deleted
+    AccountInactive,
+
+    // the user is signed in but not allowed to use the route, see `AdminClaims`
+    Forbidden,
+
+    #[from]
+    Database(sqlx::Error),
 }
 
 impl IntoResponse for ClaimsError {
     fn into_response(self) -> axum::response::Response {
         match self {
-            Self::Jwt(e) => {
-                tracing::error!("Jwt error: {} ", e.to_string());
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "An unkown error occured.",
-                )
+            Self::Jwt(e) => match e.kind() {
+                // jsonwebtoken checks `exp` itself when decoding
+                ErrorKind::ExpiredSignature => {
+                    tracing::debug!("Jwt token expired");
+                    ApiError::new(
+                        StatusCode::UNAUTHORIZED,
+                        ErrorCode::TokenExpired,
+                        "Your session has expired, please sign in again.",
+                    )
                     .into_response()
-            }
+                }
+                ErrorKind::InvalidToken
+                | ErrorKind::InvalidSignature
+                | ErrorKind::InvalidAlgorithm
+                | ErrorKind::Base64(_)
+                | ErrorKind::Json(_)
+                | ErrorKind::Utf8(_)
+                | ErrorKind::ImmatureSignature => ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    ErrorCode::InvalidToken,
+                    "The authorization token is invalid.",
+                )
+                .into_response(),
+                _ => {
+                    tracing::error!("Jwt error: {} ", e.to_string());
+                    ApiError::internal().into_response()
+                }
+            },
             Self::TokenExpired {
                 exp: _,
                 current_time: _,
             } => {
                 tracing::debug!("Jwt token expired");
-                // this will redirect the user to the home page
-                Redirect::to("/").into_response()
+                ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    ErrorCode::TokenExpired,
+                    "Your session has expired, please sign in again.",
+                )
+                .into_response()
+            }
+            Self::MissingToken => ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::MissingToken,
+                "No authorization header",
+            )
+            .into_response(),
+            Self::SessionRevoked => ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::SessionRevoked,
+                "Session is no longer valid, please sign in again.",
+            )
+            .into_response(),
+            Self::AccountInactive => ApiError::new(
+                StatusCode::FORBIDDEN,
+                ErrorCode::AccountInactive,
+                "This account is not active anymore, please sign in again.",
+            )
+            .into_response(),
+            Self::Forbidden => ApiError::new(
+                StatusCode::FORBIDDEN,
+                ErrorCode::Forbidden,
+                "You do not have permission to do this.",
+            )
+            .into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error while validating session {:?}", e);
+                ApiError::internal().into_response()
             }
         }
     }
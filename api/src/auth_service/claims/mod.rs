@@ -1,14 +1,28 @@
+use super::session::Session;
+use super::user::{Role, User};
+use crate::server::AppState;
+use axum::{
+    extract::{FromRequestParts, Request},
+    http::{header::AUTHORIZATION, request::Parts},
+    middleware::Next,
+    response::Response,
+};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 pub mod error;
 use error::ClaimsError;
 use uuid::Uuid;
 pub type JwtTokenString = String;
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct JwtClaims {
     pub user_id: uuid::Uuid,
+    pub session_id: uuid::Uuid,
+    // tokens issued before roles existed do not have this field, they belong to normal users
+    #[serde(default)]
+    pub role: Role,
     exp: usize,
 }
 
@@ -19,14 +33,29 @@ impl JwtClaims {
     const SECRET_KEY: &str = "Super-Secret-Key";
 
     pub fn new(user_id: Uuid) -> Self {
+        Self::with_role(user_id, Role::User)
+    }
+
+    pub fn with_role(user_id: Uuid, role: Role) -> Self {
+        Self::for_session(user_id, Uuid::new_v4(), role)
+    }
+
+    // a token for an existing session, see `Session::refresh`
+    pub fn for_session(user_id: Uuid, session_id: Uuid, role: Role) -> Self {
         let exp = (Utc::now() + Duration::days(Self::TOKEN_LIFETIME_IN_DAYS)).timestamp() as usize;
 
         Self {
-            user_id: user_id,
+            user_id,
+            session_id,
+            role,
             exp,
         }
     }
 
+    pub fn exp(&self) -> usize {
+        self.exp
+    }
+
     pub fn encode(&self) -> Result<JwtTokenString, ClaimsError> {
         let token = encode(
             &Header::default(),
@@ -49,4 +78,146 @@ impl JwtClaims {
         let claims = token.claims;
         Ok(claims)
     }
+
+    // decodes the token and checks that its session has not been revoked
+    // use this instead of `decode` for any request that acts on behalf of a user
+    pub async fn authenticate(
+        pool: &PgPool,
+        encoded_token: &JwtTokenString,
+    ) -> Result<Self, ClaimsError> {
+        let claims = Self::decode(encoded_token)?;
+
+        if !Session::is_active(pool, claims.session_id).await? {
+            return Err(ClaimsError::SessionRevoked);
+        }
+
+        // sessions are revoked when an account is deactivated or suspended, this also covers tokens
+        // that were issued while that was happening
+        if !User::is_active(pool, claims.user_id).await? {
+            return Err(ClaimsError::AccountInactive);
+        }
+
+        Ok(claims)
+    }
+}
+
+pub type MfaTokenString = String;
+
+// short lived token returned by signin when the password was correct but the account has 2fa enabled
+// it can only be exchanged for a real `JwtClaims` token together with a valid totp or recovery code
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MfaPendingClaims {
+    pub user_id: uuid::Uuid,
+    // a normal session token does not have this field so it cannot be decoded as pending claims
+    mfa_pending: bool,
+    // issued by /auth/account/reactivate, the deactivated account is only reactivated once the
+    // code is verified
+    #[serde(default)]
+    pub reactivate: bool,
+    // the id of the `mfa_challenges` row, see `Totp::start_challenge`
+    pub jti: Uuid,
+    exp: usize,
+}
+
+impl MfaPendingClaims {
+    const TOKEN_LIFETIME_IN_MINUTES: i64 = 5;
+
+    pub fn new(user_id: Uuid) -> Self {
+        let exp =
+            (Utc::now() + Duration::minutes(Self::TOKEN_LIFETIME_IN_MINUTES)).timestamp() as usize;
+
+        Self {
+            user_id,
+            mfa_pending: true,
+            reactivate: false,
+            jti: Uuid::new_v4(),
+            exp,
+        }
+    }
+
+    pub fn reactivation(user_id: Uuid) -> Self {
+        Self {
+            reactivate: true,
+            ..Self::new(user_id)
+        }
+    }
+
+    pub fn expires_at(&self) -> chrono::NaiveDateTime {
+        chrono::DateTime::from_timestamp(self.exp as i64, 0)
+            .unwrap_or_default()
+            .naive_utc()
+    }
+
+    pub fn encode(&self) -> Result<MfaTokenString, ClaimsError> {
+        let token = encode(
+            &Header::default(),
+            self,
+            &EncodingKey::from_secret(JwtClaims::SECRET_KEY.as_ref()),
+        )?;
+
+        Ok(token)
+    }
+
+    pub fn decode(encoded_token: &MfaTokenString) -> Result<Self, ClaimsError> {
+        let token = decode::<MfaPendingClaims>(
+            encoded_token,
+            &DecodingKey::from_secret(JwtClaims::SECRET_KEY.as_ref()),
+            &Validation::default(),
+        )?;
+
+        Ok(token.claims)
+    }
+}
+
+// lets handlers take `claims: JwtClaims` as an argument to require a signed in user
+// the token is read from the AUTHORIZATION header, with or without the `Bearer ` prefix
+impl FromRequestParts<AppState> for JwtClaims {
+    type Rejection = ClaimsError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|header_value| header_value.to_str().ok())
+            .map(|token| token.trim_start_matches("Bearer ").to_string())
+            .ok_or(ClaimsError::MissingToken)?;
+
+        Self::authenticate(&state.pool, &token).await
+    }
+}
+
+// like `JwtClaims` but only lets admins through, everyone else gets `ClaimsError::Forbidden`
+// the role in the token lets most requests be rejected right away, the database is still checked
+// so that taking the role away takes effect before the token expires
+#[derive(Debug, Clone)]
+pub struct AdminClaims(pub JwtClaims);
+
+impl FromRequestParts<AppState> for AdminClaims {
+    type Rejection = ClaimsError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let claims = JwtClaims::from_request_parts(parts, state).await?;
+        if claims.role != Role::Admin {
+            return Err(ClaimsError::Forbidden);
+        }
+
+        match User::get_role(&state.pool, claims.user_id).await? {
+            Some(Role::Admin) => Ok(Self(claims)),
+            _ => Err(ClaimsError::Forbidden),
+        }
+    }
+}
+
+// a route layer that only lets admins through, the handlers behind it read the verified claims
+// with `Extension<AdminClaims>` instead of checking the token and the role again
+pub async fn require_admin(admin: AdminClaims, mut request: Request, next: Next) -> Response {
+    request.extensions_mut().insert(admin);
+
+    next.run(request).await
 }
@@ -1,4 +1,6 @@
 use super::claims::error::*;
+use super::mfa::error::*;
+use super::password_reset::error::*;
 use super::user::error::*;
 
 use axum::response::{IntoResponse, Response};
@@ -12,6 +14,12 @@ pub enum AuthError {
     Signup(SignUpError),
     #[from]
     Claims(ClaimsError),
+    #[from]
+    PasswordReset(PasswordResetError),
+    #[from]
+    Account(AccountError),
+    #[from]
+    Mfa(MfaError),
 }
 
 impl IntoResponse for AuthError {
@@ -20,6 +28,9 @@ impl IntoResponse for AuthError {
             Self::Signin(e) => e.into_response(),
             Self::Signup(e) => e.into_response(),
             Self::Claims(e) => e.into_response(),
+            Self::PasswordReset(e) => e.into_response(),
+            Self::Account(e) => e.into_response(),
+            Self::Mfa(e) => e.into_response(),
         }
     }
 }
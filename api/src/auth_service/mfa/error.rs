@@ -0,0 +1,79 @@
+use super::super::{claims::error::ClaimsError, user::error::SignInError};
+use crate::server::error::{ApiError, ErrorCode};
+use axum::{http::StatusCode, response::IntoResponse};
+use derive_more::From;
+
+#[derive(Debug, From)]
+pub enum MfaError {
+    AlreadyEnabled,
+    NotEnrolled,
+
+    // the totp or recovery code is wrong, expired or was already used
+    InvalidCode,
+
+    // 2fa is enabled but the request did not have a code
+    CodeRequired,
+
+    // the mfa_token was already used, expired or had too many wrong codes
+    ChallengeExpired,
+
+    // the password could not be verified when disabling 2fa, or the account cannot sign in
+    #[from]
+    Verification(SignInError),
+
+    #[from]
+    Totp(totp_rs::TotpUrlError),
+
+    #[from]
+    Claims(ClaimsError),
+
+    #[from]
+    Database(sqlx::Error),
+}
+
+impl IntoResponse for MfaError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::AlreadyEnabled => ApiError::new(
+                StatusCode::CONFLICT,
+                ErrorCode::MfaAlreadyEnabled,
+                "Two-factor authentication is already enabled for this account.",
+            )
+            .into_response(),
+            Self::NotEnrolled => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::MfaNotEnrolled,
+                "Two-factor authentication has not been set up for this account.",
+            )
+            .into_response(),
+            Self::InvalidCode => ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::InvalidMfaCode,
+                "The authentication code is invalid or has already been used.",
+            )
+            .into_response(),
+            Self::CodeRequired => ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::MfaCodeRequired,
+                "Enter a code from your authenticator app or one of your recovery codes.",
+            )
+            .into_response(),
+            Self::ChallengeExpired => ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::MfaTokenExpired,
+                "This sign in has expired, please sign in with your password again.",
+            )
+            .into_response(),
+            Self::Verification(e) => e.into_response(),
+            Self::Totp(e) => {
+                tracing::error!("Totp error {:?}", e);
+                ApiError::internal().into_response()
+            }
+            Self::Claims(e) => e.into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error in two-factor authentication {:?}", e);
+                ApiError::internal().into_response()
+            }
+        }
+    }
+}
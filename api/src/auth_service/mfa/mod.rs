@@ -0,0 +1,411 @@
+pub mod error;
+
+use super::claims::{error::ClaimsError, JwtTokenString, MfaPendingClaims, MfaTokenString};
+use super::session::Session;
+use super::signin_throttle::SignInThrottle;
+use super::user::{account_state::AccountStatus, error::SignInError, User};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use error::MfaError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::net::IpAddr;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+// returned when enrollment starts, the uri can be shown as a qr code in the client
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+pub struct Totp;
+
+impl Totp {
+    const ISSUER: &str = "ChatApp";
+    const DIGITS: usize = 6;
+    const STEP_IN_SECONDS: u64 = 30;
+    // codes from one step before or after the current one are accepted to allow for clock drift
+    const ALLOWED_SKEW: i64 = 1;
+    const SECRET_BYTES: usize = 20;
+    const RECOVERY_CODE_COUNT: usize = 10;
+    const RECOVERY_CODE_BYTES: usize = 8;
+    // codes that can be tried with one mfa_token, the user has to sign in with the password again after that
+    const MAX_ATTEMPTS_PER_CHALLENGE: i32 = 5;
+
+    pub async fn is_enabled(pool: &PgPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        let rec = sqlx::query!(
+            "SELECT user_id FROM user_totp WHERE user_id = $1 AND confirmed_at IS NOT NULL",
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(rec.is_some())
+    }
+
+    // generates a new secret, 2fa is not enforced until it is confirmed with `confirm_enrollment`
+    // starting again before confirming replaces the previous secret
+    pub async fn begin_enrollment(
+        pool: &PgPool,
+        user_id: Uuid,
+    ) -> Result<TotpEnrollment, MfaError> {
+        if Self::is_enabled(pool, user_id).await? {
+            return Err(MfaError::AlreadyEnabled);
+        }
+
+        let user = User::get_user_by_id(pool, user_id).await?;
+
+        let mut bytes = [0u8; Self::SECRET_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        let secret = Secret::Raw(bytes.to_vec()).to_encoded().to_string();
+        let totp = Self::totp(&secret, user.email)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_totp (user_id, secret, created_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id)
+            DO UPDATE SET secret = $2, created_at = $3, confirmed_at = NULL, last_used_step = NULL
+            "#,
+            user_id,
+            secret,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(TotpEnrollment {
+            secret,
+            otpauth_uri: totp.get_url(),
+        })
+    }
+
+    // turns 2fa on once the first code checks out, the recovery codes are only returned this once
+    pub async fn confirm_enrollment(
+        pool: &PgPool,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<RecoveryCodes, MfaError> {
+        let rec = sqlx::query!(
+            "SELECT secret, confirmed_at FROM user_totp WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(MfaError::NotEnrolled)?;
+
+        if rec.confirmed_at.is_some() {
+            return Err(MfaError::AlreadyEnabled);
+        }
+
+        let step = Self::matching_step(&rec.secret, code)?.ok_or(MfaError::InvalidCode)?;
+
+        let mut tx = pool.begin().await?;
+        let now = sqlx::types::chrono::Utc::now().naive_utc();
+
+        sqlx::query!(
+            "UPDATE user_totp SET confirmed_at = $2, last_used_step = $3 WHERE user_id = $1",
+            user_id,
+            now,
+            step,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM mfa_recovery_codes WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut recovery_codes = Vec::with_capacity(Self::RECOVERY_CODE_COUNT);
+        for _ in 0..Self::RECOVERY_CODE_COUNT {
+            let code = Self::generate_recovery_code();
+
+            sqlx::query!(
+                r#"
+                INSERT INTO mfa_recovery_codes (id, user_id, code_hash, created_at)
+                VALUES ($1, $2, $3, $4)
+                "#,
+                Uuid::new_v4(),
+                user_id,
+                Self::hash_recovery_code(&code),
+                now,
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            recovery_codes.push(code);
+        }
+
+        tx.commit().await?;
+
+        Ok(RecoveryCodes { recovery_codes })
+    }
+
+    // stores the challenge of a pending token, the token is useless without the row
+    pub async fn start_challenge(
+        pool: &PgPool,
+        claims: &MfaPendingClaims,
+    ) -> Result<MfaTokenString, ClaimsError> {
+        sqlx::query!(
+            "INSERT INTO mfa_challenges (id, user_id, expires_at) VALUES ($1, $2, $3)",
+            claims.jti,
+            claims.user_id,
+            claims.expires_at(),
+        )
+        .execute(pool)
+        .await?;
+
+        let token = claims.encode()?;
+
+        Ok(token)
+    }
+
+    // second step of signin, exchanges the pending token from `User::signin` for a session token
+    // wrong codes count towards the lockout of the account like wrong passwords do
+    pub async fn complete_signin(
+        pool: &PgPool,
+        mfa_token: &MfaTokenString,
+        code: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<JwtTokenString, MfaError> {
+        let claims = MfaPendingClaims::decode(mfa_token)?;
+        let user = User::get_user_by_id(pool, claims.user_id).await?;
+
+        SignInThrottle::check(pool, &user.email, client_ip).await?;
+        Self::use_challenge_attempt(pool, &claims).await?;
+
+        match Self::verify(pool, claims.user_id, code).await {
+            Ok(()) => {}
+            Err(MfaError::InvalidCode) => {
+                SignInThrottle::record_failure(pool, &user.email, client_ip).await?;
+                return Err(MfaError::InvalidCode);
+            }
+            Err(e) => return Err(e),
+        }
+
+        // deleting the challenge is what makes the token single use, also between concurrent requests
+        let res = sqlx::query!("DELETE FROM mfa_challenges WHERE id = $1", claims.jti)
+            .execute(pool)
+            .await?;
+        if res.rows_affected() == 0 {
+            return Err(MfaError::ChallengeExpired);
+        }
+        SignInThrottle::record_success(pool, &user.email).await?;
+
+        // the account may have changed since the password was checked
+        let state = User::account_state(pool, claims.user_id).await?;
+        match state.status {
+            AccountStatus::Active => {}
+            AccountStatus::Deactivated if claims.reactivate => {
+                User::set_active(pool, claims.user_id).await?
+            }
+            AccountStatus::Deactivated => {
+                return Err(SignInError::AccountDeactivated {
+                    user_id: claims.user_id,
+                }
+                .into())
+            }
+            AccountStatus::Suspended => {
+                return Err(SignInError::AccountSuspended {
+                    user_id: claims.user_id,
+                    reason: state.suspension_reason,
+                    until: state.suspended_until,
+                }
+                .into())
+            }
+        }
+
+        let token = Session::start(pool, claims.user_id).await?;
+
+        Ok(token)
+    }
+
+    // counts the attempt before the code is checked so concurrent requests cannot go over the limit,
+    // the challenge is deleted with the last attempt
+    async fn use_challenge_attempt(
+        pool: &PgPool,
+        claims: &MfaPendingClaims,
+    ) -> Result<(), MfaError> {
+        let rec = sqlx::query!(
+            r#"
+            UPDATE mfa_challenges
+            SET attempts = attempts + 1
+            WHERE id = $1 AND user_id = $2 AND attempts < $3 AND expires_at > $4
+            RETURNING attempts
+            "#,
+            claims.jti,
+            claims.user_id,
+            Self::MAX_ATTEMPTS_PER_CHALLENGE,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(MfaError::ChallengeExpired)?;
+
+        if rec.attempts >= Self::MAX_ATTEMPTS_PER_CHALLENGE {
+            sqlx::query!(
+                "DELETE FROM mfa_challenges WHERE id = $1 AND attempts >= $2",
+                claims.jti,
+                Self::MAX_ATTEMPTS_PER_CHALLENGE,
+            )
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    // challenges that expired without being used, returns how many were deleted
+    pub async fn purge_expired_challenges(pool: &PgPool) -> Result<u64, sqlx::Error> {
+        let res = sqlx::query!(
+            "DELETE FROM mfa_challenges WHERE expires_at < $1",
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    // requires the password and a valid code so a stolen session cannot turn 2fa off
+    pub async fn disable(
+        pool: &PgPool,
+        user_id: Uuid,
+        password: &str,
+        code: &str,
+    ) -> Result<(), MfaError> {
+        let user = User::get_user_by_id(pool, user_id).await?;
+        user.verify_password(password)?;
+
+        Self::verify(pool, user_id, code).await?;
+
+        let mut tx = pool.begin().await?;
+        sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM mfa_recovery_codes WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // accepts either a current totp code or an unused recovery code, both can only be used once
+    pub async fn verify(pool: &PgPool, user_id: Uuid, code: &str) -> Result<(), MfaError> {
+        let rec = sqlx::query!(
+            "SELECT secret FROM user_totp WHERE user_id = $1 AND confirmed_at IS NOT NULL",
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(MfaError::NotEnrolled)?;
+
+        if let Some(step) = Self::matching_step(&rec.secret, code)? {
+            // only move forward so the same code cannot be used twice, even by concurrent requests
+            let res = sqlx::query!(
+                r#"
+                UPDATE user_totp
+                SET last_used_step = $2
+                WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+                "#,
+                user_id,
+                step,
+            )
+            .execute(pool)
+            .await?;
+
+            return match res.rows_affected() {
+                0 => Err(MfaError::InvalidCode),
+                _ => Ok(()),
+            };
+        }
+
+        let used_recovery_code = sqlx::query!(
+            r#"
+            UPDATE mfa_recovery_codes
+            SET used_at = $3
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            RETURNING id
+            "#,
+            user_id,
+            Self::hash_recovery_code(code),
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        match used_recovery_code {
+            Some(_) => Ok(()),
+            None => Err(MfaError::InvalidCode),
+        }
+    }
+
+    // helper functions
+    fn totp(secret: &str, account_name: String) -> Result<TOTP, MfaError> {
+        let secret = Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .map_err(|_| MfaError::Totp(totp_rs::TotpUrlError::Secret(secret.to_string())))?;
+
+        let totp = TOTP::new(
+            Algorithm::SHA1,
+            Self::DIGITS,
+            0,
+            Self::STEP_IN_SECONDS,
+            secret,
+            Some(Self::ISSUER.to_string()),
+            account_name,
+        )?;
+
+        Ok(totp)
+    }
+
+    // returns the time step the code belongs to so it can be marked as used
+    // `TOTP::check` compares in constant time so the response time does not leak matching digits
+    fn matching_step(secret: &str, code: &str) -> Result<Option<i64>, MfaError> {
+        let code = code.trim();
+        if code.len() != Self::DIGITS {
+            return Ok(None);
+        }
+
+        let totp = Self::totp(secret, String::new())?;
+        let current_step =
+            (sqlx::types::chrono::Utc::now().timestamp() as u64 / Self::STEP_IN_SECONDS) as i64;
+
+        let step = (current_step - Self::ALLOWED_SKEW..=current_step + Self::ALLOWED_SKEW)
+            .find(|step| totp.check(code, *step as u64 * Self::STEP_IN_SECONDS));
+
+        Ok(step)
+    }
+
+    // formatted as xxxx-xxxx-xxxx-xxxx so they are easier to write down
+    fn generate_recovery_code() -> String {
+        let mut bytes = [0u8; Self::RECOVERY_CODE_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+
+        hex::encode(bytes)
+            .as_bytes()
+            .chunks(4)
+            .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    // dashes, spaces and case are ignored when the user types the code back in
+    fn hash_recovery_code(code: &str) -> String {
+        let normalized: String = code
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        hex::encode(Sha256::digest(normalized.as_bytes()))
+    }
+}
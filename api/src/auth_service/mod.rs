@@ -1,4 +1,8 @@
 pub mod claims;
 pub mod error;
+pub mod mfa;
+pub mod password_reset;
 pub mod router;
+pub mod session;
+pub mod signin_throttle;
 pub mod user;
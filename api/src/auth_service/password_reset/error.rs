@@ -0,0 +1,50 @@
+use super::super::user::error::SignUpError;
+use crate::mail_service::MailError;
+use crate::server::error::{ApiError, ErrorCode};
+use axum::{http::StatusCode, response::IntoResponse};
+use derive_more::From;
+
+#[derive(Debug, From)]
+pub enum PasswordResetError {
+    // the token does not exist, was already used or has expired
+    InvalidToken,
+
+    // the new password did not pass `User::validate_password_strength`
+    #[from]
+    InvalidPassword(SignUpError),
+
+    #[from]
+    Database(sqlx::Error),
+
+    #[from]
+    PasswordHashing(argon2::password_hash::Error),
+
+    #[from]
+    Mail(MailError),
+}
+
+impl IntoResponse for PasswordResetError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::InvalidToken => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidResetToken,
+                "This password reset link is invalid or has expired. Please request a new one.",
+            )
+            .into_response(),
+            Self::InvalidPassword(e) => e.into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error while resetting password {:?}", e);
+                ApiError::internal().into_response()
+            }
+            Self::PasswordHashing(e) => {
+                tracing::error!("Argon2 hashing error in password reset {:?}", e);
+                ApiError::internal().into_response()
+            }
+            Self::Mail(e) => {
+                tracing::error!("Could not send password reset email {:?}", e);
+                ApiError::internal().into_response()
+            }
+        }
+    }
+}
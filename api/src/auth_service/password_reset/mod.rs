@@ -0,0 +1,219 @@
+pub mod error;
+
+use super::session::Session;
+use super::user::User;
+use crate::mail_service::{MailMessage, Mailer};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::Duration;
+use error::PasswordResetError;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub type ResetTokenString = String;
+
+pub struct PasswordReset;
+
+impl PasswordReset {
+    const TOKEN_LIFETIME_IN_MINUTES: i64 = 30;
+    const TOKEN_BYTES: usize = 32;
+
+    // creates a reset token and emails it to the user, the link points to `reset_url`
+    // does nothing if the email does not belong to an account, callers should respond the same way
+    // in both cases so that the endpoint cannot be used to find out which emails are registered
+    pub async fn request(
+        pool: &PgPool,
+        mailer: &dyn Mailer,
+        reset_url: &str,
+        email: &str,
+    ) -> Result<(), PasswordResetError> {
+        let user = match User::get_user_by_email(pool, email).await {
+            Ok(user) => user,
+            Err(sqlx::Error::RowNotFound) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let token = Self::create_token(pool, user.id).await?;
+
+        mailer
+            .send(MailMessage {
+                to: user.email,
+                subject: "Reset your password".to_string(),
+                body: format!(
+                    "Someone requested a password reset for your account.\n\
+                    Use the link below to choose a new password, it expires in {} minutes.\n\n\
+                    {}?token={}\n\n\
+                    If this was not you, you can ignore this email.",
+                    Self::TOKEN_LIFETIME_IN_MINUTES,
+                    reset_url,
+                    token
+                ),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    // used by admins when an account may be compromised, the current password stops working,
+    // every session is revoked and the user has to choose a new password through the emailed link
+    pub async fn force(
+        pool: &PgPool,
+        mailer: &dyn Mailer,
+        reset_url: &str,
+        user_id: Uuid,
+    ) -> Result<(), PasswordResetError> {
+        let user = User::get_user_by_id(pool, user_id).await?;
+
+        let mut random_password = [0u8; Self::TOKEN_BYTES];
+        OsRng.fill_bytes(&mut random_password);
+        let unusable_hash = User::hash_password(&hex::encode(random_password))?;
+
+        let mut tx = pool.begin().await?;
+        User::update_password_hash(&mut *tx, user.id, &unusable_hash).await?;
+        Session::revoke_all_for_user(&mut *tx, user.id).await?;
+        tx.commit().await?;
+
+        let token = Self::create_token(pool, user.id).await?;
+
+        mailer
+            .send(MailMessage {
+                to: user.email,
+                subject: "Your password has to be reset".to_string(),
+                body: format!(
+                    "An administrator reset the password of your account and signed you out everywhere.\n\
+                    Use the link below to choose a new password, it expires in {} minutes.\n\n\
+                    {}?token={}",
+                    Self::TOKEN_LIFETIME_IN_MINUTES,
+                    reset_url,
+                    token
+                ),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    // stores the hash of a new single use token and returns the plain token
+    pub async fn create_token(
+        pool: &PgPool,
+        user_id: Uuid,
+    ) -> Result<ResetTokenString, PasswordResetError> {
+        let mut bytes = [0u8; Self::TOKEN_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        let token = hex::encode(bytes);
+
+        let created_at = sqlx::types::chrono::Utc::now().naive_utc();
+        let expires_at = created_at + Duration::minutes(Self::TOKEN_LIFETIME_IN_MINUTES);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO password_reset_tokens (id, user_id, token_hash, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::new_v4(),
+            user_id,
+            Self::hash_token(&token),
+            created_at,
+            expires_at,
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    // consumes the token, sets the new password and signs the user out everywhere
+    pub async fn reset(
+        pool: &PgPool,
+        token: &str,
+        new_password: &str,
+    ) -> Result<Uuid, PasswordResetError> {
+        User::validate_password_strength(new_password)?;
+        let password_hash = User::hash_password(new_password)?;
+
+        let mut tx = pool.begin().await?;
+        let now = sqlx::types::chrono::Utc::now().naive_utc();
+
+        // marking the token as used in the same statement that checks it makes it single use
+        // even when two resets race each other
+        let rec = sqlx::query!(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = $2
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2
+            RETURNING user_id
+            "#,
+            Self::hash_token(token),
+            now,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(PasswordResetError::InvalidToken)?;
+
+        User::update_password_hash(&mut *tx, rec.user_id, &password_hash).await?;
+
+        // any other outstanding reset links for this user should stop working as well
+        sqlx::query!(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = $2
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+            rec.user_id,
+            now,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        Session::revoke_all_for_user(&mut *tx, rec.user_id).await?;
+
+        tx.commit().await?;
+
+        Ok(rec.user_id)
+    }
+
+    // used by operators from the command line, sets the password without a token and signs the
+    // user out everywhere, outstanding reset links stop working
+    pub async fn set_password(
+        pool: &PgPool,
+        user_id: Uuid,
+        new_password: &str,
+    ) -> Result<(), PasswordResetError> {
+        User::validate_password_strength(new_password)?;
+        let password_hash = User::hash_password(new_password)?;
+
+        let mut tx = pool.begin().await?;
+        User::update_password_hash(&mut *tx, user_id, &password_hash).await?;
+        sqlx::query!(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = $2
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+            user_id,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(&mut *tx)
+        .await?;
+        Session::revoke_all_for_user(&mut *tx, user_id).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // returns how many expired tokens were deleted
+    pub async fn purge_expired(pool: &PgPool) -> Result<u64, sqlx::Error> {
+        let res = sqlx::query!(
+            "DELETE FROM password_reset_tokens WHERE expires_at < $1",
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    fn hash_token(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+}
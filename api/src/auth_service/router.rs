@@ -1,66 +1,217 @@
-use crate::server::AppState;
+use crate::audit_service::{AuditContext, AuditEventKind, AuditLog, NewAuditEvent};
+use crate::server::{
+    deprecation::form_to_json, error::ErrorBody, extract::Json, metrics::METRICS, AppState,
+};
 use axum::{
-    extract::{Form, State},
+    extract::State,
     http::{header, StatusCode},
+    middleware,
     response::IntoResponse,
     routing::post,
 };
 use serde::{Deserialize, Serialize};
-pub fn auth_routes(state: AppState) -> axum::Router<AppState> {
-    axum::Router::new()
-        .route("/signup", post(signup_service))
-        .route("/signin", post(signin_service))
+use serde_json::json;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+pub fn auth_routes(state: AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(signup_service))
+        .routes(routes!(signin_service))
+        .routes(routes!(refresh_token_service))
+        .routes(routes!(mfa_enroll_service))
+        .routes(routes!(mfa_confirm_service))
+        .routes(routes!(mfa_verify_service))
+        .routes(routes!(mfa_disable_service))
+        .routes(routes!(change_password_service))
+        .routes(routes!(delete_account_service))
+        .routes(routes!(deactivate_account_service))
+        .routes(routes!(reactivate_account_service))
+        .routes(routes!(forgot_password_service))
+        .routes(routes!(reset_password_service))
+        .with_state(state)
+}
+
+// the routes from before /v1, they took form bodies that are turned into json for the same handlers
+pub fn legacy_auth_routes(state: AppState) -> axum::Router<AppState> {
+    axum::Router::from(auth_routes(state.clone()))
+        .layer(middleware::from_fn(form_to_json))
         .route("/search", post(search_service))
         .with_state(state)
 }
 
-use super::user::User;
+use super::claims::{JwtClaims, MfaPendingClaims, MfaTokenString};
+use super::mfa::{error::MfaError, RecoveryCodes, Totp, TotpEnrollment};
+use super::password_reset::PasswordReset;
+use super::session::Session;
+use super::user::{error::SignInError, PublicUserData, SignInOutcome, User};
+use crate::user_service::{error::UserSearchError, search::UserDirectory};
 
 #[derive(Serialize, Deserialize)]
 pub struct SearchForm {
     pub email: String,
 }
 
+// only part of the legacy routes, this is an exact email lookup now, see `UserDirectory` for the directory search
+// and GET /v1/users/lookup
 pub async fn search_service(
     State(state): State<AppState>,
-    Form(form): Form<SearchForm>,
+    claims: JwtClaims,
+    axum::Form(form): axum::Form<SearchForm>,
 ) -> impl IntoResponse {
     let pool = &state.pool;
 
-    let search_res = User::search_users(pool, &form.email).await;
+    let search_res = UserDirectory::find_by_email(pool, claims.user_id, &form.email).await;
 
     match search_res {
-        Ok(users) => {
-            if let Ok(user_json) = serde_json::to_string(&users) {
-                (StatusCode::OK, user_json).into_response()
-            } else {
-                tracing::error!(
-                    "An unexpected error occured searching users while converting users to json"
-                );
-
-                (StatusCode::INTERNAL_SERVER_ERROR, "An unkown error occured").into_response()
-            }
+        Ok(user) => (StatusCode::OK, Json(vec![user])).into_response(),
+        Err(UserSearchError::NoUsersFound) => {
+            (StatusCode::OK, Json(Vec::<PublicUserData>::new())).into_response()
         }
         Err(e) => e.into_response(),
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
 pub struct AuthForm {
-    email: String,
-    password: String,
+    pub email: String,
+    pub password: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/signin",
+    tag = "auth",
+    request_body = AuthForm,
+    responses(
+        (status = 200, description = "Signed in, the token is in the authorization header", body = String, headers(("authorization" = String))),
+        (status = 202, description = "Two-factor authentication is enabled, send the mfa_token with a code to /auth/mfa/verify", body = MfaRequiredResponse),
+        (status = 401, description = "invalid_credentials", body = ErrorBody),
+        (status = 403, description = "account_suspended or account_deactivated", body = ErrorBody),
+        (status = 429, description = "too_many_attempts", body = ErrorBody, headers(("retry-after" = u64))),
+    )
+)]
 pub async fn signin_service(
     State(state): State<AppState>,
-    Form(form): Form<AuthForm>,
+    context: AuditContext,
+    Json(form): Json<AuthForm>,
 ) -> impl IntoResponse {
     let pool = &state.pool;
 
-    let signin_res = User::signin(pool, &form.email, &form.password).await;
+    let signin_res = User::signin(pool, &form.email, &form.password, context.ip).await;
+    audit_signin(pool, &context, &form.email, &signin_res).await;
 
-    match signin_res {
+    signin_response(signin_res)
+}
+
+#[utoipa::path(
+    post,
+    path = "/refresh",
+    tag = "auth",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "A new token for the same session, in the authorization header", body = String, headers(("authorization" = String))),
+        (status = 401, description = "An invalid token, an expired token cannot be refreshed", body = ErrorBody),
+    )
+)]
+// keeps a session signed in without the password, so it also works for accounts with 2fa
+pub async fn refresh_token_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+) -> impl IntoResponse {
+    match Session::refresh(&state.pool, &claims).await {
         Ok(jwt_token) => {
+            let headers = [(header::AUTHORIZATION, jwt_token.as_str())];
+            (StatusCode::OK, headers, "Token refreshed").into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/account/reactivate",
+    tag = "auth",
+    request_body = AuthForm,
+    responses(
+        (status = 200, description = "Reactivated and signed in, the token is in the authorization header", body = String, headers(("authorization" = String))),
+        (status = 202, description = "Two-factor authentication is enabled, the account is reactivated once the mfa_token is verified at /auth/mfa/verify", body = MfaRequiredResponse),
+        (status = 401, description = "invalid_credentials", body = ErrorBody),
+        (status = 403, description = "account_suspended", body = ErrorBody),
+        (status = 429, description = "too_many_attempts", body = ErrorBody),
+    )
+)]
+// signs a deactivated account back in, responds like /auth/signin
+pub async fn reactivate_account_service(
+    State(state): State<AppState>,
+    context: AuditContext,
+    Json(form): Json<AuthForm>,
+) -> impl IntoResponse {
+    let pool = &state.pool;
+
+    let reactivate_res =
+        User::reactivate_account(pool, &form.email, &form.password, context.ip).await;
+    // with 2fa the account is reactivated by /auth/mfa/verify, which records the event
+    if let Ok(outcome @ SignInOutcome::Authenticated(_)) = &reactivate_res {
+        let event = NewAuditEvent::new(AuditEventKind::AccountReactivated, &context)
+            .user(outcome_user_id(outcome));
+        AuditLog::record(pool, event).await;
+    }
+    audit_signin(pool, &context, &form.email, &reactivate_res).await;
+
+    signin_response(reactivate_res)
+}
+
+fn outcome_user_id(outcome: &SignInOutcome) -> Option<uuid::Uuid> {
+    match outcome {
+        SignInOutcome::Authenticated(token) => JwtClaims::decode(token).ok().map(|c| c.user_id),
+        SignInOutcome::MfaRequired(token) => {
+            MfaPendingClaims::decode(token).ok().map(|c| c.user_id)
+        }
+    }
+}
+
+// a signin that still needs a 2fa code is recorded by /auth/mfa/verify instead
+async fn audit_signin(
+    pool: &sqlx::PgPool,
+    context: &AuditContext,
+    email: &str,
+    signin_res: &Result<SignInOutcome, SignInError>,
+) {
+    let event = match signin_res {
+        Ok(SignInOutcome::MfaRequired(_)) => return,
+        Ok(outcome) => {
+            METRICS.signin_succeeded();
+            NewAuditEvent::new(AuditEventKind::SigninSucceeded, context)
+                .user(outcome_user_id(outcome))
+        }
+        Err(e) => {
+            let reason = match e {
+                SignInError::WrongPassword { .. } => "wrong_password",
+                SignInError::EmailNotFound { .. } => "email_not_found",
+                SignInError::TooManyAttempts { .. } => "too_many_attempts",
+                SignInError::AccountSuspended { .. } => "account_suspended",
+                SignInError::AccountDeactivated { .. } => "account_deactivated",
+                _ => return,
+            };
+            METRICS.signin_failed(reason);
+            // the email is only kept when it is an account, a mistyped one could be a password
+            let user_id = e.user_id();
+            let details = match user_id {
+                Some(_) => json!({ "email": email, "reason": reason }),
+                None => json!({ "reason": reason }),
+            };
+            NewAuditEvent::new(AuditEventKind::SigninFailed, context)
+                .user(user_id)
+                .details(details)
+        }
+    };
+
+    AuditLog::record(pool, event).await;
+}
+
+fn signin_response(signin_res: Result<SignInOutcome, SignInError>) -> axum::response::Response {
+    match signin_res {
+        Ok(SignInOutcome::Authenticated(jwt_token)) => {
             let headers = [(header::AUTHORIZATION, jwt_token.as_str())];
             (
                 StatusCode::OK,
@@ -69,21 +220,435 @@ pub async fn signin_service(
             )
                 .into_response()
         }
+        // the client has to send the mfa_token with a code to /auth/mfa/verify to get the real token
+        Ok(SignInOutcome::MfaRequired(mfa_token)) => (
+            StatusCode::ACCEPTED,
+            Json(MfaRequiredResponse {
+                mfa_required: true,
+                mfa_token,
+            }),
+        )
+            .into_response(),
         Err(e) => e.into_response(),
     }
 }
+
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MfaRequiredResponse {
+    pub mfa_required: bool,
+    pub mfa_token: MfaTokenString,
+}
+#[utoipa::path(
+    post,
+    path = "/signup",
+    tag = "auth",
+    request_body = AuthForm,
+    responses(
+        (status = 200, description = "Account created, the token is in the authorization header", body = String, headers(("authorization" = String))),
+        (status = 400, description = "invalid_email, password_too_short or password_too_weak", body = ErrorBody),
+        (status = 409, description = "email_taken", body = ErrorBody),
+    )
+)]
 pub async fn signup_service(
     State(state): State<AppState>,
-    Form(form): Form<AuthForm>,
+    context: AuditContext,
+    Json(form): Json<AuthForm>,
 ) -> impl IntoResponse {
     let pool = &state.pool;
     let signup_res = User::signup(pool, &form.email, &form.password).await;
 
     match signup_res {
         Ok(jwt_token) => {
+            let event = NewAuditEvent::new(AuditEventKind::Signup, &context)
+                .user(JwtClaims::decode(&jwt_token).ok().map(|c| c.user_id));
+            AuditLog::record(pool, event).await;
+
             let headers = [(header::AUTHORIZATION, jwt_token.as_str())];
             (StatusCode::OK, headers, "Account successfully created").into_response()
         }
         Err(e) => e.into_response(),
     }
 }
+
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ForgotPasswordForm {
+    pub email: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/password/forgot",
+    tag = "auth",
+    request_body = ForgotPasswordForm,
+    responses(
+        (status = 200, description = "Always the same response, whether the account exists or not", body = String),
+    )
+)]
+// always responds with the same message so that it cannot be used to check if an email is registered
+// the token is created and sent in the background so the response time does not give it away either
+pub async fn forgot_password_service(
+    State(state): State<AppState>,
+    Json(form): Json<ForgotPasswordForm>,
+) -> impl IntoResponse {
+    tokio::spawn(async move {
+        if let Err(e) = PasswordReset::request(
+            &state.pool,
+            state.mailer.as_ref(),
+            &state.password_reset_url,
+            &form.email,
+        )
+        .await
+        {
+            tracing::error!("could not send password reset email: {:?}", e);
+        }
+    });
+
+    (
+        StatusCode::OK,
+        "If an account exists for this email, a password reset link has been sent.",
+    )
+}
+
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ResetPasswordForm {
+    pub token: String,
+    pub password: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/password/reset",
+    tag = "auth",
+    request_body = ResetPasswordForm,
+    responses(
+        (status = 200, description = "Password reset, every session is signed out", body = String),
+        (status = 400, description = "invalid_reset_token, password_too_short or password_too_weak", body = ErrorBody),
+    )
+)]
+pub async fn reset_password_service(
+    State(state): State<AppState>,
+    context: AuditContext,
+    Json(form): Json<ResetPasswordForm>,
+) -> impl IntoResponse {
+    let pool = &state.pool;
+
+    match PasswordReset::reset(pool, &form.token, &form.password).await {
+        Ok(user_id) => {
+            let event = NewAuditEvent::new(AuditEventKind::PasswordReset, &context).user(user_id);
+            AuditLog::record(pool, event).await;
+            let event = NewAuditEvent::new(AuditEventKind::SessionsRevoked, &context)
+                .user(user_id)
+                .details(json!({ "reason": "password_reset" }));
+            AuditLog::record(pool, event).await;
+
+            (
+                StatusCode::OK,
+                "Password successfully reset, please sign in with your new password",
+            )
+                .into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ChangePasswordForm {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/password",
+    tag = "auth",
+    security(("jwt" = [])),
+    request_body = ChangePasswordForm,
+    responses(
+        (status = 200, description = "Password changed, every other session is signed out", body = String),
+        (status = 400, description = "password_too_short or password_too_weak", body = ErrorBody),
+        (status = 401, description = "invalid_credentials or an invalid token", body = ErrorBody),
+    )
+)]
+// requires the AUTHORIZATION header, the session making the request stays signed in
+pub async fn change_password_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    context: AuditContext,
+    Json(form): Json<ChangePasswordForm>,
+) -> impl IntoResponse {
+    let pool = &state.pool;
+
+    match User::change_password(
+        pool,
+        claims.user_id,
+        claims.session_id,
+        &form.current_password,
+        &form.new_password,
+    )
+    .await
+    {
+        Ok(()) => {
+            let event =
+                NewAuditEvent::new(AuditEventKind::PasswordChanged, &context).user(claims.user_id);
+            AuditLog::record(pool, event).await;
+            let event = NewAuditEvent::new(AuditEventKind::SessionsRevoked, &context)
+                .user(claims.user_id)
+                .details(
+                    json!({ "reason": "password_changed", "kept_session_id": claims.session_id }),
+                );
+            AuditLog::record(pool, event).await;
+
+            (StatusCode::OK, "Password successfully changed").into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct DeleteAccountForm {
+    pub password: String,
+    // a totp or recovery code, only needed when 2fa is enabled
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/account",
+    tag = "auth",
+    security(("jwt" = [])),
+    request_body = DeleteAccountForm,
+    responses(
+        (status = 200, description = "Account deleted", body = String),
+        (status = 401, description = "invalid_credentials, mfa_code_required, invalid_mfa_code or an invalid token", body = ErrorBody),
+    )
+)]
+// requires the AUTHORIZATION header and the password of the account, and a code when 2fa is enabled
+pub async fn delete_account_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    context: AuditContext,
+    Json(form): Json<DeleteAccountForm>,
+) -> impl IntoResponse {
+    let pool = &state.pool;
+
+    match User::delete_account(pool, claims.user_id, &form.password, form.code.as_deref()).await {
+        Ok(user) => {
+            // the email is kept so the event can still be matched to a person after the account is gone
+            let event = NewAuditEvent::new(AuditEventKind::AccountDeleted, &context)
+                .user(user.id)
+                .details(json!({ "email": user.email }));
+            AuditLog::record(pool, event).await;
+
+            (StatusCode::OK, "Account successfully deleted").into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct DeactivateAccountForm {
+    pub password: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/account/deactivate",
+    tag = "auth",
+    security(("jwt" = [])),
+    request_body = DeactivateAccountForm,
+    responses(
+        (status = 200, description = "Account deactivated, every session is signed out", body = String),
+        (status = 401, description = "invalid_credentials or an invalid token", body = ErrorBody),
+    )
+)]
+// requires the AUTHORIZATION header and the password, every session of the account is signed out
+pub async fn deactivate_account_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    context: AuditContext,
+    Json(form): Json<DeactivateAccountForm>,
+) -> impl IntoResponse {
+    let pool = &state.pool;
+
+    match User::deactivate(pool, claims.user_id, &form.password).await {
+        Ok(()) => {
+            let event = NewAuditEvent::new(AuditEventKind::AccountDeactivated, &context)
+                .user(claims.user_id);
+            AuditLog::record(pool, event).await;
+            let event = NewAuditEvent::new(AuditEventKind::SessionsRevoked, &context)
+                .user(claims.user_id)
+                .details(json!({ "reason": "account_deactivated" }));
+            AuditLog::record(pool, event).await;
+
+            (StatusCode::OK, "Account successfully deactivated").into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/mfa/enroll",
+    tag = "auth",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The secret to add to an authenticator app", body = TotpEnrollment),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 409, description = "mfa_already_enabled", body = ErrorBody),
+    )
+)]
+// starts 2fa enrollment, responds with the secret and an otpauth:// uri for authenticator apps
+pub async fn mfa_enroll_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+) -> impl IntoResponse {
+    match Totp::begin_enrollment(&state.pool, claims.user_id).await {
+        Ok(enrollment) => (StatusCode::OK, Json(enrollment)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MfaCodeForm {
+    pub code: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/mfa/confirm",
+    tag = "auth",
+    security(("jwt" = [])),
+    request_body = MfaCodeForm,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled", body = RecoveryCodes),
+        (status = 400, description = "mfa_not_enrolled", body = ErrorBody),
+        (status = 401, description = "invalid_mfa_code or an invalid token", body = ErrorBody),
+        (status = 409, description = "mfa_already_enabled", body = ErrorBody),
+    )
+)]
+// enables 2fa with the first code from the authenticator app, responds with the recovery codes
+pub async fn mfa_confirm_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    context: AuditContext,
+    Json(form): Json<MfaCodeForm>,
+) -> impl IntoResponse {
+    match Totp::confirm_enrollment(&state.pool, claims.user_id, &form.code).await {
+        Ok(recovery_codes) => {
+            let event =
+                NewAuditEvent::new(AuditEventKind::MfaEnabled, &context).user(claims.user_id);
+            AuditLog::record(&state.pool, event).await;
+
+            (StatusCode::OK, Json(recovery_codes)).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MfaVerifyForm {
+    pub mfa_token: MfaTokenString,
+    pub code: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/mfa/verify",
+    tag = "auth",
+    request_body = MfaVerifyForm,
+    responses(
+        (status = 200, description = "Signed in, the token is in the authorization header", body = String, headers(("authorization" = String))),
+        (status = 401, description = "invalid_mfa_code, mfa_token_expired or an invalid mfa_token", body = ErrorBody),
+        (status = 403, description = "account_suspended or account_deactivated", body = ErrorBody),
+        (status = 429, description = "too_many_attempts", body = ErrorBody, headers(("retry-after" = u64))),
+    )
+)]
+// second step of signin, the code can be a totp code or one of the recovery codes
+// wrong codes lock the account out like wrong passwords on /auth/signin
+pub async fn mfa_verify_service(
+    State(state): State<AppState>,
+    context: AuditContext,
+    Json(form): Json<MfaVerifyForm>,
+) -> impl IntoResponse {
+    let pending = MfaPendingClaims::decode(&form.mfa_token).ok();
+    let user_id = pending.as_ref().map(|c| c.user_id);
+
+    match Totp::complete_signin(&state.pool, &form.mfa_token, &form.code, context.ip).await {
+        Ok(jwt_token) => {
+            if pending.is_some_and(|c| c.reactivate) {
+                let event =
+                    NewAuditEvent::new(AuditEventKind::AccountReactivated, &context).user(user_id);
+                AuditLog::record(&state.pool, event).await;
+            }
+            METRICS.signin_succeeded();
+            let event = NewAuditEvent::new(AuditEventKind::SigninSucceeded, &context)
+                .user(user_id)
+                .details(json!({ "mfa": true }));
+            AuditLog::record(&state.pool, event).await;
+
+            let headers = [(header::AUTHORIZATION, jwt_token.as_str())];
+            (
+                StatusCode::OK,
+                headers,
+                "Account successfully authenticated",
+            )
+                .into_response()
+        }
+        Err(MfaError::InvalidCode) => {
+            METRICS.signin_failed("invalid_mfa_code");
+            let event = NewAuditEvent::new(AuditEventKind::SigninFailed, &context)
+                .user(user_id)
+                .details(json!({ "reason": "invalid_mfa_code" }));
+            AuditLog::record(&state.pool, event).await;
+
+            MfaError::InvalidCode.into_response()
+        }
+        Err(MfaError::Verification(e @ SignInError::TooManyAttempts { .. })) => {
+            METRICS.signin_failed("too_many_attempts");
+            let event = NewAuditEvent::new(AuditEventKind::SigninFailed, &context)
+                .user(user_id)
+                .details(json!({ "reason": "too_many_attempts", "mfa": true }));
+            AuditLog::record(&state.pool, event).await;
+
+            e.into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MfaDisableForm {
+    pub password: String,
+    pub code: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/mfa/disable",
+    tag = "auth",
+    security(("jwt" = [])),
+    request_body = MfaDisableForm,
+    responses(
+        (status = 200, description = "Two-factor authentication disabled", body = String),
+        (status = 400, description = "mfa_not_enrolled", body = ErrorBody),
+        (status = 401, description = "invalid_credentials, invalid_mfa_code or an invalid token", body = ErrorBody),
+    )
+)]
+pub async fn mfa_disable_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    context: AuditContext,
+    Json(form): Json<MfaDisableForm>,
+) -> impl IntoResponse {
+    match Totp::disable(&state.pool, claims.user_id, &form.password, &form.code).await {
+        Ok(()) => {
+            let event =
+                NewAuditEvent::new(AuditEventKind::MfaDisabled, &context).user(claims.user_id);
+            AuditLog::record(&state.pool, event).await;
+
+            (StatusCode::OK, "Two-factor authentication disabled").into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,165 @@
+use super::claims::{error::ClaimsError, JwtClaims, JwtTokenString};
+use super::user::User;
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+// a session is created for every issued jwt, revoking it invalidates the token before it expires
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl Session {
+    // creates the session row and returns the encoded jwt for it, the token carries the current role of the user
+    pub async fn start(pool: &PgPool, user_id: Uuid) -> Result<JwtTokenString, ClaimsError> {
+        let role = User::get_role(pool, user_id).await?.unwrap_or_default();
+        let claims = JwtClaims::with_role(user_id, role);
+        let token = claims.encode()?;
+
+        let created_at = sqlx::types::chrono::Utc::now().naive_utc();
+        let expires_at = DateTime::from_timestamp(claims.exp() as i64, 0)
+            .map(|exp| exp.naive_utc())
+            .unwrap_or(created_at);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, user_id, created_at, expires_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            claims.session_id,
+            user_id,
+            created_at,
+            expires_at,
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    // a new token for the session of `claims` with the full lifetime, the session expires with it
+    // only a token that has not expired yet can be refreshed, after that the user signs in again
+    pub async fn refresh(pool: &PgPool, claims: &JwtClaims) -> Result<JwtTokenString, ClaimsError> {
+        let role = User::get_role(pool, claims.user_id)
+            .await?
+            .unwrap_or_default();
+        let refreshed = JwtClaims::for_session(claims.user_id, claims.session_id, role);
+        let token = refreshed.encode()?;
+
+        let now = sqlx::types::chrono::Utc::now().naive_utc();
+        let expires_at = DateTime::from_timestamp(refreshed.exp() as i64, 0)
+            .map(|exp| exp.naive_utc())
+            .unwrap_or(now);
+
+        let res = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET expires_at = $2
+            WHERE id = $1 AND revoked_at IS NULL AND expires_at > $3
+            "#,
+            claims.session_id,
+            expires_at,
+            now,
+        )
+        .execute(pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(ClaimsError::SessionRevoked);
+        }
+
+        Ok(token)
+    }
+
+    pub async fn is_active(pool: &PgPool, session_id: Uuid) -> Result<bool, sqlx::Error> {
+        let rec = sqlx::query!(
+            r#"
+            SELECT id
+            FROM sessions
+            WHERE id = $1 AND revoked_at IS NULL AND expires_at > $2
+            "#,
+            session_id,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(rec.is_some())
+    }
+
+    // newest first, includes revoked and expired sessions
+    pub async fn list_for_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<Session>, sqlx::Error> {
+        sqlx::query_as!(
+            Session,
+            r#"
+            SELECT id, user_id, created_at, expires_at, revoked_at
+            FROM sessions
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#,
+            user_id,
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    // expired sessions cannot be used any more, revoked or not, returns how many were deleted
+    pub async fn purge_expired(pool: &PgPool) -> Result<u64, sqlx::Error> {
+        let res = sqlx::query!(
+            "DELETE FROM sessions WHERE expires_at < $1",
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    // returns the amount of sessions that were revoked
+    pub async fn revoke_all_for_user(
+        executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<u64, sqlx::Error> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET revoked_at = $2
+            WHERE user_id = $1 AND revoked_at IS NULL
+            "#,
+            user_id,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    // used when the user changes their password, every other device is signed out
+    pub async fn revoke_all_for_user_except(
+        executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        keep_session_id: Uuid,
+    ) -> Result<u64, sqlx::Error> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET revoked_at = $3
+            WHERE user_id = $1 AND id <> $2 AND revoked_at IS NULL
+            "#,
+            user_id,
+            keep_session_id,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+}
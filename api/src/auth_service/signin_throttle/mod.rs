@@ -0,0 +1,173 @@
+use super::user::error::SignInError;
+use chrono::{Duration, NaiveDateTime};
+use sqlx::PgPool;
+use std::net::IpAddr;
+
+// how many failures are allowed before a key is locked and for how long
+struct ThrottlePolicy {
+    max_failures: i32,
+    base_lockout_in_seconds: i64,
+}
+
+// counts failed signins per account and per client ip, once a key has too many failures it is locked
+// and every failure after that doubles the lockout (exponential backoff) up to `MAX_LOCKOUT_IN_SECONDS`
+pub struct SignInThrottle;
+
+impl SignInThrottle {
+    const ACCOUNT_POLICY: ThrottlePolicy = ThrottlePolicy {
+        max_failures: 5,
+        base_lockout_in_seconds: 30,
+    };
+    // an ip can legitimately be shared by many users so it gets a higher limit
+    const IP_POLICY: ThrottlePolicy = ThrottlePolicy {
+        max_failures: 20,
+        base_lockout_in_seconds: 60,
+    };
+    const MAX_LOCKOUT_IN_SECONDS: i64 = 60 * 60;
+    // failures older than this are forgotten
+    const FAILURE_WINDOW_IN_SECONDS: i64 = 60 * 60;
+
+    // returns `SignInError::TooManyAttempts` if the account or the ip is locked
+    pub async fn check(
+        pool: &PgPool,
+        email: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<(), SignInError> {
+        let now = sqlx::types::chrono::Utc::now().naive_utc();
+
+        let mut keys = vec![Self::account_key(email)];
+        if let Some(ip) = client_ip {
+            keys.push(Self::ip_key(ip));
+        }
+
+        let rec = sqlx::query!(
+            r#"
+            SELECT MAX(locked_until) AS locked_until
+            FROM signin_failures
+            WHERE key = ANY($1) AND locked_until > $2
+            "#,
+            &keys,
+            now,
+        )
+        .fetch_one(pool)
+        .await?;
+
+        match rec.locked_until {
+            Some(locked_until) => Err(SignInError::TooManyAttempts {
+                retry_after_seconds: (locked_until - now).num_seconds().max(1) as u64,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn record_failure(
+        pool: &PgPool,
+        email: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<(), sqlx::Error> {
+        Self::record_key_failure(pool, &Self::account_key(email), &Self::ACCOUNT_POLICY).await?;
+
+        if let Some(ip) = client_ip {
+            Self::record_key_failure(pool, &Self::ip_key(ip), &Self::IP_POLICY).await?;
+        }
+
+        Ok(())
+    }
+
+    // only the account is cleared, otherwise an attacker could reset the ip counter with their own account
+    pub async fn record_success(pool: &PgPool, email: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM signin_failures WHERE key = $1",
+            Self::account_key(email)
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // keys whose failures are outside of the window and that are not locked any more,
+    // returns how many were deleted
+    pub async fn purge_expired(pool: &PgPool) -> Result<u64, sqlx::Error> {
+        let now = sqlx::types::chrono::Utc::now().naive_utc();
+        let window_start = now - Duration::seconds(Self::FAILURE_WINDOW_IN_SECONDS);
+
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM signin_failures
+            WHERE last_failed_at < $2 AND (locked_until IS NULL OR locked_until < $1)
+            "#,
+            now,
+            window_start,
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    async fn record_key_failure(
+        pool: &PgPool,
+        key: &str,
+        policy: &ThrottlePolicy,
+    ) -> Result<(), sqlx::Error> {
+        let now = sqlx::types::chrono::Utc::now().naive_utc();
+        let window_start = now - Duration::seconds(Self::FAILURE_WINDOW_IN_SECONDS);
+
+        let rec = sqlx::query!(
+            r#"
+            INSERT INTO signin_failures (key, failure_count, last_failed_at)
+            VALUES ($1, 1, $2)
+            ON CONFLICT (key) DO UPDATE SET
+                failure_count = CASE
+                    WHEN signin_failures.last_failed_at < $3 THEN 1
+                    ELSE signin_failures.failure_count + 1
+                END,
+                last_failed_at = $2
+            RETURNING failure_count
+            "#,
+            key,
+            now,
+            window_start,
+        )
+        .fetch_one(pool)
+        .await?;
+
+        if let Some(locked_until) = Self::locked_until(policy, rec.failure_count, now) {
+            sqlx::query!(
+                "UPDATE signin_failures SET locked_until = $2 WHERE key = $1",
+                key,
+                locked_until,
+            )
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    fn locked_until(
+        policy: &ThrottlePolicy,
+        failure_count: i32,
+        now: NaiveDateTime,
+    ) -> Option<NaiveDateTime> {
+        if failure_count < policy.max_failures {
+            return None;
+        }
+
+        // the exponent is capped so the shift cannot overflow
+        let exponent = (failure_count - policy.max_failures).min(16) as u32;
+        let lockout =
+            (policy.base_lockout_in_seconds << exponent).min(Self::MAX_LOCKOUT_IN_SECONDS);
+
+        Some(now + Duration::seconds(lockout))
+    }
+
+    fn account_key(email: &str) -> String {
+        format!("account:{}", email.trim().to_lowercase())
+    }
+
+    fn ip_key(ip: IpAddr) -> String {
+        format!("ip:{}", ip)
+    }
+}
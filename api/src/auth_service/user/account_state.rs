@@ -0,0 +1,162 @@
+use super::super::session::Session;
+use super::error::AccountError;
+use super::User;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{query, query_as, PgPool};
+use uuid::Uuid;
+
+// deactivated accounts are switched off by the user themselves and can be reactivated by signing in again,
+// suspended accounts can only be reactivated by an admin or when `suspended_until` has passed
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum AccountStatus {
+    #[default]
+    Active,
+    Deactivated,
+    Suspended,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountState {
+    pub status: AccountStatus,
+    pub suspension_reason: Option<String>,
+    // a suspension without an end date lasts until an admin lifts it
+    pub suspended_until: Option<NaiveDateTime>,
+}
+
+impl User {
+    // suspensions that ran out are lifted here, so there is no job needed for it
+    pub async fn account_state(pool: &PgPool, id: Uuid) -> Result<AccountState, sqlx::Error> {
+        let state = query_as!(
+            AccountState,
+            r#"
+            SELECT account_status AS "status: AccountStatus", suspension_reason, suspended_until
+            FROM users
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        let now = sqlx::types::chrono::Utc::now().naive_utc();
+        if state.status == AccountStatus::Suspended
+            && state.suspended_until.is_some_and(|until| until <= now)
+        {
+            Self::reactivate(pool, id).await?;
+            return Ok(AccountState {
+                status: AccountStatus::Active,
+                suspension_reason: None,
+                suspended_until: None,
+            });
+        }
+
+        Ok(state)
+    }
+
+    pub async fn is_active(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+        match Self::account_state(pool, id).await {
+            Ok(state) => Ok(state.status == AccountStatus::Active),
+            Err(sqlx::Error::RowNotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn is_suspended(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+        match Self::account_state(pool, id).await {
+            Ok(state) => Ok(state.status == AccountStatus::Suspended),
+            Err(sqlx::Error::RowNotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    // suspended users cannot sign in and every session they have is revoked
+    pub async fn suspend(
+        executor: &mut sqlx::PgConnection,
+        id: Uuid,
+        reason: &str,
+        until: Option<NaiveDateTime>,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            r#"
+            UPDATE users
+            SET account_status = 'suspended', suspended_at = $2, suspension_reason = $3, suspended_until = $4
+            WHERE id = $1
+            "#,
+            id,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+            reason,
+            until,
+        )
+        .execute(&mut *executor)
+        .await?;
+
+        Session::revoke_all_for_user(&mut *executor, id).await?;
+
+        Ok(())
+    }
+
+    // lifts a suspension or deactivation
+    pub async fn reactivate(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+        query!(
+            r#"
+            UPDATE users
+            SET account_status = 'active', suspended_at = NULL, suspension_reason = NULL,
+                suspended_until = NULL, deactivated_at = NULL
+            WHERE id = $1
+            "#,
+            id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // only reactivates deactivated accounts, so a suspension cannot be lifted through it
+    pub(in crate::auth_service) async fn set_active(
+        pool: &PgPool,
+        id: Uuid,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            r#"
+            UPDATE users SET account_status = 'active', deactivated_at = NULL
+            WHERE id = $1 AND account_status = 'deactivated'
+            "#,
+            id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // the account and its data are kept, the user is signed out everywhere and hidden from other users
+    pub async fn deactivate(pool: &PgPool, id: Uuid, password: &str) -> Result<(), AccountError> {
+        let user = Self::get_account(pool, id).await?;
+        user.verify_password(password)?;
+
+        let mut tx = pool.begin().await?;
+
+        query!(
+            r#"
+            UPDATE users SET account_status = 'deactivated', deactivated_at = $2
+            WHERE id = $1 AND account_status = 'active'
+            "#,
+            id,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        Session::revoke_all_for_user(&mut *tx, id).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
@@ -1,17 +1,37 @@
-use super::super::claims::error::ClaimsError;
+use super::super::{claims::error::ClaimsError, mfa::error::MfaError};
+use crate::server::error::{ApiError, ErrorCode};
 use argon2::password_hash;
 use axum::{
-    http::{self},
+    http::{header, StatusCode},
     response::IntoResponse,
 };
 use derive_more::{Debug, From};
+use serde_json::json;
+use uuid::Uuid;
 
+// `user_id` is the account the email belongs to, it is never sent to the client
 #[derive(Debug, From)]
 pub enum SignInError {
-    WrongPassword,
+    WrongPassword {
+        user_id: Uuid,
+    },
     EmailNotFound {
         requested_email: String,
     },
+    // the account or the client ip failed to sign in too many times, see `SignInThrottle`
+    TooManyAttempts {
+        retry_after_seconds: u64,
+    },
+    // suspended by an admin or moderator, `until` is empty when it has no end date
+    AccountSuspended {
+        user_id: Uuid,
+        reason: Option<String>,
+        until: Option<chrono::NaiveDateTime>,
+    },
+    // deactivated by the user, they can reactivate it through POST /auth/account/reactivate
+    AccountDeactivated {
+        user_id: Uuid,
+    },
     #[from]
     Database(sqlx::Error),
 
@@ -22,27 +42,71 @@ pub enum SignInError {
     PasswordHashing(argon2::password_hash::Error),
 }
 
+impl SignInError {
+    // the account the failed signin was for, `None` when the email is not registered or the
+    // attempt was refused before the account was looked up
+    pub fn user_id(&self) -> Option<Uuid> {
+        match self {
+            Self::WrongPassword { user_id }
+            | Self::AccountSuspended { user_id, .. }
+            | Self::AccountDeactivated { user_id } => Some(*user_id),
+            _ => None,
+        }
+    }
+}
+
 impl IntoResponse for SignInError {
     fn into_response(self) -> axum::response::Response {
         match self {
             Self::Database(e) => {
                 tracing::error!("Database error while signingin user {:?}", e);
-                http::status::StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                ApiError::internal().into_response()
             }
-            Self::EmailNotFound { requested_email } => (
-                http::status::StatusCode::NOT_FOUND,
-                format!("Email: {}, Not found.", requested_email),
+            // both respond the same way so the signin form cannot be used to find out which emails are registered
+            Self::EmailNotFound { requested_email: _ } | Self::WrongPassword { .. } => ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::InvalidCredentials,
+                "Invalid email or password, double check your credentials and try again.",
             )
-                .into_response(),
-            Self::WrongPassword => (
-                http::StatusCode::UNAUTHORIZED,
-                format!("Password incorrect, double check your credentials and try again."),
+            .into_response(),
+            Self::TooManyAttempts {
+                retry_after_seconds,
+            } => (
+                [(header::RETRY_AFTER, retry_after_seconds.to_string())],
+                ApiError::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    ErrorCode::TooManyAttempts,
+                    format!(
+                        "Too many failed sign in attempts. Please try again in {} seconds.",
+                        retry_after_seconds
+                    ),
+                )
+                .with_details(json!({ "retry_after_seconds": retry_after_seconds })),
             )
                 .into_response(),
+            Self::AccountSuspended { reason, until, .. } => {
+                let mut message = "This account has been suspended".to_string();
+                if let Some(until) = until {
+                    message.push_str(&format!(" until {} UTC", until.format("%Y-%m-%d %H:%M")));
+                }
+                message.push('.');
+                if let Some(reason) = &reason {
+                    message.push_str(&format!(" Reason: {}", reason));
+                }
+                ApiError::new(StatusCode::FORBIDDEN, ErrorCode::AccountSuspended, message)
+                    .with_details(json!({ "reason": reason, "until": until }))
+                    .into_response()
+            }
+            Self::AccountDeactivated { .. } => ApiError::new(
+                StatusCode::FORBIDDEN,
+                ErrorCode::AccountDeactivated,
+                "This account has been deactivated. Sign in through /auth/account/reactivate to reactivate it.",
+            )
+            .into_response(),
             Self::JwtClaims(e) => e.into_response(),
             Self::PasswordHashing(e) => {
                 tracing::error!("Argon2 hashing error on signin {:?}", e);
-                http::status::StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                ApiError::internal().into_response()
             }
         }
     }
@@ -79,91 +143,110 @@ pub enum SignUpError {
 impl IntoResponse for SignUpError {
     fn into_response(self) -> axum::response::Response {
         match self {
-                Self::InvalidEmail { requested_email } => {
-                    (
-                        http::status::StatusCode::BAD_REQUEST,
-                        format!(
-                            "Email {} is not a valid email. Please check your input and try again.",
-                            requested_email
-                        ),
-                    )
-                        .into_response()
-                },
-                SignUpError::EmailTaken { requested_email } => {
-                    (
-                        http::status::StatusCode::CONFLICT,
-                        format!(
-                            "Email {} is already taken. Please choose a different email, or try logging in.",
-                            requested_email
-                        ),
-                    )
-                        .into_response()
-                }
-                SignUpError::PasswordTooShort {
-                    min_length,
-                    actual_length,
-                } => (
-                    http::status::StatusCode::BAD_REQUEST,
-                    format!(
-                        "Password must be at least {} characters long. You provided a password of {} characters.",
-                        min_length, actual_length
-                    ),
-                )
-                    .into_response(),
-                SignUpError::PasswordTooWeak {
-                    has_lowercase,
-                    has_uppercase,
-                    has_number,
-                    has_special,
-                } => {
-                    let mut password_requirements = std::collections::HashMap::new();
-                        password_requirements.insert("lowercase", has_lowercase);
-                        password_requirements.insert("uppercase", has_uppercase);
-                        password_requirements.insert("number", has_number);
-                        password_requirements.insert("special", has_special);
-
-                    (
-                        http::status::StatusCode::BAD_REQUEST,
-                        axum::Json(password_requirements),
-                    )
-                        .into_response()
-                }
-                SignUpError::JwtClaims(e) =>{
-                    e.into_response()
-                    // (
-                    //     http::status::StatusCode::INTERNAL_SERVER_ERROR,
-                    //     "Error generating authentication token. Please try again later.".to_string(),
-                    // )
-                }
-                    .into_response(),
-                SignUpError::Database(e) => {
-                    tracing::error!("Database error while signingup user {:?}", e);
-                    http::status::StatusCode::INTERNAL_SERVER_ERROR.into_response()
-                },
-                Self::PasswordHashing(e) => {
-                    tracing::error!("Argon2 hashing error in signup {:?}", e);
-                    http::status::StatusCode::INTERNAL_SERVER_ERROR.into_response()
-                }
+            Self::InvalidEmail { requested_email } => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidEmail,
+                format!(
+                    "Email {} is not a valid email. Please check your input and try again.",
+                    requested_email
+                ),
+            )
+            .into_response(),
+            Self::EmailTaken { requested_email } => ApiError::new(
+                StatusCode::CONFLICT,
+                ErrorCode::EmailTaken,
+                format!(
+                    "Email {} is already taken. Please choose a different email, or try logging in.",
+                    requested_email
+                ),
+            )
+            .into_response(),
+            Self::PasswordTooShort {
+                min_length,
+                actual_length,
+            } => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::PasswordTooShort,
+                format!(
+                    "Password must be at least {} characters long. You provided a password of {} characters.",
+                    min_length, actual_length
+                ),
+            )
+            .with_details(json!({ "min_length": min_length, "actual_length": actual_length }))
+            .into_response(),
+            // the details say which kinds of characters the password has
+            Self::PasswordTooWeak {
+                has_lowercase,
+                has_uppercase,
+                has_number,
+                has_special,
+            } => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::PasswordTooWeak,
+                "Password must contain a lowercase letter, an uppercase letter, a number and a special character.",
+            )
+            .with_details(json!({
+                "lowercase": has_lowercase,
+                "uppercase": has_uppercase,
+                "number": has_number,
+                "special": has_special,
+            }))
+            .into_response(),
+            Self::JwtClaims(e) => e.into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error while signingup user {:?}", e);
+                ApiError::internal().into_response()
+            }
+            Self::PasswordHashing(e) => {
+                tracing::error!("Argon2 hashing error in signup {:?}", e);
+                ApiError::internal().into_response()
+            }
         }
     }
 }
 
-#[derive(From, Debug)]
-pub enum UserSearchError {
-    NoUsersFound,
+#[derive(Debug, From)]
+pub enum AccountError {
+    AccountNotFound,
+
+    // the current password could not be verified
+    #[from]
+    Verification(SignInError),
+
+    // the new password did not pass `User::validate_password_strength`
+    #[from]
+    InvalidPassword(SignUpError),
+
+    // 2fa is enabled and the totp or recovery code is missing or wrong
+    #[from]
+    Mfa(MfaError),
+
     #[from]
     Database(sqlx::Error),
+
+    #[from]
+    PasswordHashing(password_hash::Error),
 }
 
-impl IntoResponse for UserSearchError {
+impl IntoResponse for AccountError {
     fn into_response(self) -> axum::response::Response {
         match self {
-            Self::NoUsersFound => {
-                (http::status::StatusCode::NOT_FOUND, "No users found.").into_response()
-            }
+            Self::AccountNotFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::AccountNotFound,
+                "Account not found.",
+            )
+            .into_response(),
+            Self::Verification(e) => e.into_response(),
+            Self::InvalidPassword(e) => e.into_response(),
+            Self::Mfa(e) => e.into_response(),
             Self::Database(e) => {
-                tracing::error!("Database error while searching users {:?}", e);
-                http::status::StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                tracing::error!("Database error while updating account {:?}", e);
+                ApiError::internal().into_response()
+            }
+            Self::PasswordHashing(e) => {
+                tracing::error!("Argon2 hashing error while updating account {:?}", e);
+                ApiError::internal().into_response()
             }
         }
     }
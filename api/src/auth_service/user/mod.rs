@@ -1,9 +1,16 @@
 use super::claims::*;
+use super::mfa::{error::MfaError, Totp};
+use super::session::Session;
+use super::signin_throttle::SignInThrottle;
+use crate::user_service::profile::UserProfile;
+pub mod account_state;
 pub mod error;
+use account_state::AccountStatus;
 use chrono::NaiveDateTime;
-use error::{SignInError, SignUpError, UserSearchError};
+use error::{AccountError, SignInError, SignUpError};
 use serde::{Deserialize, Serialize};
 use sqlx::{query, query_as, PgPool};
+use std::{net::IpAddr, sync::LazyLock};
 use uuid::Uuid;
 
 use argon2::{
@@ -19,74 +26,224 @@ pub struct User {
     pub created_at: NaiveDateTime,
 }
 
-// this is for public user search results
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+// this is for public user search results, it never contains the email
+// the profile fields are empty until the user fills in their profile, see `user_service::profile`
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct PublicUserData {
     pub id: Uuid,
-    pub email: String,
+    pub handle: Option<String>,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub status_text: Option<String>,
 }
 
-impl From<&User> for PublicUserData {
-    fn from(user: &User) -> Self {
+// a row of the queries for `PublicUserData`, the avatar url is built in rust from the id
+pub struct PublicUserRecord {
+    pub id: Uuid,
+    pub handle: Option<String>,
+    pub display_name: Option<String>,
+    pub has_avatar: bool,
+    pub status_text: Option<String>,
+}
+
+impl From<PublicUserRecord> for PublicUserData {
+    fn from(rec: PublicUserRecord) -> Self {
         PublicUserData {
-            id: user.id,
-            email: user.email.clone(),
+            id: rec.id,
+            handle: rec.handle,
+            display_name: rec.display_name,
+            avatar_url: rec.has_avatar.then(|| UserProfile::avatar_url(rec.id)),
+            status_text: rec.status_text,
         }
     }
 }
 
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum Role {
+    #[default]
+    User,
+    Admin,
+}
+
+// verified against when the email does not exist so that the response takes as long as a wrong password
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    User::hash_password("dummy-password-for-constant-time-signin")
+        .expect("error hashing dummy password")
+});
+
+// signin either finishes right away or, when 2fa is enabled, needs a second step with `Totp::complete_signin`
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignInOutcome {
+    Authenticated(JwtTokenString),
+    MfaRequired(MfaTokenString),
+}
+
 impl User {
     pub const MIN_PASSWORD_LENGTH: usize = 6;
-    pub async fn search_users(
+    // failed attempts are counted per account and per `client_ip`, see `SignInThrottle`
+    pub async fn signin(
         pool: &PgPool,
-        search_request: &str,
-    ) -> Result<Vec<PublicUserData>, UserSearchError> {
-        let search_results = query_as!(
-            User,
-            "SELECT id, email, password, created_at FROM users WHERE email LIKE $1",
-            format!("%{}%", search_request)
-        )
-        .fetch_all(pool)
-        .await?;
+        email: &str,
+        password: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<SignInOutcome, SignInError> {
+        let user = Self::authenticate_credentials(pool, email, password, client_ip).await?;
 
-        let public_data: Vec<PublicUserData> = search_results
-            .iter()
-            .map(|u| PublicUserData::from(u))
-            .collect();
+        // only checked after the password so the error does not tell anyone which accounts exist
+        let state = Self::account_state(pool, user.id).await?;
+        match state.status {
+            AccountStatus::Active => {}
+            AccountStatus::Deactivated => {
+                return Err(SignInError::AccountDeactivated { user_id: user.id })
+            }
+            AccountStatus::Suspended => {
+                return Err(SignInError::AccountSuspended {
+                    user_id: user.id,
+                    reason: state.suspension_reason,
+                    until: state.suspended_until,
+                })
+            }
+        }
 
-        Ok(public_data)
+        Self::start_signin(pool, user.id, false).await
     }
-    pub async fn signin(
+
+    // signs a deactivated account back in, suspended accounts can only be reactivated by an admin
+    // with 2fa enabled the account stays deactivated until `Totp::complete_signin`
+    pub async fn reactivate_account(
+        pool: &PgPool,
+        email: &str,
+        password: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<SignInOutcome, SignInError> {
+        let user = Self::authenticate_credentials(pool, email, password, client_ip).await?;
+
+        let state = Self::account_state(pool, user.id).await?;
+        match state.status {
+            AccountStatus::Active => Self::start_signin(pool, user.id, false).await,
+            AccountStatus::Deactivated => Self::start_signin(pool, user.id, true).await,
+            AccountStatus::Suspended => Err(SignInError::AccountSuspended {
+                user_id: user.id,
+                reason: state.suspension_reason,
+                until: state.suspended_until,
+            }),
+        }
+    }
+
+    async fn authenticate_credentials(
         pool: &PgPool,
         email: &str,
         password: &str,
-    ) -> Result<JwtTokenString, SignInError> {
-        return match Self::get_user_by_email(pool, email).await {
+        client_ip: Option<IpAddr>,
+    ) -> Result<User, SignInError> {
+        SignInThrottle::check(pool, email, client_ip).await?;
+
+        match Self::verify_credentials(pool, email, password).await {
             Ok(user) => {
-                let parsed_hash = PasswordHash::new(&user.password)?;
-
-                let verification_res =
-                    Argon2::default().verify_password(password.as_bytes(), &parsed_hash);
-
-                if Err(argon2::password_hash::Error::Password) == verification_res {
-                    return Err(SignInError::WrongPassword);
-                } else if let Err(e) = verification_res {
-                    tracing::error!("Unexpected Error {:?} in password verification", e);
-                    return Err(SignInError::PasswordHashing(e));
-                } else {
-                    let claims = JwtClaims::new(user.id);
-                    let token = claims.encode()?;
-
-                    return Ok(token);
-                }
+                SignInThrottle::record_success(pool, email).await?;
+                Ok(user)
+            }
+            Err(e @ (SignInError::WrongPassword { .. } | SignInError::EmailNotFound { .. })) => {
+                SignInThrottle::record_failure(pool, email, client_ip).await?;
+                Err(e)
             }
-            Err(err) => match err {
-                sqlx::Error::RowNotFound => Err(SignInError::EmailNotFound {
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn start_signin(
+        pool: &PgPool,
+        user_id: Uuid,
+        reactivate: bool,
+    ) -> Result<SignInOutcome, SignInError> {
+        if Totp::is_enabled(pool, user_id).await? {
+            let claims = if reactivate {
+                MfaPendingClaims::reactivation(user_id)
+            } else {
+                MfaPendingClaims::new(user_id)
+            };
+            return Ok(SignInOutcome::MfaRequired(
+                Totp::start_challenge(pool, &claims).await?,
+            ));
+        }
+
+        if reactivate {
+            Self::set_active(pool, user_id).await?;
+        }
+        let token = Session::start(pool, user_id).await?;
+
+        Ok(SignInOutcome::Authenticated(token))
+    }
+
+    // a password is always verified, even when the email does not exist, so both cases take the same time
+    async fn verify_credentials(
+        pool: &PgPool,
+        email: &str,
+        password: &str,
+    ) -> Result<User, SignInError> {
+        match Self::get_user_by_email(pool, email).await {
+            Ok(user) => {
+                user.verify_password(password)?;
+                Ok(user)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let parsed_hash = PasswordHash::new(&DUMMY_PASSWORD_HASH)?;
+                let _ = Argon2::default().verify_password(password.as_bytes(), &parsed_hash);
+
+                Err(SignInError::EmailNotFound {
                     requested_email: email.to_string(),
-                }),
-                _ => Err(SignInError::Database(err)),
-            },
-        };
+                })
+            }
+            Err(err) => Err(SignInError::Database(err)),
+        }
+    }
+
+    // requires the current password, every other session of the user is signed out
+    pub async fn change_password(
+        pool: &PgPool,
+        id: Uuid,
+        current_session_id: Uuid,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), AccountError> {
+        let user = Self::get_account(pool, id).await?;
+        user.verify_password(current_password)?;
+
+        Self::validate_password_strength(new_password)?;
+        let password_hash = Self::hash_password(new_password)?;
+
+        let mut tx = pool.begin().await?;
+        Self::update_password_hash(&mut *tx, id, &password_hash).await?;
+        Session::revoke_all_for_user_except(&mut *tx, id, current_session_id).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // requires the password again before anything is deleted, and a code when 2fa is enabled
+    // so a stolen session with the password is not enough, like `Totp::disable`
+    // conversations, messages and sessions of the user are removed by the ON DELETE CASCADE constraints
+    pub async fn delete_account(
+        pool: &PgPool,
+        id: Uuid,
+        password: &str,
+        code: Option<&str>,
+    ) -> Result<User, AccountError> {
+        let user = Self::get_account(pool, id).await?;
+        user.verify_password(password)?;
+
+        if Totp::is_enabled(pool, id).await? {
+            let code = code.ok_or(MfaError::CodeRequired)?;
+            Totp::verify(pool, id, code).await?;
+        }
+
+        let deleted_user = Self::delete_user_by_id(pool, id).await?;
+
+        Ok(deleted_user)
     }
 
     pub async fn signup(
@@ -97,15 +254,11 @@ impl User {
         let id = Uuid::new_v4();
         let created_at = sqlx::types::chrono::Utc::now().naive_utc();
 
-        Self::validate_email(&pool, email).await?;
+        Self::validate_email(pool, email).await?;
         Self::validate_password_strength(password)?;
 
         // encrypt password
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        let password_hash = argon2
-            .hash_password(password.as_bytes(), &salt)?
-            .to_string();
+        let password_hash = Self::hash_password(password)?;
 
         query!(
             "INSERT INTO users (id, email, password, created_at) VALUES ($1, $2, $3, $4)",
@@ -117,12 +270,45 @@ impl User {
         .execute(pool)
         .await?;
 
-        let jwt_token = JwtClaims::new(id).encode()?;
+        let jwt_token = Session::start(pool, id).await?;
 
         Ok(jwt_token)
     }
 
     // helper functions
+    pub fn verify_password(&self, password: &str) -> Result<(), SignInError> {
+        let parsed_hash = PasswordHash::new(&self.password)?;
+
+        let verification_res = Argon2::default().verify_password(password.as_bytes(), &parsed_hash);
+
+        if Err(argon2::password_hash::Error::Password) == verification_res {
+            Err(SignInError::WrongPassword { user_id: self.id })
+        } else if let Err(e) = verification_res {
+            tracing::error!("Unexpected Error {:?} in password verification", e);
+            Err(SignInError::PasswordHashing(e))
+        } else {
+            Ok(())
+        }
+    }
+
+    // the account can be gone while a token for it is still valid
+    async fn get_account(pool: &PgPool, id: Uuid) -> Result<User, AccountError> {
+        match Self::get_user_by_id(pool, id).await {
+            Ok(user) => Ok(user),
+            Err(sqlx::Error::RowNotFound) => Err(AccountError::AccountNotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)?
+            .to_string();
+
+        Ok(password_hash)
+    }
+
     pub fn validate_password_strength(password: &str) -> Result<(), SignUpError> {
         let length = password.len();
 
@@ -131,7 +317,7 @@ impl User {
         let has_number = password.chars().any(|c| c.is_numeric());
         let has_special = password.chars().any(|c| !c.is_alphanumeric());
 
-        return if length < Self::MIN_PASSWORD_LENGTH {
+        if length < Self::MIN_PASSWORD_LENGTH {
             Err(SignUpError::PasswordTooShort {
                 min_length: Self::MIN_PASSWORD_LENGTH,
                 actual_length: length,
@@ -145,7 +331,7 @@ impl User {
             })
         } else {
             Ok(())
-        };
+        }
     }
 
     pub async fn validate_email(pool: &PgPool, email: &str) -> Result<(), SignUpError> {
@@ -159,13 +345,13 @@ impl User {
             .fetch_one(pool)
             .await;
 
-        return match res {
+        match res {
             Ok(_) => Err(SignUpError::EmailTaken {
                 requested_email: email.to_string(),
             }),
             Err(sqlx::Error::RowNotFound) => Ok(()),
             Err(err) => Err(err.into()),
-        };
+        }
     }
 
     // crud helper functions
@@ -190,8 +376,50 @@ impl User {
     }
 
     pub async fn delete_user_by_id(pool: &PgPool, id: Uuid) -> Result<User, sqlx::Error> {
-        query_as!(User, "DELETE FROM users WHERE id = $1 RETURNING *", id)
-            .fetch_one(pool)
-            .await
+        query_as!(
+            User,
+            "DELETE FROM users WHERE id = $1 RETURNING id, email, password, created_at",
+            id
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    // `None` when the user does not exist
+    pub async fn get_role(pool: &PgPool, id: Uuid) -> Result<Option<Role>, sqlx::Error> {
+        let rec = query!(
+            r#"SELECT role AS "role: Role" FROM users WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(rec.map(|rec| rec.role))
+    }
+
+    // the new role is only part of tokens issued after this
+    pub async fn set_role(pool: &PgPool, id: Uuid, role: Role) -> Result<(), sqlx::Error> {
+        query!("UPDATE users SET role = $2 WHERE id = $1", id, role as Role)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // the password must already be hashed with `hash_password`
+    pub async fn update_password_hash(
+        executor: impl sqlx::PgExecutor<'_>,
+        id: Uuid,
+        password_hash: &str,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            "UPDATE users SET password = $2 WHERE id = $1",
+            id,
+            password_hash
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
     }
 }
@@ -0,0 +1,29 @@
+use derive_more::From;
+
+#[derive(Debug, From)]
+pub enum ConfigError {
+    // the environment variable is set but its value could not be parsed
+    InvalidValue {
+        variable: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        match self {
+            Self::InvalidValue {
+                variable,
+                value,
+                expected,
+            } => write!(
+                fmt,
+                "invalid value {:?} for {}, expected {}",
+                value, variable, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
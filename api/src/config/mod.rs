@@ -0,0 +1,159 @@
+pub mod error;
+
+use crate::db_service::DEFAULT_DATABASE_URL;
+use crate::server::{
+    client_ip::TrustedProxies,
+    rate_limit::{RateLimitBackendKind, RateLimitConfig, RateLimitPolicy},
+};
+use error::ConfigError;
+use std::time::Duration;
+
+// runtime configuration read from environment variables, every value has a default for local development
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub database_url: String,
+    pub rate_limit: RateLimitConfig,
+    // audit events older than this are deleted by `AuditLog::spawn_retention_job`
+    pub audit_retention_days: u32,
+    // how long requests in flight get to finish after SIGTERM or ctrl-c
+    pub shutdown_timeout: Duration,
+    // the page of the web client where users choose a new password, reset emails link to it
+    // with the token in a `token` query parameter
+    pub password_reset_url: String,
+    // requests from these addresses have the client ip read from their forwarding headers
+    pub trusted_proxies: TrustedProxies,
+}
+
+impl Config {
+    pub const DATABASE_URL_VAR: &str = "DATABASE_URL";
+    // `memory` or `postgres`, use postgres when several instances run behind a load balancer
+    pub const RATE_LIMIT_BACKEND_VAR: &str = "RATE_LIMIT_BACKEND";
+    // policy used for every route without its own policy, e.g. `120/60`
+    pub const RATE_LIMIT_DEFAULT_VAR: &str = "RATE_LIMIT_DEFAULT";
+    // comma separated per route policies that are added to (or replace) the built in ones,
+    // e.g. `POST /v1/auth/signup=5/600,/v1/users/search=30/60`
+    pub const RATE_LIMIT_ROUTES_VAR: &str = "RATE_LIMIT_ROUTES";
+    // number of days audit events are kept
+    pub const AUDIT_RETENTION_DAYS_VAR: &str = "AUDIT_RETENTION_DAYS";
+    pub const DEFAULT_AUDIT_RETENTION_DAYS: u32 = 365;
+    // seconds the server waits for requests in flight when it is stopped
+    pub const SHUTDOWN_TIMEOUT_SECONDS_VAR: &str = "SHUTDOWN_TIMEOUT_SECONDS";
+    pub const DEFAULT_SHUTDOWN_TIMEOUT_SECONDS: u64 = 30;
+    pub const PASSWORD_RESET_URL_VAR: &str = "PASSWORD_RESET_URL";
+    pub const DEFAULT_PASSWORD_RESET_URL: &str = "http://localhost:5173/reset-password";
+    // comma separated addresses or networks of the load balancers in front of the api,
+    // e.g. `10.0.0.0/8,127.0.0.1`. unset, `Forwarded` and `X-Forwarded-For` are ignored
+    pub const TRUSTED_PROXIES_VAR: &str = "TRUSTED_PROXIES";
+
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let mut rate_limit = RateLimitConfig::default();
+
+        if let Some(value) = Self::var(Self::RATE_LIMIT_BACKEND_VAR) {
+            rate_limit.backend = match value.to_lowercase().as_str() {
+                "memory" => RateLimitBackendKind::Memory,
+                "postgres" => RateLimitBackendKind::Postgres,
+                _ => {
+                    return Err(ConfigError::InvalidValue {
+                        variable: Self::RATE_LIMIT_BACKEND_VAR,
+                        value,
+                        expected: "`memory` or `postgres`",
+                    })
+                }
+            };
+        }
+
+        if let Some(value) = Self::var(Self::RATE_LIMIT_DEFAULT_VAR) {
+            rate_limit.default_policy =
+                value
+                    .parse::<RateLimitPolicy>()
+                    .map_err(|_| ConfigError::InvalidValue {
+                        variable: Self::RATE_LIMIT_DEFAULT_VAR,
+                        value: value.clone(),
+                        expected: "<requests>/<seconds>",
+                    })?;
+        }
+
+        if let Some(value) = Self::var(Self::RATE_LIMIT_ROUTES_VAR) {
+            for entry in value.split(',').filter(|entry| !entry.trim().is_empty()) {
+                let (route, policy) = entry
+                    .split_once('=')
+                    .and_then(|(route, policy)| {
+                        Some((route.trim().to_string(), policy.parse().ok()?))
+                    })
+                    .ok_or_else(|| ConfigError::InvalidValue {
+                        variable: Self::RATE_LIMIT_ROUTES_VAR,
+                        value: entry.to_string(),
+                        expected: "[METHOD ]<path>=<requests>/<seconds>",
+                    })?;
+
+                rate_limit.route_policies.insert(route, policy);
+            }
+        }
+
+        let audit_retention_days = match Self::var(Self::AUDIT_RETENTION_DAYS_VAR) {
+            Some(value) => value
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|days| *days > 0)
+                .ok_or(ConfigError::InvalidValue {
+                    variable: Self::AUDIT_RETENTION_DAYS_VAR,
+                    value,
+                    expected: "a number of days greater than 0",
+                })?,
+            None => Self::DEFAULT_AUDIT_RETENTION_DAYS,
+        };
+
+        let shutdown_timeout_seconds = match Self::var(Self::SHUTDOWN_TIMEOUT_SECONDS_VAR) {
+            Some(value) => value
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| ConfigError::InvalidValue {
+                    variable: Self::SHUTDOWN_TIMEOUT_SECONDS_VAR,
+                    value,
+                    expected: "a number of seconds",
+                })?,
+            None => Self::DEFAULT_SHUTDOWN_TIMEOUT_SECONDS,
+        };
+
+        let password_reset_url = match Self::var(Self::PASSWORD_RESET_URL_VAR) {
+            Some(value) if value.starts_with("http://") || value.starts_with("https://") => {
+                value.trim().to_string()
+            }
+            Some(value) => {
+                return Err(ConfigError::InvalidValue {
+                    variable: Self::PASSWORD_RESET_URL_VAR,
+                    value,
+                    expected: "an http:// or https:// url",
+                })
+            }
+            None => Self::DEFAULT_PASSWORD_RESET_URL.to_string(),
+        };
+
+        let trusted_proxies = match Self::var(Self::TRUSTED_PROXIES_VAR) {
+            Some(value) => value.parse().map_err(|_| ConfigError::InvalidValue {
+                variable: Self::TRUSTED_PROXIES_VAR,
+                value: value.clone(),
+                expected: "comma separated ip addresses or networks like 10.0.0.0/8",
+            })?,
+            None => TrustedProxies::default(),
+        };
+
+        Ok(Config {
+            database_url: Self::var(Self::DATABASE_URL_VAR)
+                .unwrap_or_else(|| DEFAULT_DATABASE_URL.to_string()),
+            rate_limit,
+            audit_retention_days,
+            shutdown_timeout: Duration::from_secs(shutdown_timeout_seconds),
+            password_reset_url,
+            trusted_proxies,
+        })
+    }
+
+    // unset and empty variables are treated the same
+    fn var(name: &str) -> Option<String> {
+        std::env::var(name)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+    }
+}
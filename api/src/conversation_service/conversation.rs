@@ -1,7 +1,11 @@
 use super::error::ConversationError;
 use super::message::Message;
+use crate::auth_service::user::{account_state::AccountStatus, PublicUserData, User};
+use crate::server::metrics::METRICS;
+use crate::user_service::{block::UserBlock, contact::Contact, profile::UserProfile};
 use axum::response::Result;
 use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -10,6 +14,20 @@ pub struct Conversation {
     sender_id: Uuid,
     receiver_id: Uuid,
     started_at: sqlx::types::chrono::NaiveDateTime,
+    // `None` while this is a message request that the receiver has not accepted yet
+    accepted_at: Option<sqlx::types::chrono::NaiveDateTime>,
+}
+
+// a conversation as shown in the conversation list, with the profile of the other participant
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ConversationSummary {
+    pub id: Uuid,
+    pub started_at: NaiveDateTime,
+    pub other_user: PublicUserData,
+    // false for message requests, only the user that started the conversation sees those in their list
+    pub accepted: bool,
+    // clients should not notify about new messages in muted conversations
+    pub muted: bool,
 }
 
 impl Conversation {
@@ -38,12 +56,245 @@ impl Conversation {
         Ok(conversations)
     }
 
+    // the inbox, message requests sent to `user_id` are left out until they are accepted
+    pub async fn list_for_user(
+        pool: &PgPool,
+        user_id: Uuid,
+    ) -> Result<Vec<ConversationSummary>, ConversationError> {
+        Conversation::summaries(pool, user_id, false).await
+    }
+
+    // message requests waiting for `user_id` to accept or decline them
+    pub async fn list_requests(
+        pool: &PgPool,
+        user_id: Uuid,
+    ) -> Result<Vec<ConversationSummary>, ConversationError> {
+        Conversation::summaries(pool, user_id, true).await
+    }
+
+    // newest conversations first, the status of users that blocked `user_id` is hidden
+    async fn summaries(
+        pool: &PgPool,
+        user_id: Uuid,
+        requests: bool,
+    ) -> Result<Vec<ConversationSummary>, ConversationError> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                conversations.id,
+                conversations.started_at,
+                users.id AS other_user_id,
+                users.handle,
+                users.display_name,
+                user_avatars.user_id IS NOT NULL AS "has_avatar!",
+                CASE WHEN user_blocks.blocker_id IS NULL THEN users.status_text
+                    ELSE NULL
+                END AS "status_text?",
+                conversations.accepted_at IS NOT NULL AS "accepted!",
+                conversation_mutes.user_id IS NOT NULL AS "muted!"
+            FROM conversations
+            JOIN users ON users.id = CASE
+                WHEN conversations.sender_id = $1 THEN conversations.receiver_id
+                ELSE conversations.sender_id
+            END
+            LEFT JOIN user_avatars ON user_avatars.user_id = users.id
+            LEFT JOIN user_blocks
+                ON user_blocks.blocker_id = users.id AND user_blocks.blocked_id = $1
+            LEFT JOIN conversation_mutes
+                ON conversation_mutes.conversation_id = conversations.id
+                AND conversation_mutes.user_id = $1
+            WHERE CASE
+                WHEN $2 THEN conversations.receiver_id = $1 AND conversations.accepted_at IS NULL
+                ELSE conversations.sender_id = $1
+                    OR (conversations.receiver_id = $1 AND conversations.accepted_at IS NOT NULL)
+            END
+            ORDER BY conversations.started_at DESC
+            "#,
+            user_id,
+            requests,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let conversations = records
+            .into_iter()
+            .map(|rec| ConversationSummary {
+                id: rec.id,
+                started_at: rec.started_at,
+                other_user: PublicUserData {
+                    id: rec.other_user_id,
+                    handle: rec.handle,
+                    display_name: rec.display_name,
+                    avatar_url: rec
+                        .has_avatar
+                        .then(|| UserProfile::avatar_url(rec.other_user_id)),
+                    status_text: rec.status_text,
+                },
+                accepted: rec.accepted,
+                muted: rec.muted,
+            })
+            .collect();
+
+        Ok(conversations)
+    }
+
+    // only the receiver of a pending request can accept it
+    pub async fn accept_request(
+        pool: &PgPool,
+        user_id: Uuid,
+        conversation_id: Uuid,
+    ) -> Result<(), ConversationError> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE conversations SET accepted_at = $3
+            WHERE id = $1 AND receiver_id = $2 AND accepted_at IS NULL
+            "#,
+            conversation_id,
+            user_id,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(pool)
+        .await?;
+
+        match res.rows_affected() {
+            0 => Err(ConversationError::MessageRequestNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    // declining deletes the conversation and its messages, block the sender to stop new requests
+    pub async fn decline_request(
+        pool: &PgPool,
+        user_id: Uuid,
+        conversation_id: Uuid,
+    ) -> Result<(), ConversationError> {
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM conversations
+            WHERE id = $1 AND receiver_id = $2 AND accepted_at IS NULL
+            "#,
+            conversation_id,
+            user_id,
+        )
+        .execute(pool)
+        .await?;
+
+        match res.rows_affected() {
+            0 => Err(ConversationError::MessageRequestNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    // muting only affects `user_id`, the conversation and its messages stay the same
+    pub async fn set_muted(
+        pool: &PgPool,
+        user_id: Uuid,
+        conversation_id: Uuid,
+        muted: bool,
+    ) -> Result<(), ConversationError> {
+        Conversation::other_participant(pool, conversation_id, user_id).await?;
+
+        if muted {
+            sqlx::query!(
+                r#"
+                INSERT INTO conversation_mutes (user_id, conversation_id, created_at)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (user_id, conversation_id) DO NOTHING
+                "#,
+                user_id,
+                conversation_id,
+                sqlx::types::chrono::Utc::now().naive_utc(),
+            )
+            .execute(pool)
+            .await?;
+        } else {
+            sqlx::query!(
+                "DELETE FROM conversation_mutes WHERE user_id = $1 AND conversation_id = $2",
+                user_id,
+                conversation_id,
+            )
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn is_muted(
+        pool: &PgPool,
+        user_id: Uuid,
+        conversation_id: Uuid,
+    ) -> Result<bool, ConversationError> {
+        let rec = sqlx::query!(
+            "SELECT user_id FROM conversation_mutes WHERE user_id = $1 AND conversation_id = $2",
+            user_id,
+            conversation_id,
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(rec.is_some())
+    }
+
+    async fn is_pending_request_for(
+        pool: &PgPool,
+        conversation_id: Uuid,
+        receiver_id: Uuid,
+    ) -> Result<bool, ConversationError> {
+        let rec = sqlx::query!(
+            r#"
+            SELECT id FROM conversations
+            WHERE id = $1 AND receiver_id = $2 AND accepted_at IS NULL
+            "#,
+            conversation_id,
+            receiver_id,
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(rec.is_some())
+    }
+
+    // the id of the other person in the conversation
+    pub async fn other_participant(
+        pool: &PgPool,
+        conversation_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Uuid, ConversationError> {
+        let conversation = sqlx::query!(
+            "SELECT sender_id, receiver_id FROM conversations WHERE id = $1",
+            conversation_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ConversationError::ConversationDoesNotExist)?;
+
+        if conversation.sender_id == user_id {
+            Ok(conversation.receiver_id)
+        } else if conversation.receiver_id == user_id {
+            Ok(conversation.sender_id)
+        } else {
+            Err(ConversationError::NotAParticipant)
+        }
+    }
+
     pub async fn send_message(
         pool: &PgPool,
         sender_id: Uuid,
         conversation_id: Uuid,
         message_content: &str,
     ) -> Result<Uuid, ConversationError> {
+        let receiver_id = Conversation::other_participant(pool, conversation_id, sender_id).await?;
+        Conversation::check_accounts_active(pool, sender_id, receiver_id).await?;
+        if UserBlock::is_blocked_between(pool, sender_id, receiver_id).await? {
+            return Err(ConversationError::Blocked);
+        }
+
+        // the receiver of a message request has to accept it before replying
+        if Conversation::is_pending_request_for(pool, conversation_id, sender_id).await? {
+            return Err(ConversationError::MessageRequestPending);
+        }
+
         let message_id = Uuid::new_v4();
         sqlx::query!(
             r#"
@@ -58,10 +309,62 @@ impl Conversation {
         )
         .execute(pool)
         .await?;
+        METRICS.message_sent();
 
         Ok(message_id)
     }
 
+    // deactivated and suspended users can neither send nor receive messages
+    async fn check_accounts_active(
+        pool: &PgPool,
+        sender_id: Uuid,
+        receiver_id: Uuid,
+    ) -> Result<(), ConversationError> {
+        if !User::is_active(pool, sender_id).await? {
+            return Err(ConversationError::AccountInactive);
+        }
+        match User::account_state(pool, receiver_id).await {
+            Ok(state) if state.status == AccountStatus::Active => {}
+            Ok(_) => return Err(ConversationError::RecipientUnavailable),
+            Err(sqlx::Error::RowNotFound) => return Err(ConversationError::RecipientNotFound),
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_message(
+        pool: &PgPool,
+        message_id: Uuid,
+    ) -> Result<Message, ConversationError> {
+        sqlx::query_as!(
+            Message,
+            r#"
+            SELECT id, conversation_id, content, sent_at, sender_id
+            FROM messages
+            WHERE id = $1
+            "#,
+            message_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ConversationError::MessageDoesNotExist)
+    }
+
+    pub async fn delete_message(
+        executor: impl sqlx::PgExecutor<'_>,
+        message_id: Uuid,
+    ) -> Result<(), ConversationError> {
+        let res = sqlx::query!("DELETE FROM messages WHERE id = $1", message_id)
+            .execute(executor)
+            .await?;
+
+        match res.rows_affected() {
+            0 => Err(ConversationError::MessageDoesNotExist),
+            _ => Ok(()),
+        }
+    }
+
     pub async fn get_all_messages(
         pool: &PgPool,
         conversation_id: Uuid,
@@ -72,7 +375,7 @@ impl Conversation {
             SELECT id, conversation_id, content, sent_at, sender_id
             FROM messages
             WHERE conversation_id = $1
-            ORDER BY sent_at ASC
+            ORDER BY sent_at ASC, id ASC
             "#,
             conversation_id,
         )
@@ -82,6 +385,54 @@ impl Conversation {
         Ok(messages)
     }
 
+    // like `get_all_messages`, but only for the two people in the conversation
+    // with `after` only the messages sent after that time, for clients that poll for new ones
+    // `after_id` is the id of the message at `after`, with it messages sent at the same time are
+    // neither skipped nor returned twice
+    pub async fn get_messages_for_participant(
+        pool: &PgPool,
+        user_id: Uuid,
+        conversation_id: Uuid,
+        after: Option<NaiveDateTime>,
+        after_id: Option<Uuid>,
+    ) -> Result<Vec<Message>, ConversationError> {
+        Conversation::other_participant(pool, conversation_id, user_id).await?;
+        match (after, after_id) {
+            (Some(time), Some(id)) => {
+                Conversation::get_messages_after_message(pool, conversation_id, time, id).await
+            }
+            (Some(time), None) => {
+                Conversation::get_messages_after_time(pool, conversation_id, time).await
+            }
+            (None, _) => Conversation::get_all_messages(pool, conversation_id).await,
+        }
+    }
+
+    // messages are ordered by `(sent_at, id)`, this continues right after the given message
+    pub async fn get_messages_after_message(
+        pool: &PgPool,
+        conversation_id: Uuid,
+        sent_at: NaiveDateTime,
+        message_id: Uuid,
+    ) -> Result<Vec<Message>, ConversationError> {
+        let messages = sqlx::query_as!(
+            Message,
+            r#"
+            SELECT id, conversation_id, content, sent_at, sender_id
+            FROM messages
+            WHERE conversation_id = $1 AND (sent_at, id) > ($2, $3)
+            ORDER BY sent_at ASC, id ASC
+            "#,
+            conversation_id,
+            sent_at,
+            message_id,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(messages)
+    }
+
     pub async fn get_messages_after_time(
         pool: &PgPool,
         conversation_id: Uuid,
@@ -93,7 +444,7 @@ impl Conversation {
             SELECT id, conversation_id, content, sent_at, sender_id
             FROM messages
             WHERE conversation_id = $1 AND sent_at > $2
-            ORDER BY sent_at ASC
+            ORDER BY sent_at ASC, id ASC
             "#,
             conversation_id,
             time,
@@ -103,6 +454,7 @@ impl Conversation {
 
         Ok(messages)
     }
+
     pub async fn start(
         pool: &PgPool,
         sender_id: Uuid,
@@ -113,6 +465,12 @@ impl Conversation {
             return Err(ConversationError::SameSenderAndReceiver);
         }
 
+        Conversation::check_accounts_active(pool, sender_id, receiver_id).await?;
+
+        if UserBlock::is_blocked_between(pool, sender_id, receiver_id).await? {
+            return Err(ConversationError::Blocked);
+        }
+
         // check if there is already a conversation with the reciever and sender
         // if there is return that conversation id
         if let Some(id) = Conversation::pair_exists(pool, sender_id, receiver_id).await? {
@@ -121,23 +479,32 @@ impl Conversation {
             });
         }
 
+        // people that are not in the receivers contacts can only send a message request
+        let started_at = sqlx::types::chrono::Utc::now().naive_utc();
+        let accepted_at = match Contact::is_contact(pool, receiver_id, sender_id).await? {
+            true => Some(started_at),
+            false => None,
+        };
+
         let id = uuid::Uuid::new_v4();
         let conversation = Conversation {
             id,
             sender_id,
             receiver_id,
-            started_at: sqlx::types::chrono::Utc::now().naive_utc(),
+            started_at,
+            accepted_at,
         };
 
         sqlx::query!(
             r#"
-            INSERT INTO conversations (id, sender_id, receiver_id, started_at)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO conversations (id, sender_id, receiver_id, started_at, accepted_at)
+            VALUES ($1, $2, $3, $4, $5)
             "#,
             conversation.id,
             conversation.sender_id,
             conversation.receiver_id,
-            conversation.started_at
+            conversation.started_at,
+            conversation.accepted_at,
         )
         .execute(pool)
         .await?;
@@ -1,13 +1,113 @@
+use crate::server::error::{ApiError, ErrorCode};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
 use derive_more::From;
+use serde_json::json;
 
 #[derive(Debug, From)]
 pub enum ConversationError {
     ConversationDoesNotExist,
+    MessageDoesNotExist,
     SameSenderAndReceiver,
     ConversationAlreadyExists {
         conversation_id: uuid::Uuid,
     },
+    // the user is not one of the two people in the conversation
+    NotAParticipant,
+    // one of the users blocked the other, see `user_service::block`
+    Blocked,
+    // there is no pending message request with this id for the user
+    MessageRequestNotFound,
+    // the receiver tried to reply before accepting the message request
+    MessageRequestPending,
+    // the account of the user is deactivated or suspended
+    AccountInactive,
+    // the other user deactivated their account or was suspended
+    RecipientUnavailable,
+    // there is no account with the id of the receiver
+    RecipientNotFound,
 
     #[from]
     Database(sqlx::Error),
 }
+
+impl IntoResponse for ConversationError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::ConversationDoesNotExist => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::ConversationNotFound,
+                "Conversation not found.",
+            )
+            .into_response(),
+            Self::MessageDoesNotExist => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::MessageNotFound,
+                "Message not found.",
+            )
+            .into_response(),
+            Self::SameSenderAndReceiver => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::SameSenderAndReceiver,
+                "You cannot start a conversation with yourself.",
+            )
+            .into_response(),
+            // the client can open the existing conversation instead
+            Self::ConversationAlreadyExists { conversation_id } => ApiError::new(
+                StatusCode::CONFLICT,
+                ErrorCode::ConversationAlreadyExists,
+                "You already have a conversation with this user.",
+            )
+            .with_details(json!({ "conversation_id": conversation_id }))
+            .into_response(),
+            Self::NotAParticipant => ApiError::new(
+                StatusCode::FORBIDDEN,
+                ErrorCode::NotAParticipant,
+                "You are not part of this conversation.",
+            )
+            .into_response(),
+            Self::Blocked => ApiError::new(
+                StatusCode::FORBIDDEN,
+                ErrorCode::Blocked,
+                "You cannot send messages to this user.",
+            )
+            .into_response(),
+            Self::MessageRequestNotFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::MessageRequestNotFound,
+                "Message request not found.",
+            )
+            .into_response(),
+            Self::MessageRequestPending => ApiError::new(
+                StatusCode::FORBIDDEN,
+                ErrorCode::MessageRequestPending,
+                "Accept the message request before replying.",
+            )
+            .into_response(),
+            Self::AccountInactive => ApiError::new(
+                StatusCode::FORBIDDEN,
+                ErrorCode::AccountInactive,
+                "Your account is not active.",
+            )
+            .into_response(),
+            Self::RecipientUnavailable => ApiError::new(
+                StatusCode::FORBIDDEN,
+                ErrorCode::RecipientUnavailable,
+                "This user is not available.",
+            )
+            .into_response(),
+            Self::RecipientNotFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::UserNotFound,
+                "User not found.",
+            )
+            .into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error in conversations {:?}", e);
+                ApiError::internal().into_response()
+            }
+        }
+    }
+}
@@ -8,7 +8,7 @@ use uuid::Uuid;
 // reciever_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
 // sent_at TIMESTAMP NOT NULL
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, utoipa::ToSchema)]
 pub struct Message {
     pub id: Uuid,
     pub conversation_id: Uuid,
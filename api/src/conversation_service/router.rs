@@ -1,28 +1,338 @@
 use std::str::FromStr;
 
-use super::conversation::Conversation;
-use crate::{auth_service::claims::JwtClaims, server::AppState};
+use super::conversation::{Conversation, ConversationSummary};
+use super::message::Message;
+use crate::{
+    auth_service::claims::{error::ClaimsError, JwtClaims},
+    server::{
+        error::{ApiError, ErrorBody, ErrorCode},
+        extract::{Json, Path, Query},
+        metrics::METRICS,
+        AppState,
+    },
+};
 use axum::{
     extract::State,
     http::{header::AUTHORIZATION, HeaderMap, StatusCode},
     response::IntoResponse,
-    routing::{get, post},
-    Json,
+    routing::{get, post, put},
 };
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
 
-pub fn conversation_routes(state: AppState) -> axum::Router<AppState> {
+pub fn conversation_routes(state: AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        // conversations of the current user, POST starts one
+        .routes(routes!(
+            list_conversations_service,
+            create_conversation_service
+        ))
+        // message requests from users that do not have the current user in their contacts
+        .routes(routes!(list_requests_service))
+        .routes(routes!(list_messages_service, create_message_service))
+        .routes(routes!(accept_request_service))
+        .routes(routes!(decline_request_service))
+        // mutes or unmutes notifications for the current user without leaving the conversation
+        .routes(routes!(
+            mute_conversation_service,
+            unmute_conversation_service
+        ))
+        .with_state(state)
+}
+
+// the routes from before /v1, they take string ids in json bodies, also on GET
+pub fn legacy_conversation_routes(state: AppState) -> axum::Router<AppState> {
     axum::Router::new()
         // post requeset to send a message
         .route("/message", post(send_message_service))
         //post request to create a conversation
         .route("/", post(start_conversation_service))
+        //get request to list the conversations of the current user
+        .route("/", get(list_conversations_service))
         //gets all the messages in the conversation
         .route("/message", get(get_conversation_service))
+        .route("/requests", get(list_requests_service))
+        .route("/{id}/accept", post(accept_request_service))
+        .route("/{id}/decline", post(decline_request_service))
+        .route(
+            "/{id}/mute",
+            put(mute_conversation_service).delete(unmute_conversation_service),
+        )
         .with_state(state)
 }
 
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "conversations",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The conversations of the signed in user", body = Vec<ConversationSummary>),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+    )
+)]
+// returns Vec<ConversationSummary>, every entry has the profile of the other user so the
+// frontend can show a display name and avatar instead of an id
+pub async fn list_conversations_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+) -> impl IntoResponse {
+    match Conversation::list_for_user(&state.pool, claims.user_id).await {
+        Ok(conversations) => (StatusCode::OK, Json(conversations)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/requests",
+    tag = "conversations",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "Message requests sent to the signed in user", body = Vec<ConversationSummary>),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+    )
+)]
+pub async fn list_requests_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+) -> impl IntoResponse {
+    match Conversation::list_requests(&state.pool, claims.user_id).await {
+        Ok(requests) => (StatusCode::OK, Json(requests)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/accept",
+    tag = "conversations",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the conversation")),
+    responses(
+        (status = 200, description = "Message request accepted", body = String),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 404, description = "message_request_not_found", body = ErrorBody),
+    )
+)]
+pub async fn accept_request_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match Conversation::accept_request(&state.pool, claims.user_id, id).await {
+        Ok(()) => (StatusCode::OK, "Message request accepted").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/decline",
+    tag = "conversations",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the conversation")),
+    responses(
+        (status = 200, description = "Message request declined and removed", body = String),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 404, description = "message_request_not_found", body = ErrorBody),
+    )
+)]
+pub async fn decline_request_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match Conversation::decline_request(&state.pool, claims.user_id, id).await {
+        Ok(()) => (StatusCode::OK, "Message request declined").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/{id}/mute",
+    tag = "conversations",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the conversation")),
+    responses(
+        (status = 200, description = "Conversation muted", body = String),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 403, description = "not_a_participant", body = ErrorBody),
+        (status = 404, description = "conversation_not_found", body = ErrorBody),
+    )
+)]
+pub async fn mute_conversation_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    set_muted(&state, claims, id, true).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}/mute",
+    tag = "conversations",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the conversation")),
+    responses(
+        (status = 200, description = "Conversation unmuted", body = String),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 403, description = "not_a_participant", body = ErrorBody),
+        (status = 404, description = "conversation_not_found", body = ErrorBody),
+    )
+)]
+pub async fn unmute_conversation_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    set_muted(&state, claims, id, false).await
+}
+
+async fn set_muted(
+    state: &AppState,
+    claims: JwtClaims,
+    conversation_id: Uuid,
+    muted: bool,
+) -> axum::response::Response {
+    match Conversation::set_muted(&state.pool, claims.user_id, conversation_id, muted).await {
+        Ok(()) if muted => (StatusCode::OK, "Conversation muted").into_response(),
+        Ok(()) => (StatusCode::OK, "Conversation unmuted").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Deserialize, Serialize, utoipa::ToSchema)]
+pub struct NewConversation {
+    // the current user is the sender, see the jwt
+    pub receiver_id: Uuid,
+}
+
+#[derive(Deserialize, Serialize, utoipa::ToSchema)]
+pub struct CreatedConversation {
+    pub conversation_id: Uuid,
+}
+
+#[utoipa::path(
+    post,
+    path = "",
+    tag = "conversations",
+    security(("jwt" = [])),
+    request_body = NewConversation,
+    responses(
+        (status = 201, description = "The conversation was started, for people that do not have the sender in their contacts it is a message request", body = CreatedConversation),
+        (status = 400, description = "same_sender_and_receiver or invalid_body", body = ErrorBody),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 403, description = "blocked, account_inactive or recipient_unavailable", body = ErrorBody),
+        (status = 404, description = "user_not_found", body = ErrorBody),
+        (status = 409, description = "conversation_already_exists, the id is in details.conversation_id", body = ErrorBody),
+    )
+)]
+pub async fn create_conversation_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Json(new_conversation): Json<NewConversation>,
+) -> impl IntoResponse {
+    match Conversation::start(&state.pool, claims.user_id, new_conversation.receiver_id).await {
+        Ok(conversation_id) => (
+            StatusCode::CREATED,
+            Json(CreatedConversation { conversation_id }),
+        )
+            .into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+// query string of GET /conversations/{id}/messages
+#[derive(Serialize, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MessagesQuery {
+    // only the messages sent after this time, the `sent_at` of the last message a client has
+    pub after: Option<NaiveDateTime>,
+    // the `id` of that message, so messages sent at the same time are not skipped, needs `after`
+    pub after_id: Option<Uuid>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}/messages",
+    tag = "conversations",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the conversation"), MessagesQuery),
+    responses(
+        (status = 200, description = "Oldest message first", body = Vec<Message>),
+        (status = 400, description = "invalid_id or invalid_query", body = ErrorBody),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 403, description = "not_a_participant", body = ErrorBody),
+        (status = 404, description = "conversation_not_found", body = ErrorBody),
+    )
+)]
+pub async fn list_messages_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Path(id): Path<Uuid>,
+    Query(query): Query<MessagesQuery>,
+) -> impl IntoResponse {
+    let _poll = METRICS.message_poll();
+    let pool = &state.pool;
+
+    match Conversation::get_messages_for_participant(
+        pool,
+        claims.user_id,
+        id,
+        query.after,
+        query.after_id,
+    )
+    .await
+    {
+        Ok(messages) => (StatusCode::OK, Json(messages)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Deserialize, Serialize, utoipa::ToSchema)]
+pub struct NewMessage {
+    pub content: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/messages",
+    tag = "conversations",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the conversation")),
+    request_body = NewMessage,
+    responses(
+        (status = 201, description = "The sent message", body = Message),
+        (status = 400, description = "invalid_id or invalid_body", body = ErrorBody),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 403, description = "not_a_participant, blocked, message_request_pending, account_inactive or recipient_unavailable", body = ErrorBody),
+        (status = 404, description = "conversation_not_found", body = ErrorBody),
+    )
+)]
+pub async fn create_message_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Path(id): Path<Uuid>,
+    Json(new_message): Json<NewMessage>,
+) -> impl IntoResponse {
+    let pool = &state.pool;
+
+    match Conversation::send_message(pool, claims.user_id, id, &new_message.content).await {
+        Ok(message_id) => match Conversation::get_message(pool, message_id).await {
+            Ok(message) => (StatusCode::CREATED, Json(message)).into_response(),
+            Err(e) => e.into_response(),
+        },
+        Err(e) => e.into_response(),
+    }
+}
+
+// -- legacy routes, see `legacy_conversation_routes`
+
 #[derive(Deserialize, Serialize)]
 pub struct ConversationRequest {
     // this is the id of the user that the current logged in user is starting a conversation with
@@ -46,7 +356,7 @@ pub async fn start_conversation_service(
     let response = match authorization_header {
         Some(token) => {
             // Decode the JWT token
-            match JwtClaims::decode(&token.to_string()) {
+            match JwtClaims::authenticate(&state.pool, &token.to_string()).await {
                 Ok(claims) => {
                     let sender_id = claims.user_id;
                     let pool = &state.pool;
@@ -60,32 +370,33 @@ pub async fn start_conversation_service(
                                     // Return the conversation ID to the frontend on success
                                     (StatusCode::OK, conversation_id.to_string()).into_response()
                                 }
-                                Err(e) => {
-                                    tracing::error!("could not start conversation: {:?}", e);
-                                    (
-                                        StatusCode::INTERNAL_SERVER_ERROR,
-                                        "Could not start conversation",
-                                    )
-                                        .into_response()
-                                }
+                                // 409 with the id of the existing conversation so the client can open it
+                                Err(e) => e.into_response(),
                             }
                         }
                         _ => {
                             // Return an error if the sender or receiver ID is invalid
-                            (StatusCode::BAD_REQUEST, "Invalid sender||receiver id").into_response()
+                            ApiError::new(
+                                StatusCode::BAD_REQUEST,
+                                ErrorCode::InvalidId,
+                                "Invalid receiver id",
+                            )
+                            .into_response()
                         }
                     }
                 }
                 Err(e) => {
-                    tracing::error!("could not decode jwt token in start conversation: {:?}", e);
-                    // Return an error if the JWT token is invalid
-                    (StatusCode::BAD_REQUEST, "Invalid jwt").into_response()
+                    tracing::debug!(
+                        "could not authenticate jwt token in start conversation: {:?}",
+                        e
+                    );
+                    e.into_response()
                 }
             }
         }
         None => {
             // Return an error if the authorization header is missing
-            (StatusCode::UNAUTHORIZED, "No authorization header").into_response()
+            ClaimsError::MissingToken.into_response()
         }
     };
 
@@ -98,10 +409,12 @@ pub struct GetConversationRequest {
 }
 
 // For the frontend:
-// Send a POST request to /conversation with a JSON body containing the conversation_id.
+// Deprecated, GET /v1/conversations/{id}/messages returns the messages as JSON instead of a JSON encoded string.
+// Send a GET request to /conversation/message with a JSON body containing the conversation_id.
 // Include the JWT in the Authorization header obtained during sign-in.
 // On success, you'll receive a JSON representation of the conversation Vec<Message> see the message.rs to view the data structure.
-// Handle 400 errors for invalid conversation IDs or JWTs, and 401 for missing authorization headers.
+// Handle 400 errors for invalid conversation IDs, 401 for missing or invalid JWTs,
+// 403 if the user is not part of the conversation and 404 if it does not exist.
 // 500 errors indicate server-side issues.
 pub async fn get_conversation_service(
     State(state): State<AppState>,
@@ -116,8 +429,8 @@ pub async fn get_conversation_service(
     let response = match authorization_header {
         Some(token) => {
             // Decode the JWT token
-            match JwtClaims::decode(&token.to_string()) {
-                Ok(_) => {
+            match JwtClaims::authenticate(&state.pool, &token.to_string()).await {
+                Ok(claims) => {
                     // Get the database pool from the application state
                     let pool = &state.pool;
 
@@ -125,7 +438,15 @@ pub async fn get_conversation_service(
                     match Uuid::from_str(&conversation_request.conversation_id) {
                         Ok(conversation_id) => {
                             // Get the conversation from the database
-                            match Conversation::get_all_messages(pool, conversation_id).await {
+                            match Conversation::get_messages_for_participant(
+                                pool,
+                                claims.user_id,
+                                conversation_id,
+                                None,
+                                None,
+                            )
+                            .await
+                            {
                                 Ok(messages) => {
                                     // Return the conversation to the client
                                     match serde_json::to_string(&messages) {
@@ -137,41 +458,37 @@ pub async fn get_conversation_service(
                                                 "could not serialize conversation: {:?}",
                                                 e
                                             );
-                                            (
-                                                StatusCode::INTERNAL_SERVER_ERROR,
-                                                "Could not serialize conversation",
-                                            )
-                                                .into_response()
+                                            ApiError::internal().into_response()
                                         }
                                     }
                                 }
-                                Err(e) => {
-                                    // Log the error and return an internal server error response
-                                    tracing::error!("could not get conversation: {:?}", e);
-                                    (
-                                        StatusCode::INTERNAL_SERVER_ERROR,
-                                        "Could not get conversation",
-                                    )
-                                        .into_response()
-                                }
+                                Err(e) => e.into_response(),
                             }
                         }
                         Err(_) => {
                             // Return a bad request response if the conversation ID is invalid
-                            (StatusCode::BAD_REQUEST, "Invalid conversation id").into_response()
+                            ApiError::new(
+                                StatusCode::BAD_REQUEST,
+                                ErrorCode::InvalidId,
+                                "Invalid conversation id",
+                            )
+                            .into_response()
                         }
                     }
                 }
                 Err(e) => {
                     // Log the error and return a bad request response if the JWT is invalid
-                    tracing::error!("could not decode jwt token in get conversation: {:?}", e);
-                    (StatusCode::BAD_REQUEST, "Invalid jwt").into_response()
+                    tracing::debug!(
+                        "could not authenticate jwt token in get conversation: {:?}",
+                        e
+                    );
+                    e.into_response()
                 }
             }
         }
         None => {
             // Return an unauthorized response if the authorization header is missing
-            (StatusCode::UNAUTHORIZED, "No authorization header").into_response()
+            ClaimsError::MissingToken.into_response()
         }
     };
 
@@ -199,7 +516,7 @@ pub async fn send_message_service(
     let response = match authorization_header {
         Some(token) => {
             // Decode the JWT token
-            match JwtClaims::decode(&token.to_string()) {
+            match JwtClaims::authenticate(&state.pool, &token.to_string()).await {
                 Ok(claims) => {
                     let sender_id = claims.user_id;
                     let pool = &state.pool;
@@ -217,30 +534,29 @@ pub async fn send_message_service(
                             .await
                             {
                                 Ok(_) => (StatusCode::OK, "Message sent").into_response(),
-                                Err(e) => {
-                                    tracing::error!("could not send message: {:?}", e);
-                                    (StatusCode::INTERNAL_SERVER_ERROR, "Could not send message")
-                                        .into_response()
-                                }
+                                Err(e) => e.into_response(),
                             }
                         }
                         _ => {
                             // Return an error if the sender or receiver ID is invalid
-                            (StatusCode::BAD_REQUEST, "Invalid sender||conversation id")
-                                .into_response()
+                            ApiError::new(
+                                StatusCode::BAD_REQUEST,
+                                ErrorCode::InvalidId,
+                                "Invalid conversation id",
+                            )
+                            .into_response()
                         }
                     }
                 }
                 Err(e) => {
-                    tracing::error!("could not decode jwt token in send message: {:?}", e);
-                    // Return an error if the JWT token is invalid
-                    (StatusCode::BAD_REQUEST, "Invalid jwt").into_response()
+                    tracing::debug!("could not authenticate jwt token in send message: {:?}", e);
+                    e.into_response()
                 }
             }
         }
         None => {
             // Return an error if the authorization header is missing
-            (StatusCode::UNAUTHORIZED, "No authorization header").into_response()
+            ClaimsError::MissingToken.into_response()
         }
     };
 
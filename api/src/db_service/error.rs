@@ -0,0 +1,27 @@
+use derive_more::From;
+use sqlx::migrate::MigrateError;
+
+#[derive(Debug, From)]
+pub enum MigrationError {
+    // versions applied to the database that are not embedded in this binary,
+    // a newer release already migrated it
+    SchemaAhead(Vec<i64>),
+
+    #[from]
+    Migrate(MigrateError),
+}
+
+impl core::fmt::Display for MigrationError {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        match self {
+            Self::SchemaAhead(versions) => write!(
+                fmt,
+                "the database has migrations this binary does not know: {:?}",
+                versions
+            ),
+            Self::Migrate(e) => write!(fmt, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
@@ -1,7 +1,14 @@
+pub mod admin_service;
+pub mod audit_service;
 pub mod auth_service;
+pub mod config;
 pub mod conversation_service;
 pub mod db_service;
+pub mod mail_service;
+pub mod moderation_service;
+pub mod ops;
 pub mod server;
+pub mod user_service;
 
 use derive_more::From;
 use server::ServerError;
@@ -28,6 +35,9 @@ pub enum AppError {
 
     #[from]
     Conversation(conversation_service::error::ConversationError),
+
+    #[from]
+    Profile(user_service::error::ProfileError),
 }
 
 // Note: Implement Display as debug, for Web and app error, as anyway those errors will need to be streamed as JSON probably
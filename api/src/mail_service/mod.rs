@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+use derive_more::From;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, From)]
+pub enum MailError {
+    Transport(String),
+}
+
+// anything that can deliver an email, the server only depends on this trait
+// so that a real smtp/api provider can be swapped in without touching the services
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: MailMessage) -> Result<(), MailError>;
+}
+
+// default mailer for local development, the email is written to the logs instead of being sent
+#[derive(Debug, Default, Clone)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, message: MailMessage) -> Result<(), MailError> {
+        tracing::info!(
+            "email to: {} subject: {}\n{}",
+            message.to,
+            message.subject,
+            message.body
+        );
+        Ok(())
+    }
+}
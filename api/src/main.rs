@@ -1,6 +1,14 @@
-use api::server::run_server;
+use api::ops::{run, Cli};
+use clap::Parser;
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() {
-    run_server().await.expect("Error starting server");
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
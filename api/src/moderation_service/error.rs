@@ -0,0 +1,89 @@
+use crate::server::error::{ApiError, ErrorCode};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use derive_more::From;
+use serde_json::json;
+
+#[derive(Debug, From)]
+pub enum ModerationError {
+    // a report needs a message or a user
+    MissingTarget,
+    CannotReportSelf,
+    // also returned for messages in conversations the reporter is not part of
+    MessageNotFound,
+    UserNotFound,
+    ReportNotFound,
+    DetailsTooLong {
+        max_length: usize,
+        actual_length: usize,
+    },
+    // the action does not fit the report, e.g. deleting the message of a user report
+    InvalidAction {
+        reason: &'static str,
+    },
+
+    #[from]
+    Database(sqlx::Error),
+}
+
+impl IntoResponse for ModerationError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::MissingTarget => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::MissingReportTarget,
+                "A report needs a message_id or a user_id.",
+            )
+            .into_response(),
+            Self::CannotReportSelf => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::CannotReportSelf,
+                "You cannot report yourself.",
+            )
+            .into_response(),
+            Self::MessageNotFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::MessageNotFound,
+                "Message not found.",
+            )
+            .into_response(),
+            Self::UserNotFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::UserNotFound,
+                "User not found.",
+            )
+            .into_response(),
+            Self::ReportNotFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::ReportNotFound,
+                "Report not found.",
+            )
+            .into_response(),
+            Self::DetailsTooLong {
+                max_length,
+                actual_length,
+            } => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::DetailsTooLong,
+                format!(
+                    "Details must be at most {} characters long. You provided {} characters.",
+                    max_length, actual_length
+                ),
+            )
+            .with_details(json!({ "max_length": max_length, "actual_length": actual_length }))
+            .into_response(),
+            Self::InvalidAction { reason } => ApiError::new(
+                StatusCode::CONFLICT,
+                ErrorCode::InvalidModerationAction,
+                reason,
+            )
+            .into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error in moderation {:?}", e);
+                ApiError::internal().into_response()
+            }
+        }
+    }
+}
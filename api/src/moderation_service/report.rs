@@ -0,0 +1,367 @@
+use super::error::ModerationError;
+use crate::auth_service::user::User;
+use crate::conversation_service::{conversation::Conversation, error::ConversationError};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    HateSpeech,
+    Impersonation,
+    Other,
+}
+
+// open -> triaged -> resolved or dismissed
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum ReportStatus {
+    Open,
+    Triaged,
+    Resolved,
+    Dismissed,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum ModerationActionKind {
+    Triage,
+    Resolve,
+    Dismiss,
+    // deletes the reported message and resolves the report
+    DeleteMessage,
+    // suspends the reported user and resolves the report
+    SuspendUser,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Report {
+    pub id: Uuid,
+    pub reporter_id: Option<Uuid>,
+    pub reported_user_id: Option<Uuid>,
+    pub message_id: Option<Uuid>,
+    pub message_content: Option<String>,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+    pub status: ReportStatus,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+// body of POST /reports, when a message is reported the user is the sender of the message
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct NewReport {
+    pub message_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ModerationRequest {
+    pub action: ModerationActionKind,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ModerationAction {
+    pub id: Uuid,
+    pub report_id: Option<Uuid>,
+    pub moderator_id: Option<Uuid>,
+    pub action: ModerationActionKind,
+    pub target_user_id: Option<Uuid>,
+    pub target_message_id: Option<Uuid>,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+// a report together with everything moderators did with it, oldest action first
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ReportDetails {
+    #[serde(flatten)]
+    pub report: Report,
+    pub actions: Vec<ModerationAction>,
+}
+
+// query string of GET /moderation/reports
+#[derive(Debug, Default, Serialize, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReportFilter {
+    pub status: Option<ReportStatus>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl Report {
+    pub const MAX_DETAILS_LENGTH: usize = 1000;
+    pub const DEFAULT_LIMIT: i64 = 50;
+    pub const MAX_LIMIT: i64 = 200;
+
+    // anyone can report a user, messages can only be reported by the people in the conversation
+    pub async fn create(
+        pool: &PgPool,
+        reporter_id: Uuid,
+        new_report: NewReport,
+    ) -> Result<Report, ModerationError> {
+        let details = new_report
+            .details
+            .map(|details| details.trim().to_string())
+            .filter(|details| !details.is_empty());
+        if let Some(details) = &details {
+            let length = details.chars().count();
+            if length > Self::MAX_DETAILS_LENGTH {
+                return Err(ModerationError::DetailsTooLong {
+                    max_length: Self::MAX_DETAILS_LENGTH,
+                    actual_length: length,
+                });
+            }
+        }
+
+        let (reported_user_id, message_id, message_content) =
+            match (new_report.message_id, new_report.user_id) {
+                (Some(message_id), _) => {
+                    let message = Conversation::get_message(pool, message_id)
+                        .await
+                        .map_err(Self::message_error)?;
+                    Conversation::other_participant(pool, message.conversation_id, reporter_id)
+                        .await
+                        .map_err(Self::message_error)?;
+
+                    (message.sender_id, Some(message.id), Some(message.content))
+                }
+                (None, Some(user_id)) => {
+                    let user = match User::get_user_by_id(pool, user_id).await {
+                        Ok(user) => user,
+                        Err(sqlx::Error::RowNotFound) => return Err(ModerationError::UserNotFound),
+                        Err(e) => return Err(e.into()),
+                    };
+
+                    (user.id, None, None)
+                }
+                (None, None) => return Err(ModerationError::MissingTarget),
+            };
+
+        if reported_user_id == reporter_id {
+            return Err(ModerationError::CannotReportSelf);
+        }
+
+        let now = sqlx::types::chrono::Utc::now().naive_utc();
+        let report = sqlx::query_as!(
+            Report,
+            r#"
+            INSERT INTO reports (
+                id, reporter_id, reported_user_id, message_id, message_content,
+                reason, details, status, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, 'open', $8, $8)
+            RETURNING
+                id, reporter_id, reported_user_id, message_id, message_content,
+                reason AS "reason: ReportReason", details,
+                status AS "status: ReportStatus", created_at, updated_at
+            "#,
+            Uuid::new_v4(),
+            reporter_id,
+            reported_user_id,
+            message_id,
+            message_content,
+            new_report.reason as ReportReason,
+            details,
+            now,
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(report)
+    }
+
+    // oldest reports first so the queue is worked through in order
+    pub async fn list(
+        pool: &PgPool,
+        filter: &ReportFilter,
+    ) -> Result<Vec<Report>, ModerationError> {
+        let limit = filter
+            .limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT);
+        let offset = filter.offset.unwrap_or(0).max(0);
+
+        let reports = sqlx::query_as!(
+            Report,
+            r#"
+            SELECT
+                id, reporter_id, reported_user_id, message_id, message_content,
+                reason AS "reason: ReportReason", details,
+                status AS "status: ReportStatus", created_at, updated_at
+            FROM reports
+            WHERE $1::TEXT IS NULL OR status = $1
+            ORDER BY created_at ASC, id
+            LIMIT $2 OFFSET $3
+            "#,
+            filter.status as Option<ReportStatus>,
+            limit,
+            offset,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(reports)
+    }
+
+    pub async fn get(pool: &PgPool, id: Uuid) -> Result<ReportDetails, ModerationError> {
+        let report = sqlx::query_as!(
+            Report,
+            r#"
+            SELECT
+                id, reporter_id, reported_user_id, message_id, message_content,
+                reason AS "reason: ReportReason", details,
+                status AS "status: ReportStatus", created_at, updated_at
+            FROM reports
+            WHERE id = $1
+            "#,
+            id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ModerationError::ReportNotFound)?;
+
+        let actions = sqlx::query_as!(
+            ModerationAction,
+            r#"
+            SELECT
+                id, report_id, moderator_id, action AS "action: ModerationActionKind",
+                target_user_id, target_message_id, note, created_at
+            FROM moderation_actions
+            WHERE report_id = $1
+            ORDER BY created_at ASC, id
+            "#,
+            id,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(ReportDetails { report, actions })
+    }
+
+    // applies the action and records it in `moderation_actions` in the same transaction
+    pub async fn moderate(
+        pool: &PgPool,
+        moderator_id: Uuid,
+        report_id: Uuid,
+        request: ModerationRequest,
+    ) -> Result<ReportDetails, ModerationError> {
+        let mut tx = pool.begin().await?;
+
+        let report = sqlx::query!(
+            r#"
+            SELECT reported_user_id, message_id, status AS "status: ReportStatus"
+            FROM reports
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            report_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(ModerationError::ReportNotFound)?;
+
+        let note = request
+            .note
+            .map(|note| note.trim().to_string())
+            .filter(|note| !note.is_empty());
+
+        let (status, target_user_id, target_message_id) = match request.action {
+            ModerationActionKind::Triage => {
+                if report.status != ReportStatus::Open {
+                    return Err(ModerationError::InvalidAction {
+                        reason: "Only open reports can be triaged.",
+                    });
+                }
+                (ReportStatus::Triaged, None, None)
+            }
+            ModerationActionKind::Resolve => (ReportStatus::Resolved, None, None),
+            ModerationActionKind::Dismiss => (ReportStatus::Dismissed, None, None),
+            ModerationActionKind::DeleteMessage => {
+                let message_id = report.message_id.ok_or(ModerationError::InvalidAction {
+                    reason: "This report has no message to delete.",
+                })?;
+                match Conversation::delete_message(&mut *tx, message_id).await {
+                    Ok(()) => {}
+                    Err(ConversationError::MessageDoesNotExist) => {
+                        return Err(ModerationError::MessageNotFound)
+                    }
+                    Err(ConversationError::Database(e)) => return Err(e.into()),
+                    Err(e) => {
+                        tracing::error!("unexpected error deleting reported message: {:?}", e);
+                        return Err(ModerationError::MessageNotFound);
+                    }
+                }
+                (ReportStatus::Resolved, None, Some(message_id))
+            }
+            ModerationActionKind::SuspendUser => {
+                let user_id = report
+                    .reported_user_id
+                    .ok_or(ModerationError::InvalidAction {
+                        reason: "The reported user no longer exists.",
+                    })?;
+                let reason = note.as_deref().unwrap_or("Suspended after a report.");
+                User::suspend(&mut tx, user_id, reason, None).await?;
+                (ReportStatus::Resolved, Some(user_id), None)
+            }
+        };
+
+        let now = sqlx::types::chrono::Utc::now().naive_utc();
+
+        sqlx::query!(
+            "UPDATE reports SET status = $2, updated_at = $3 WHERE id = $1",
+            report_id,
+            status as ReportStatus,
+            now,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO moderation_actions (
+                id, report_id, moderator_id, action, target_user_id, target_message_id, note, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            Uuid::new_v4(),
+            report_id,
+            moderator_id,
+            request.action as ModerationActionKind,
+            target_user_id,
+            target_message_id,
+            note,
+            now,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Self::get(pool, report_id).await
+    }
+
+    // the reporter cannot tell a message that does not exist from one in someone elses conversation
+    fn message_error(e: ConversationError) -> ModerationError {
+        match e {
+            ConversationError::Database(e) => ModerationError::Database(e),
+            _ => ModerationError::MessageNotFound,
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use super::report::{ModerationRequest, NewReport, Report, ReportDetails, ReportFilter};
+use crate::{
+    audit_service::{AuditContext, AuditEventKind, AuditLog, NewAuditEvent},
+    auth_service::claims::{require_admin, AdminClaims, JwtClaims},
+    server::{
+        error::ErrorBody,
+        extract::{Json, Path, Query},
+        AppState,
+    },
+};
+use axum::{extract::State, http::StatusCode, middleware, response::IntoResponse, Extension};
+use serde_json::json;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+// used by every signed in user to report messages and users
+pub fn report_routes(state: AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(create_report_service))
+        .with_state(state)
+}
+
+// the moderation queue, every route requires an admin, the check is a route layer like in `admin_routes`
+pub fn moderation_routes(state: AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list_reports_service))
+        .routes(routes!(get_report_service))
+        .routes(routes!(moderate_report_service))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    post,
+    path = "",
+    tag = "moderation",
+    security(("jwt" = [])),
+    request_body = NewReport,
+    responses(
+        (status = 201, body = Report),
+        (status = 400, description = "missing_report_target, cannot_report_self or details_too_long", body = ErrorBody),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 404, description = "user_not_found, or message_not_found also for messages of other conversations", body = ErrorBody),
+    )
+)]
+pub async fn create_report_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Json(new_report): Json<NewReport>,
+) -> impl IntoResponse {
+    match Report::create(&state.pool, claims.user_id, new_report).await {
+        Ok(report) => (StatusCode::CREATED, Json(report)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports",
+    tag = "moderation",
+    security(("jwt" = [])),
+    params(ReportFilter),
+    responses(
+        (status = 200, description = "Oldest reports first", body = Vec<Report>),
+        (status = 401, description = "missing_token, invalid_token, token_expired or session_revoked", body = ErrorBody),
+        (status = 403, description = "forbidden", body = ErrorBody),
+    )
+)]
+pub async fn list_reports_service(
+    State(state): State<AppState>,
+    Query(filter): Query<ReportFilter>,
+) -> impl IntoResponse {
+    match Report::list(&state.pool, &filter).await {
+        Ok(reports) => (StatusCode::OK, Json(reports)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/{id}",
+    tag = "moderation",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the report")),
+    responses(
+        (status = 200, body = ReportDetails),
+        (status = 401, description = "missing_token, invalid_token, token_expired or session_revoked", body = ErrorBody),
+        (status = 403, description = "forbidden", body = ErrorBody),
+        (status = 404, description = "report_not_found", body = ErrorBody),
+    )
+)]
+pub async fn get_report_service(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match Report::get(&state.pool, id).await {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/reports/{id}/actions",
+    tag = "moderation",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the report")),
+    request_body = ModerationRequest,
+    responses(
+        (status = 200, body = ReportDetails),
+        (status = 401, description = "missing_token, invalid_token, token_expired or session_revoked", body = ErrorBody),
+        (status = 403, description = "forbidden", body = ErrorBody),
+        (status = 404, description = "report_not_found", body = ErrorBody),
+        (status = 409, description = "invalid_moderation_action", body = ErrorBody),
+    )
+)]
+// triage, resolve or dismiss a report, or delete the reported message or suspend the reported user
+pub async fn moderate_report_service(
+    State(state): State<AppState>,
+    Extension(AdminClaims(claims)): Extension<AdminClaims>,
+    context: AuditContext,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ModerationRequest>,
+) -> impl IntoResponse {
+    let action = request.action;
+    match Report::moderate(&state.pool, claims.user_id, id, request).await {
+        Ok(report) => {
+            let target_user_id = report
+                .actions
+                .last()
+                .and_then(|moderation_action| moderation_action.target_user_id);
+            let event = NewAuditEvent::new(AuditEventKind::AdminAction, &context)
+                .user(target_user_id)
+                .actor(claims.user_id)
+                .details(json!({ "action": "moderate_report", "report_id": id, "moderation_action": action }));
+            AuditLog::record(&state.pool, event).await;
+
+            (StatusCode::OK, Json(report)).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
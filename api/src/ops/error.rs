@@ -0,0 +1,61 @@
+use crate::{
+    admin_service::error::AdminError,
+    auth_service::{password_reset::error::PasswordResetError, user::error::SignUpError},
+    config::error::ConfigError,
+    db_service::error::MigrationError,
+    server::ServerError,
+};
+use derive_more::From;
+
+#[derive(Debug, From)]
+pub enum OpsError {
+    // there is no account with this email
+    UserNotFound(String),
+
+    #[from]
+    Config(ConfigError),
+
+    #[from]
+    Database(sqlx::Error),
+
+    #[from]
+    Migration(MigrationError),
+
+    #[from]
+    Server(ServerError),
+
+    #[from]
+    SignUp(SignUpError),
+
+    #[from]
+    PasswordReset(PasswordResetError),
+
+    #[from]
+    Admin(AdminError),
+
+    #[from]
+    Io(std::io::Error),
+
+    #[from]
+    Json(serde_json::Error),
+}
+
+// printed to stderr by `main`
+impl core::fmt::Display for OpsError {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        match self {
+            Self::UserNotFound(email) => {
+                write!(fmt, "there is no account with the email {}", email)
+            }
+            Self::Config(e) => write!(fmt, "{}", e),
+            Self::Migration(e) => write!(fmt, "migration failed: {}", e),
+            Self::SignUp(e) => write!(fmt, "could not create the account: {:?}", e),
+            Self::PasswordReset(PasswordResetError::InvalidPassword(e)) => {
+                write!(fmt, "the password is not strong enough: {:?}", e)
+            }
+            e => write!(fmt, "{e:?}"),
+        }
+    }
+}
+
+impl std::error::Error for OpsError {}
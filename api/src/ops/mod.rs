@@ -0,0 +1,219 @@
+pub mod error;
+
+use crate::{
+    admin_service::Admin,
+    audit_service::{AuditContext, AuditEventKind, AuditLog, NewAuditEvent},
+    auth_service::{
+        mfa::Totp,
+        password_reset::PasswordReset,
+        session::Session,
+        signin_throttle::SignInThrottle,
+        user::{Role, User},
+    },
+    config::Config,
+    db_service::{self, run_migrations},
+    mail_service::LogMailer,
+    server::{init_tracing, rate_limit::postgres::PostgresRateLimitBackend, run_server},
+};
+use clap::{Parser, Subcommand};
+use error::OpsError;
+use serde::Serialize;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// The chat api server, the other commands are for operators and use the same configuration
+#[derive(Parser)]
+#[command(name = "api")]
+pub struct Cli {
+    /// Refuse to start when the database has migrations this binary does not know,
+    /// without it the server only warns so old instances keep running during a deploy
+    #[arg(long, global = true)]
+    pub check_migrations: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Apply the pending migrations and start the server, this is the default
+    Serve,
+    /// Apply the embedded migrations that are not applied yet
+    Migrate,
+    /// Create an admin account, or make an existing account an admin
+    CreateAdmin {
+        email: String,
+        /// Asked for when it is not set
+        #[arg(long, env = "ADMIN_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+    },
+    /// Sign a user out everywhere and email them a password reset link
+    ResetPassword {
+        email: String,
+        /// Set a new password instead of sending a link, it is asked for unless NEW_PASSWORD is set
+        #[arg(long)]
+        set: bool,
+    },
+    /// Delete expired sessions, reset tokens, signin lockouts, 2fa challenges, rate limit buckets and audit events
+    PurgeExpired,
+    /// Print the counts of the admin dashboard as json
+    Stats,
+}
+
+pub async fn run(cli: Cli) -> Result<(), OpsError> {
+    init_tracing();
+    let config = Config::from_env()?;
+    let pool = db_service::connect(&config.database_url).await?;
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            for migration in run_migrations(&pool, !cli.check_migrations).await? {
+                tracing::info!(
+                    "applied migration {} {}",
+                    migration.version,
+                    migration.description
+                );
+            }
+            run_server(config, pool).await?
+        }
+        Command::Migrate => {
+            let applied = run_migrations(&pool, false).await?;
+            if applied.is_empty() {
+                println!("no pending migrations");
+            }
+            for migration in applied {
+                println!("applied {} {}", migration.version, migration.description);
+            }
+        }
+        Command::CreateAdmin { email, password } => {
+            // an existing account keeps its password, so it is only asked for a new account
+            let account = match promote_admin(&pool, &email).await? {
+                Some(user_id) => AdminAccount::Promoted(user_id),
+                None => {
+                    let password = match password {
+                        Some(password) => password,
+                        None => rpassword::prompt_password("Password: ")?,
+                    };
+                    create_admin(&pool, &email, &password).await?
+                }
+            };
+            match account {
+                AdminAccount::Created(id) => println!("created admin {} ({})", email, id),
+                AdminAccount::Promoted(id) => println!(
+                    "{} ({}) already had an account and is now an admin, its password was not changed",
+                    email, id
+                ),
+            }
+        }
+        Command::ResetPassword { email, set } => {
+            let user = find_user(&pool, &email).await?;
+            if set {
+                let password = match std::env::var("NEW_PASSWORD") {
+                    Ok(password) => password,
+                    Err(_) => rpassword::prompt_password("New password: ")?,
+                };
+                PasswordReset::set_password(&pool, user.id, &password).await?;
+                record_admin_action(&pool, user.id, "set_password").await;
+                println!("password of {} changed, every session is signed out", email);
+            } else {
+                PasswordReset::force(&pool, &LogMailer, &config.password_reset_url, user.id)
+                    .await?;
+                record_admin_action(&pool, user.id, "force_password_reset").await;
+                println!(
+                    "{} is signed out everywhere and was sent a reset link",
+                    email
+                );
+            }
+        }
+        Command::PurgeExpired => {
+            let purged = purge_expired(&pool, &config).await?;
+            println!("{}", serde_json::to_string_pretty(&purged)?);
+        }
+        Command::Stats => {
+            let stats = Admin::stats(&pool).await?;
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AdminAccount {
+    Created(Uuid),
+    // the account already existed, its password was not changed
+    Promoted(Uuid),
+}
+
+pub async fn create_admin(
+    pool: &PgPool,
+    email: &str,
+    password: &str,
+) -> Result<AdminAccount, OpsError> {
+    if let Some(user_id) = promote_admin(pool, email).await? {
+        return Ok(AdminAccount::Promoted(user_id));
+    }
+
+    User::signup(pool, email, password).await?;
+    let user = User::get_user_by_email(pool, email).await?;
+    // signup signs the user in, nobody needs that session
+    Session::revoke_all_for_user(pool, user.id).await?;
+    User::set_role(pool, user.id, Role::Admin).await?;
+    record_admin_action(pool, user.id, "create_admin").await;
+
+    Ok(AdminAccount::Created(user.id))
+}
+
+// makes an existing account an admin, returns `None` when there is no account with the email
+pub async fn promote_admin(pool: &PgPool, email: &str) -> Result<Option<Uuid>, OpsError> {
+    let user = match User::get_user_by_email(pool, email).await {
+        Ok(user) => user,
+        Err(sqlx::Error::RowNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    User::set_role(pool, user.id, Role::Admin).await?;
+    record_admin_action(pool, user.id, "create_admin").await;
+
+    Ok(Some(user.id))
+}
+
+// how many rows `purge_expired` deleted from each table
+#[derive(Debug, Default, Serialize)]
+pub struct PurgedRows {
+    pub sessions: u64,
+    pub password_reset_tokens: u64,
+    pub signin_failures: u64,
+    pub mfa_challenges: u64,
+    pub rate_limit_buckets: u64,
+    pub audit_events: u64,
+}
+
+// the server only purges audit events by itself, the rest only grows without this
+pub async fn purge_expired(pool: &PgPool, config: &Config) -> Result<PurgedRows, OpsError> {
+    Ok(PurgedRows {
+        sessions: Session::purge_expired(pool).await?,
+        password_reset_tokens: PasswordReset::purge_expired(pool).await?,
+        signin_failures: SignInThrottle::purge_expired(pool).await?,
+        mfa_challenges: Totp::purge_expired_challenges(pool).await?,
+        rate_limit_buckets: PostgresRateLimitBackend::purge_expired(pool).await?,
+        audit_events: AuditLog::purge_older_than(pool, config.audit_retention_days).await?,
+    })
+}
+
+async fn find_user(pool: &PgPool, email: &str) -> Result<User, OpsError> {
+    match User::get_user_by_email(pool, email).await {
+        Ok(user) => Ok(user),
+        Err(sqlx::Error::RowNotFound) => Err(OpsError::UserNotFound(email.to_string())),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// commands run by operators show up in the audit log like the actions of admins
+async fn record_admin_action(pool: &PgPool, user_id: Uuid, action: &str) {
+    let event = NewAuditEvent::new(AuditEventKind::AdminAction, &AuditContext::default())
+        .user(user_id)
+        .details(json!({ "action": action, "via": "cli" }));
+    AuditLog::record(pool, event).await;
+}
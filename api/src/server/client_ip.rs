@@ -0,0 +1,135 @@
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+use ipnet::IpNet;
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+};
+
+// the ip address of the client, used for throttling, rate limiting and the audit log
+// it is the address of the connection unless that is a trusted proxy, then it is read from the
+// `Forwarded` or `X-Forwarded-For` header the proxy added
+// it is `None` when the router is not served with `into_make_service_with_connect_info` (e.g. in tests)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+// the load balancers and reverse proxies in front of the api, set with `TRUSTED_PROXIES`
+// `app` adds it to the request extensions, without it the forwarding headers are ignored
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Arc<Vec<IpNet>>);
+
+impl TrustedProxies {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(&ip))
+    }
+
+    // the addresses in the forwarding headers are checked from the nearest hop on, the first one
+    // that is not a trusted proxy is the client. everything before it could have been sent by
+    // the client itself, so it is not used
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.contains(peer) {
+            return peer;
+        }
+
+        let mut client = peer;
+        for hop in forwarded_for(headers).into_iter().rev() {
+            match hop {
+                Some(ip) if self.contains(ip) => client = ip,
+                Some(ip) => return ip,
+                // `unknown` or an obfuscated identifier, the proxy that added it is the best we have
+                None => return client,
+            }
+        }
+
+        client
+    }
+}
+
+// a comma separated list of addresses and networks, e.g. `10.0.0.0/8,127.0.0.1`
+impl FromStr for TrustedProxies {
+    type Err = ipnet::AddrParseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let networks = value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.parse::<IpAddr>() {
+                Ok(ip) => Ok(IpNet::from(ip)),
+                Err(_) => entry.parse::<IpNet>(),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TrustedProxies(Arc::new(networks)))
+    }
+}
+
+// the hops of `Forwarded` (rfc 7239), or of `X-Forwarded-For` when there is no `Forwarded`,
+// from the client to the nearest proxy. hops without a usable address are `None`
+fn forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let values = |name: &str| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|header_value| header_value.to_str().ok())
+            .flat_map(|header_value| header_value.split(','))
+            .map(str::trim)
+            .filter(|element| !element.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    let forwarded = values("forwarded");
+    if !forwarded.is_empty() {
+        return forwarded
+            .iter()
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| parse_node(node.trim_matches('"')))
+            })
+            .collect();
+    }
+
+    values("x-forwarded-for")
+        .iter()
+        .map(|node| parse_node(node))
+        .collect()
+}
+
+// `192.0.2.60`, `192.0.2.60:4711`, `2001:db8::17` or `[2001:db8::17]:4711`
+fn parse_node(node: &str) -> Option<IpAddr> {
+    node.parse::<IpAddr>()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| {
+            node.strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+                .and_then(|ip| ip.parse().ok())
+        })
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Ok(ConnectInfo(peer)) =
+            ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await
+        else {
+            return Ok(ClientIp(None));
+        };
+
+        let ip = match parts.extensions.get::<TrustedProxies>() {
+            Some(trusted_proxies) => trusted_proxies.client_ip(peer.ip(), &parts.headers),
+            None => peer.ip(),
+        };
+
+        Ok(ClientIp(Some(ip)))
+    }
+}
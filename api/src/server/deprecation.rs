@@ -0,0 +1,78 @@
+use super::error::{ApiError, ErrorCode};
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+// RFC 9745, the routes from before /v1 are deprecated since 2026-10-18 and removed in the next release
+const DEPRECATION_HEADER: HeaderName = HeaderName::from_static("deprecation");
+const DEPRECATED_SINCE: &str = "@1792281600";
+const DEPRECATION_LINK: &str = "</docs>; rel=\"deprecation\"; type=\"text/html\"";
+
+// same limit as the axum body extractors
+const MAX_FORM_BYTES: usize = 2 * 1024 * 1024;
+
+// wraps the legacy routes, see `server::legacy_routes`
+pub async fn deprecation_middleware(request: Request, next: Next) -> Response {
+    tracing::debug!(
+        "deprecated route used: {} {}",
+        request.method(),
+        request.uri().path()
+    );
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert(
+        DEPRECATION_HEADER,
+        HeaderValue::from_static(DEPRECATED_SINCE),
+    );
+    headers.append(header::LINK, HeaderValue::from_static(DEPRECATION_LINK));
+
+    response
+}
+
+// turns an `application/x-www-form-urlencoded` body into a json object of strings so that the
+// legacy form routes can use the json handlers of /v1, other bodies are passed on unchanged
+pub async fn form_to_json(request: Request, next: Next) -> Response {
+    let is_form = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|header_value| header_value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/x-www-form-urlencoded"));
+    if !is_form {
+        return next.run(request).await;
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let fields = match axum::body::to_bytes(body, MAX_FORM_BYTES)
+        .await
+        .ok()
+        .and_then(|bytes| serde_urlencoded::from_bytes::<Vec<(String, String)>>(&bytes).ok())
+    {
+        Some(fields) => fields,
+        None => {
+            return ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidBody,
+                "The form body could not be read.",
+            )
+            .into_response()
+        }
+    };
+
+    let json: serde_json::Map<String, serde_json::Value> = fields
+        .into_iter()
+        .map(|(key, value)| (key, serde_json::Value::String(value)))
+        .collect();
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    let body = Body::from(serde_json::Value::Object(json).to_string());
+    next.run(Request::from_parts(parts, body)).await
+}
@@ -0,0 +1,138 @@
+use super::request_id::current_request_id;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+// the stable catalog of error codes, clients should match on these instead of the message
+// codes are never renamed or reused for something else, see docs/error-codes.md
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    // -- generic
+    InternalError,
+    RateLimited,
+    InvalidId,
+    InvalidBody,
+    InvalidQuery,
+    ShuttingDown,
+
+    // -- authentication
+    MissingToken,
+    InvalidToken,
+    TokenExpired,
+    SessionRevoked,
+    AccountInactive,
+    Forbidden,
+    InvalidCredentials,
+    TooManyAttempts,
+    AccountSuspended,
+    AccountDeactivated,
+    AccountNotFound,
+    InvalidEmail,
+    EmailTaken,
+    PasswordTooShort,
+    PasswordTooWeak,
+    InvalidResetToken,
+    MfaAlreadyEnabled,
+    MfaNotEnrolled,
+    InvalidMfaCode,
+    MfaCodeRequired,
+    MfaTokenExpired,
+
+    // -- users
+    UserNotFound,
+    AvatarNotFound,
+    InvalidHandle,
+    HandleTaken,
+    InvalidDisplayName,
+    BioTooLong,
+    StatusTooLong,
+    UnsupportedAvatarType,
+    AvatarTooLarge,
+    NoUsersFound,
+    QueryTooShort,
+    CannotBlockSelf,
+    CannotAddSelf,
+    Blocked,
+
+    // -- conversations
+    ConversationNotFound,
+    MessageNotFound,
+    SameSenderAndReceiver,
+    ConversationAlreadyExists,
+    NotAParticipant,
+    MessageRequestNotFound,
+    MessageRequestPending,
+    RecipientUnavailable,
+
+    // -- moderation and admin
+    MissingReportTarget,
+    CannotReportSelf,
+    ReportNotFound,
+    DetailsTooLong,
+    InvalidModerationAction,
+    CannotTargetSelf,
+    InvalidTimeRange,
+}
+
+// the body of every error response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    // human readable, can change at any time
+    pub message: String,
+    // extra machine readable data for some codes, e.g. the limits of a validation error
+    pub details: Option<serde_json::Value>,
+    // the `x-request-id` of the request, include it when reporting a problem
+    pub request_id: Option<String>,
+}
+
+// every service error is turned into one of these in its `IntoResponse` implementation
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    // the cause is only logged, it never ends up in the response
+    pub fn internal() -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::InternalError,
+            "Something went wrong on our side, please try again later.",
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            code: self.code,
+            message: self.message,
+            details: self.details,
+            request_id: current_request_id(),
+        };
+
+        (self.status, Json(body)).into_response()
+    }
+}
@@ -0,0 +1,155 @@
+use super::AppState;
+use crate::db_service::pending_migrations;
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::{Duration, Instant};
+
+// the database checks of readiness give up after this, a probe should not hang
+pub const READY_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Liveness {
+    pub status: String,
+    pub version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Readiness {
+    // the database is reachable, every migration is applied and the server is not shutting down
+    pub ready: bool,
+    pub version: String,
+    pub shutting_down: bool,
+    pub database: DatabaseCheck,
+    pub pool: PoolStats,
+}
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct DatabaseCheck {
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    // versions of the embedded migrations that are not applied yet, unknown when unreachable
+    pub pending_migrations: Option<Vec<i64>>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct PoolStats {
+    pub size: u32,
+    pub idle: u32,
+    pub max_connections: u32,
+    // every connection is open and in use, new queries wait for one
+    pub saturated: bool,
+}
+
+impl PoolStats {
+    pub fn of(pool: &PgPool) -> Self {
+        let size = pool.size();
+        let idle = pool.num_idle() as u32;
+        let max_connections = pool.options().get_max_connections();
+
+        PoolStats {
+            size,
+            idle,
+            max_connections,
+            saturated: size >= max_connections && idle == 0,
+        }
+    }
+}
+
+impl DatabaseCheck {
+    pub async fn run(pool: &PgPool) -> Self {
+        let started = Instant::now();
+        let check = async {
+            sqlx::query("SELECT 1").execute(pool).await?;
+            let latency = started.elapsed();
+            let pending = pending_migrations(pool).await?;
+
+            Ok::<_, sqlx::Error>((latency, pending))
+        };
+
+        match tokio::time::timeout(READY_CHECK_TIMEOUT, check).await {
+            Ok(Ok((latency, pending))) => DatabaseCheck {
+                reachable: true,
+                latency_ms: Some(latency.as_millis() as u64),
+                pending_migrations: Some(
+                    pending.iter().map(|migration| migration.version).collect(),
+                ),
+                error: None,
+            },
+            Ok(Err(e)) => {
+                tracing::error!("Database error in the readiness check {:?}", e);
+                DatabaseCheck::unreachable()
+            }
+            Err(_) => {
+                tracing::error!(
+                    "Database did not answer the readiness check within {:?}",
+                    READY_CHECK_TIMEOUT
+                );
+                DatabaseCheck::unreachable()
+            }
+        }
+    }
+
+    // the route is not authenticated, so the cause is only logged and not sent to the caller
+    fn unreachable() -> Self {
+        DatabaseCheck {
+            reachable: false,
+            latency_ms: None,
+            pending_migrations: None,
+            error: Some("database unavailable".to_string()),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    tag = "health",
+    responses((status = 200, description = "The process is up, nothing else is checked", body = Liveness))
+)]
+pub async fn live_service() -> Json<Liveness> {
+    Json(Liveness {
+        status: "ok".to_string(),
+        version: VERSION.to_string(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "The instance can take traffic", body = Readiness),
+        (status = 503, description = "The database is not usable or the server is shutting down", body = Readiness)
+    )
+)]
+pub async fn ready_service(State(app_state): State<AppState>) -> (StatusCode, Json<Readiness>) {
+    let database = DatabaseCheck::run(&app_state.pool).await;
+    let shutting_down = app_state.shutdown.is_triggered();
+    let ready = database.reachable
+        && database
+            .pending_migrations
+            .as_ref()
+            .is_some_and(|pending| pending.is_empty())
+        && !shutting_down;
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(Readiness {
+            ready,
+            version: VERSION.to_string(),
+            shutting_down,
+            database,
+            pool: PoolStats::of(&app_state.pool),
+        }),
+    )
+}
@@ -0,0 +1,312 @@
+use super::{
+    health::{PoolStats, READY_CHECK_TIMEOUT},
+    AppState,
+};
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+// in seconds, the default buckets of the prometheus client libraries
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+// requests that did not match a route share one label, so random urls do not add series
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+// one registry for the process, services record into it without going through `AppState`
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+#[derive(Default)]
+pub struct Metrics {
+    // by (method, matched route)
+    requests: Mutex<BTreeMap<(String, String), RouteStats>>,
+    signins_succeeded: AtomicU64,
+    // by reason, the same reasons as the signin_failed audit events
+    signins_failed: Mutex<BTreeMap<&'static str, u64>>,
+    messages_sent: AtomicU64,
+    message_polls: AtomicU64,
+}
+
+#[derive(Default)]
+struct RouteStats {
+    statuses: BTreeMap<u16, u64>,
+    // not cumulative, `render` adds them up
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+    count: u64,
+}
+
+// counts a request to the messages of a conversation until it is dropped, also when it is cancelled
+pub struct MessagePoll(&'static Metrics);
+
+impl Drop for MessagePoll {
+    fn drop(&mut self) {
+        self.0.message_polls.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Metrics {
+    pub fn record_request(&self, method: &str, route: &str, status: u16, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        let mut requests = self.requests.lock().unwrap();
+        let stats = requests
+            .entry((method.to_string(), route.to_string()))
+            .or_default();
+
+        *stats.statuses.entry(status).or_default() += 1;
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|le| seconds <= *le) {
+            stats.latency_buckets[bucket] += 1;
+        }
+        stats.latency_sum += seconds;
+        stats.count += 1;
+    }
+
+    pub fn signin_succeeded(&self) {
+        self.signins_succeeded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn signin_failed(&self, reason: &'static str) {
+        *self
+            .signins_failed
+            .lock()
+            .unwrap()
+            .entry(reason)
+            .or_default() += 1;
+    }
+
+    pub fn message_sent(&self) {
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // held while GET /conversations/{id}/messages is handled, the route clients poll to follow
+    // a conversation since there is no push endpoint
+    pub fn message_poll(&'static self) -> MessagePoll {
+        self.message_polls.fetch_add(1, Ordering::Relaxed);
+        MessagePoll(self)
+    }
+
+    // the prometheus text format, `probe_wait` is `None` when the probe got no connection
+    pub fn render(&self, pool: &PoolStats, probe_wait: Option<Duration>) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "chat_http_requests_total",
+            "counter",
+            "HTTP requests by method, matched route and status",
+        );
+        let requests = self.requests.lock().unwrap();
+        for ((method, route), stats) in requests.iter() {
+            for (status, count) in &stats.statuses {
+                let _ = writeln!(
+                    out,
+                    "chat_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                    escape(method),
+                    escape(route),
+                    status,
+                    count
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "chat_http_request_duration_seconds",
+            "histogram",
+            "Time until the response headers were ready, by method and matched route",
+        );
+        for ((method, route), stats) in requests.iter() {
+            let labels = format!("method=\"{}\",route=\"{}\"", escape(method), escape(route));
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS.iter().zip(stats.latency_buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "chat_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, le, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "chat_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, stats.count
+            );
+            let _ = writeln!(
+                out,
+                "chat_http_request_duration_seconds_sum{{{}}} {}",
+                labels, stats.latency_sum
+            );
+            let _ = writeln!(
+                out,
+                "chat_http_request_duration_seconds_count{{{}}} {}",
+                labels, stats.count
+            );
+        }
+        drop(requests);
+
+        header(
+            &mut out,
+            "chat_signins_succeeded_total",
+            "counter",
+            "Signins that issued a token, with or without 2fa",
+        );
+        let _ = writeln!(
+            out,
+            "chat_signins_succeeded_total {}",
+            self.signins_succeeded.load(Ordering::Relaxed)
+        );
+
+        header(
+            &mut out,
+            "chat_signins_failed_total",
+            "counter",
+            "Signins that were refused, by reason",
+        );
+        for (reason, count) in self.signins_failed.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "chat_signins_failed_total{{reason=\"{}\"}} {}",
+                reason, count
+            );
+        }
+
+        header(
+            &mut out,
+            "chat_messages_sent_total",
+            "counter",
+            "Messages stored in conversations",
+        );
+        let _ = writeln!(
+            out,
+            "chat_messages_sent_total {}",
+            self.messages_sent.load(Ordering::Relaxed)
+        );
+
+        header(
+            &mut out,
+            "chat_message_polls_in_flight",
+            "gauge",
+            "Requests for the messages of a conversation that are being handled, clients poll it for new messages",
+        );
+        let _ = writeln!(
+            out,
+            "chat_message_polls_in_flight {}",
+            self.message_polls.load(Ordering::Relaxed)
+        );
+
+        header(
+            &mut out,
+            "chat_db_pool_connections",
+            "gauge",
+            "Open database connections by state",
+        );
+        let _ = writeln!(
+            out,
+            "chat_db_pool_connections{{state=\"idle\"}} {}",
+            pool.idle
+        );
+        let _ = writeln!(
+            out,
+            "chat_db_pool_connections{{state=\"in_use\"}} {}",
+            pool.size.saturating_sub(pool.idle)
+        );
+        header(
+            &mut out,
+            "chat_db_pool_max_connections",
+            "gauge",
+            "Connections the pool opens at most",
+        );
+        let _ = writeln!(out, "chat_db_pool_max_connections {}", pool.max_connections);
+
+        header(
+            &mut out,
+            "chat_db_up",
+            "gauge",
+            "Whether this scrape got a database connection in time",
+        );
+        let _ = writeln!(out, "chat_db_up {}", u8::from(probe_wait.is_some()));
+        if let Some(wait) = probe_wait {
+            // only the one connection of the scrape, the waits of requests are not measured
+            header(
+                &mut out,
+                "chat_db_pool_probe_acquire_seconds",
+                "gauge",
+                "How long the probe of this scrape waited for a database connection",
+            );
+            let _ = writeln!(
+                out,
+                "chat_db_pool_probe_acquire_seconds {}",
+                wait.as_secs_f64()
+            );
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// layered with `Router::layer`, so the matched route is known like in the trace span
+pub async fn metrics_middleware(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+    METRICS.record_request(
+        &method,
+        &route,
+        response.status().as_u16(),
+        started.elapsed(),
+    );
+
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses((status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"))
+)]
+pub async fn metrics_service(State(app_state): State<AppState>) -> impl IntoResponse {
+    // a probe of the pool, a busy pool makes every request wait about as long
+    let started = Instant::now();
+    let probe_wait = match tokio::time::timeout(READY_CHECK_TIMEOUT, app_state.pool.acquire()).await
+    {
+        Ok(Ok(_connection)) => Some(started.elapsed()),
+        _ => None,
+    };
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        METRICS.render(&PoolStats::of(&app_state.pool), probe_wait),
+    )
+}
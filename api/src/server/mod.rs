@@ -1,25 +1,61 @@
+pub mod client_ip;
+pub mod deprecation;
+pub mod error;
+pub mod extract;
+pub mod health;
+pub mod metrics;
+pub mod openapi;
+pub mod rate_limit;
+pub mod request_id;
+pub mod shutdown;
+
 use derive_more::From;
 
-use crate::{auth_service::router::auth_routes, conversation_service::router::conversation_routes};
+use crate::{
+    admin_service::router::admin_routes,
+    audit_service::AuditLog,
+    auth_service::router::{auth_routes, legacy_auth_routes},
+    config::{error::ConfigError, Config},
+    conversation_service::router::{conversation_routes, legacy_conversation_routes},
+    mail_service::{LogMailer, Mailer},
+    moderation_service::router::{moderation_routes, report_routes},
+    user_service::router::user_routes,
+};
 use axum::{
     extract::MatchedPath,
     http::{self, Request},
+    middleware,
     response::Response,
-    routing::*,
-    Router,
+    routing::get,
+    Extension, Router,
 };
+use client_ip::TrustedProxies;
+use deprecation::deprecation_middleware;
 use http::Method;
+use metrics::metrics_middleware;
+use openapi::ApiDoc;
+use rate_limit::{
+    memory::InMemoryRateLimitBackend, postgres::PostgresRateLimitBackend, rate_limit_middleware,
+    RateLimitBackend, RateLimitBackendKind, RateLimitConfig, RateLimiter,
+};
+use request_id::{current_request_id, request_id_middleware, REQUEST_ID_HEADER};
+use shutdown::{serve_until, shutdown_middleware, shutdown_signal, Shutdown};
 use sqlx::PgPool;
+use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 use tracing::{info, info_span, Span};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use utoipa::OpenApi;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use utoipa_scalar::{Scalar, Servable};
 
 #[derive(Debug, From)]
 pub enum ServerError {
     Axum(axum::Error),
     TokioIo(tokio::io::Error),
     Database(sqlx::Error),
+    Config(ConfigError),
 }
 
 const HOST_PORT: &str = "0.0.0.0:3000";
@@ -27,15 +63,77 @@ const HOST_PORT: &str = "0.0.0.0:3000";
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
+    pub mailer: Arc<dyn Mailer>,
+    pub shutdown: Shutdown,
+    pub password_reset_url: String,
+    pub trusted_proxies: TrustedProxies,
 }
 
 impl AppState {
-    fn new(pool: PgPool) -> Self {
-        AppState { pool }
+    // uses the defaults of `Config`, see `with_config`
+    pub fn new(pool: PgPool, mailer: Arc<dyn Mailer>) -> Self {
+        AppState {
+            pool,
+            mailer,
+            shutdown: Shutdown::new(),
+            password_reset_url: Config::DEFAULT_PASSWORD_RESET_URL.to_string(),
+            trusted_proxies: TrustedProxies::default(),
+        }
+    }
+
+    pub fn with_config(self, config: &Config) -> Self {
+        AppState {
+            password_reset_url: config.password_reset_url.clone(),
+            trusted_proxies: config.trusted_proxies.clone(),
+            ..self
+        }
     }
 }
 
-pub async fn run_server() -> Result<(), crate::ServerError> {
+// every route of the api without the middleware, the openapi document is generated from the
+// documented routes while they are added, see `openapi::ApiDoc`
+pub fn routes(app_state: AppState) -> Router {
+    let (router, openapi) = OpenApiRouter::with_openapi(ApiDoc::openapi())
+        .routes(routes!(health::live_service))
+        .routes(routes!(health::ready_service))
+        .routes(routes!(metrics::metrics_service))
+        .routes(routes!(openapi::openapi_service))
+        .nest("/v1", v1_routes(app_state.clone()))
+        .split_for_parts();
+
+    router
+        .merge(Scalar::with_url("/docs", openapi.clone()))
+        .layer(Extension(Arc::new(openapi)))
+        .merge(legacy_routes(app_state.clone()))
+        .with_state(app_state)
+}
+
+fn v1_routes(app_state: AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .nest("/auth", auth_routes(app_state.clone()))
+        .nest("/conversations", conversation_routes(app_state.clone()))
+        .nest("/users", user_routes(app_state.clone()))
+        .nest("/reports", report_routes(app_state.clone()))
+        .nest("/moderation", moderation_routes(app_state.clone()))
+        .nest("/admin", admin_routes(app_state))
+}
+
+// the routes from before /v1, kept for one more release with a `deprecation` header
+// routes that were added with /v1 are only served there
+fn legacy_routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        // the health check from before /health/live and /health/ready
+        .route("/health", get(health::live_service))
+        .nest("/auth", legacy_auth_routes(app_state.clone()))
+        .nest(
+            "/conversation",
+            legacy_conversation_routes(app_state.clone()),
+        )
+        .layer(middleware::from_fn(deprecation_middleware))
+}
+
+// logs go to stdout, filtered with RUST_LOG
+pub fn init_tracing() {
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
@@ -43,29 +141,89 @@ pub async fn run_server() -> Result<(), crate::ServerError> {
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
+}
+
+pub async fn run_server(config: Config, pool: PgPool) -> Result<(), crate::ServerError> {
+    let app_state = AppState::new(pool.clone(), Arc::new(LogMailer)).with_config(&config);
+    let shutdown = app_state.shutdown.clone();
+
+    let app = app(app_state, config.rate_limit);
+
+    let listener = tokio::net::TcpListener::bind(HOST_PORT).await?;
+    tracing::debug!("listening on {}", listener.local_addr().unwrap());
+
+    let retention_job =
+        AuditLog::spawn_retention_job(pool.clone(), config.audit_retention_days, shutdown.clone());
+
+    let signal = shutdown.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        signal.trigger();
+    });
+    let served = serve_until(listener, app, shutdown.clone(), config.shutdown_timeout).await;
+
+    // the background jobs stop as well when the server stopped because of an error
+    shutdown.trigger();
+    if let Err(e) = retention_job.await {
+        tracing::error!("audit retention job failed: {:?}", e);
+    }
+
+    // waits for the connections that are checked out, so no query is cut off, but not longer
+    // than the requests got to finish
+    if tokio::time::timeout(config.shutdown_timeout, pool.close())
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            "database connections were still in use after {:?}",
+            config.shutdown_timeout
+        );
+    }
+    served?;
+    tracing::info!("server stopped");
+    Ok(())
+}
 
+// the routes with every middleware the server uses, tests send requests to it with `oneshot`
+pub fn app(app_state: AppState, rate_limit: RateLimitConfig) -> Router {
+    let shutdown = app_state.shutdown.clone();
+    let trusted_proxies = app_state.trusted_proxies.clone();
     let cors = CorsLayer::new()
-        // allow `GET` and `POST` when accessing the resource
-        .allow_methods([Method::GET, Method::POST])
+        // allow `GET`, `POST`, `PUT`, `PATCH` and `DELETE` when accessing the resource
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
         // allow requests from any origin
         .allow_origin(Any)
-        .expose_headers([http::header::AUTHORIZATION]);
+        .expose_headers([
+            http::header::AUTHORIZATION,
+            http::header::RETRY_AFTER,
+            http::HeaderName::from_static("ratelimit-limit"),
+            http::HeaderName::from_static("ratelimit-remaining"),
+            http::HeaderName::from_static("ratelimit-reset"),
+            REQUEST_ID_HEADER,
+        ]);
 
-    let pool = crate::db_service::get_connection_pool().await?;
-    let app_state = AppState::new(pool);
+    let rate_limit_backend: Arc<dyn RateLimitBackend> = match rate_limit.backend {
+        RateLimitBackendKind::Memory => Arc::new(InMemoryRateLimitBackend::new()),
+        RateLimitBackendKind::Postgres => {
+            Arc::new(PostgresRateLimitBackend::new(app_state.pool.clone()))
+        }
+    };
+    let rate_limiter = RateLimiter::new(rate_limit_backend, rate_limit);
 
-    let auth_routes = auth_routes(app_state.clone());
-    let conversation_routes = conversation_routes(app_state.clone());
-
-    let app = Router::new()
-        .route(
-            "/health",
-            get(|| async { "All good! will run cargo test with this request later" }),
-        )
-        .nest("/auth", auth_routes)
-        .nest("/conversation", conversation_routes)
-        .with_state(app_state)
+    routes(app_state)
+        .layer(middleware::from_fn_with_state(
+            rate_limiter,
+            rate_limit_middleware,
+        ))
         .layer(cors)
+        // outside of the rate limiter so refused requests are counted too
+        .layer(middleware::from_fn(metrics_middleware))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(|request: &Request<_>| {
@@ -79,6 +237,7 @@ pub async fn run_server() -> Result<(), crate::ServerError> {
                         "http_request",
                         method = %request.method(),
                         path = %path,
+                        request_id = %current_request_id().unwrap_or_default(),
                     )
                 })
                 .on_request(|request: &Request<_>, _span: &Span| {
@@ -89,10 +248,13 @@ pub async fn run_server() -> Result<(), crate::ServerError> {
                         info!("status={} latency={:?}", response.status(), latency);
                     },
                 ),
-        );
-
-    let listener = tokio::net::TcpListener::bind(HOST_PORT).await?;
-    tracing::debug!("listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await?;
-    Ok(())
+        )
+        // outermost so the id is set for everything above, including the trace span
+        .layer(middleware::from_fn(request_id_middleware))
+        .layer(middleware::from_fn_with_state(
+            shutdown,
+            shutdown_middleware,
+        ))
+        // read by `ClientIp` in the rate limiter and the handlers
+        .layer(Extension(trusted_proxies))
 }
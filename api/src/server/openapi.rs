@@ -0,0 +1,49 @@
+use super::{
+    error::{ErrorBody, ErrorCode},
+    health::{DatabaseCheck, Liveness, PoolStats, Readiness},
+};
+use axum::{response::IntoResponse, Extension, Json};
+use std::sync::Arc;
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    Modify, OpenApi,
+};
+
+// the paths are added by `server::routes` from the routers themselves, so the document has every
+// route the server answers under /v1 and no other. the legacy routes are not documented
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Chat API",
+        description = "Errors have the `ErrorBody` shape, see docs/error-codes.md for every code."
+    ),
+    components(schemas(ErrorBody, ErrorCode, Liveness, Readiness, DatabaseCheck, PoolStats)),
+    modifiers(&JwtSecurity)
+)]
+pub struct ApiDoc;
+
+// the token is sent as it was received from signin, without a `Bearer ` prefix
+struct JwtSecurity;
+
+impl Modify for JwtSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "jwt",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("authorization"))),
+        );
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    tag = "health",
+    responses((status = 200, description = "This document, the docs UI is at /docs"))
+)]
+// `server::routes` adds the document it generated to the request extensions
+pub async fn openapi_service(
+    Extension(openapi): Extension<Arc<utoipa::openapi::OpenApi>>,
+) -> impl IntoResponse {
+    Json(openapi.as_ref().clone())
+}
@@ -0,0 +1,90 @@
+use super::{RateLimitBackend, RateLimitDecision, RateLimitPolicy};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    // the bucket is full again from then on, forgetting it does not change any limit
+    full_at: Instant,
+}
+
+struct Buckets {
+    by_key: HashMap<String, Bucket>,
+    cleaned_up_at: Instant,
+}
+
+// keeps the buckets in this process, limits are not shared between instances
+pub struct InMemoryRateLimitBackend {
+    buckets: Mutex<Buckets>,
+    cleanup_interval: Duration,
+}
+
+impl Default for InMemoryRateLimitBackend {
+    fn default() -> Self {
+        Self::with_cleanup_interval(Self::CLEANUP_INTERVAL)
+    }
+}
+
+impl InMemoryRateLimitBackend {
+    // full buckets are dropped at most this often, so a request does not scan the map every time
+    const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cleanup_interval(cleanup_interval: Duration) -> Self {
+        Self {
+            buckets: Mutex::new(Buckets {
+                by_key: HashMap::new(),
+                cleaned_up_at: Instant::now(),
+            }),
+            cleanup_interval,
+        }
+    }
+
+    pub fn bucket_count(&self) -> usize {
+        self.buckets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .by_key
+            .len()
+    }
+}
+
+#[async_trait]
+impl RateLimitBackend for InMemoryRateLimitBackend {
+    async fn take(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<RateLimitDecision, sqlx::Error> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if now.duration_since(buckets.cleaned_up_at) >= self.cleanup_interval {
+            // every bucket is checked against its own policy, not the one of this request
+            buckets.by_key.retain(|_, bucket| bucket.full_at > now);
+            buckets.cleaned_up_at = now;
+        }
+
+        let bucket = buckets.by_key.entry(key.to_string()).or_insert(Bucket {
+            tokens: policy.requests as f64,
+            updated_at: now,
+            full_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        let (tokens, decision) = policy.take(bucket.tokens, elapsed);
+        bucket.tokens = tokens;
+        bucket.updated_at = now;
+        bucket.full_at = now + Duration::from_secs(decision.reset_in_seconds);
+
+        Ok(decision)
+    }
+}
@@ -0,0 +1,312 @@
+pub mod memory;
+pub mod postgres;
+
+use crate::auth_service::claims::JwtClaims;
+use crate::server::{
+    client_ip::ClientIp,
+    error::{ApiError, ErrorCode},
+};
+use async_trait::async_trait;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+// a token bucket that holds up to `requests` tokens and is refilled completely every `period_in_seconds`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitPolicy {
+    pub requests: u32,
+    pub period_in_seconds: u64,
+}
+
+impl RateLimitPolicy {
+    pub const fn new(requests: u32, period_in_seconds: u64) -> Self {
+        Self {
+            requests,
+            period_in_seconds,
+        }
+    }
+
+    fn refill_per_second(&self) -> f64 {
+        self.requests as f64 / self.period_in_seconds.max(1) as f64
+    }
+
+    // refills the bucket for the elapsed time and tries to take one token from it
+    // returns the tokens left in the bucket and the decision
+    pub fn take(&self, tokens: f64, elapsed_in_seconds: f64) -> (f64, RateLimitDecision) {
+        let capacity = self.requests as f64;
+        let refill = self.refill_per_second();
+        let tokens = (tokens + elapsed_in_seconds.max(0.0) * refill).min(capacity);
+
+        if tokens >= 1.0 {
+            let tokens = tokens - 1.0;
+            let decision = RateLimitDecision {
+                allowed: true,
+                limit: self.requests,
+                remaining: tokens.floor() as u32,
+                reset_in_seconds: ((capacity - tokens) / refill).ceil() as u64,
+                retry_after_seconds: 0,
+            };
+            (tokens, decision)
+        } else {
+            let decision = RateLimitDecision {
+                allowed: false,
+                limit: self.requests,
+                remaining: 0,
+                reset_in_seconds: ((capacity - tokens) / refill).ceil() as u64,
+                retry_after_seconds: ((1.0 - tokens) / refill).ceil().max(1.0) as u64,
+            };
+            (tokens, decision)
+        }
+    }
+}
+
+// parses `<requests>/<seconds>`, e.g. `120/60`
+impl FromStr for RateLimitPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (requests, period) = s.trim().split_once('/').ok_or(())?;
+        let requests = requests.trim().parse::<u32>().map_err(|_| ())?;
+        let period_in_seconds = period.trim().parse::<u64>().map_err(|_| ())?;
+
+        if requests == 0 || period_in_seconds == 0 {
+            return Err(());
+        }
+
+        Ok(Self::new(requests, period_in_seconds))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    // seconds until the bucket is full again
+    pub reset_in_seconds: u64,
+    // seconds until the next request is allowed, 0 when the request was allowed
+    pub retry_after_seconds: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitBackendKind {
+    #[default]
+    Memory,
+    Postgres,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub backend: RateLimitBackendKind,
+    pub default_policy: RateLimitPolicy,
+    // keyed by the matched route, either `METHOD /path` or just `/path` for every method
+    pub route_policies: HashMap<String, RateLimitPolicy>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        let route_policies = [
+            ("POST /v1/auth/signup", RateLimitPolicy::new(5, 600)),
+            ("POST /v1/auth/signin", RateLimitPolicy::new(10, 60)),
+            ("GET /v1/users/search", RateLimitPolicy::new(30, 60)),
+            ("GET /v1/users/lookup", RateLimitPolicy::new(30, 60)),
+            (
+                "POST /v1/auth/password/forgot",
+                RateLimitPolicy::new(5, 3600),
+            ),
+            (
+                "POST /v1/auth/password/reset",
+                RateLimitPolicy::new(10, 600),
+            ),
+            ("POST /v1/auth/mfa/verify", RateLimitPolicy::new(10, 60)),
+            (
+                "POST /v1/conversations/{id}/messages",
+                RateLimitPolicy::new(60, 60),
+            ),
+            ("POST /v1/reports", RateLimitPolicy::new(10, 600)),
+            // legacy routes, see `server::legacy_routes`
+            ("POST /auth/signup", RateLimitPolicy::new(5, 600)),
+            ("POST /auth/signin", RateLimitPolicy::new(10, 60)),
+            ("POST /auth/search", RateLimitPolicy::new(30, 60)),
+            ("POST /auth/password/forgot", RateLimitPolicy::new(5, 3600)),
+            ("POST /auth/password/reset", RateLimitPolicy::new(10, 600)),
+            ("POST /auth/mfa/verify", RateLimitPolicy::new(10, 60)),
+            ("POST /conversation/message", RateLimitPolicy::new(60, 60)),
+        ]
+        .into_iter()
+        .map(|(route, policy)| (route.to_string(), policy))
+        .collect();
+
+        Self {
+            backend: RateLimitBackendKind::default(),
+            default_policy: RateLimitPolicy::new(120, 60),
+            route_policies,
+        }
+    }
+}
+
+// where the buckets are stored, the in memory backend only works for a single instance
+#[async_trait]
+pub trait RateLimitBackend: Send + Sync {
+    async fn take(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<RateLimitDecision, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    backend: Arc<dyn RateLimitBackend>,
+    config: Arc<RateLimitConfig>,
+}
+
+impl RateLimiter {
+    pub fn new(backend: Arc<dyn RateLimitBackend>, config: RateLimitConfig) -> Self {
+        Self {
+            backend,
+            config: Arc::new(config),
+        }
+    }
+
+    pub fn policy_for(&self, method: &str, route: &str) -> RateLimitPolicy {
+        self.config
+            .route_policies
+            .get(&format!("{} {}", method, route))
+            .or_else(|| self.config.route_policies.get(route))
+            .copied()
+            .unwrap_or(self.config.default_policy)
+    }
+
+    // every route has its own bucket per client
+    pub async fn check(
+        &self,
+        method: &str,
+        route: &str,
+        client_key: &str,
+    ) -> Result<RateLimitDecision, sqlx::Error> {
+        let policy = self.policy_for(method, route);
+        let key = format!("{}|{} {}", client_key, method, route);
+
+        self.backend.take(&key, &policy).await
+    }
+}
+
+// signed in clients are limited by their user id, everyone else by their ip
+fn client_key(request: &Request, ClientIp(client_ip): ClientIp) -> String {
+    let user_id = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|header_value| header_value.to_str().ok())
+        .map(|token| token.trim_start_matches("Bearer ").to_string())
+        .and_then(|token| JwtClaims::decode(&token).ok())
+        .map(|claims| claims.user_id);
+
+    match (user_id, client_ip) {
+        (Some(user_id), _) => format!("user:{}", user_id),
+        (None, Some(ip)) => format!("ip:{}", ip),
+        (None, None) => "anonymous".to_string(),
+    }
+}
+
+pub async fn rate_limit_middleware(
+    State(rate_limiter): State<RateLimiter>,
+    client_ip: ClientIp,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let method = request.method().to_string();
+    let client_key = client_key(&request, client_ip);
+
+    let decision = match rate_limiter.check(&method, &route, &client_key).await {
+        Ok(decision) => decision,
+        Err(e) => {
+            // do not take the whole api down when the rate limit storage is unavailable
+            tracing::error!("rate limit backend error, allowing request: {:?}", e);
+            return next.run(request).await;
+        }
+    };
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        tracing::debug!("rate limited {} on {} {}", client_key, method, route);
+        let mut response = ApiError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::RateLimited,
+            format!(
+                "Too many requests, please try again in {} seconds.",
+                decision.retry_after_seconds
+            ),
+        )
+        .with_details(serde_json::json!({ "retry_after_seconds": decision.retry_after_seconds }))
+        .into_response();
+        response.headers_mut().insert(
+            header::RETRY_AFTER,
+            HeaderValue::from(decision.retry_after_seconds),
+        );
+        response
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(
+        HeaderName::from_static("ratelimit-limit"),
+        HeaderValue::from(decision.limit),
+    );
+    headers.insert(
+        HeaderName::from_static("ratelimit-remaining"),
+        HeaderValue::from(decision.remaining),
+    );
+    headers.insert(
+        HeaderName::from_static("ratelimit-reset"),
+        HeaderValue::from(decision.reset_in_seconds),
+    );
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let policy = RateLimitPolicy::new(2, 10);
+
+        let (tokens, decision) = policy.take(2.0, 0.0);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 1);
+
+        let (tokens, decision) = policy.take(tokens, 0.0);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+
+        let (tokens, decision) = policy.take(tokens, 0.0);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_seconds, 5);
+        assert_eq!(decision.reset_in_seconds, 10);
+
+        // half the period refills one of the two tokens
+        let (_, decision) = policy.take(tokens, 5.0);
+        assert!(decision.allowed);
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!(
+            "120/60".parse::<RateLimitPolicy>(),
+            Ok(RateLimitPolicy::new(120, 60))
+        );
+        assert!("0/60".parse::<RateLimitPolicy>().is_err());
+        assert!("120".parse::<RateLimitPolicy>().is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use super::{RateLimitBackend, RateLimitDecision, RateLimitPolicy};
+use async_trait::async_trait;
+use chrono::Duration;
+use sqlx::PgPool;
+
+// keeps the buckets in the rate_limit_buckets table so every instance shares the same limits
+pub struct PostgresRateLimitBackend {
+    pool: PgPool,
+}
+
+impl PostgresRateLimitBackend {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // deletes the buckets that are full again, returns how many were deleted
+    pub async fn purge_expired(pool: &PgPool) -> Result<u64, sqlx::Error> {
+        let res = sqlx::query!(
+            "DELETE FROM rate_limit_buckets WHERE full_at <= $1",
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+}
+
+#[async_trait]
+impl RateLimitBackend for PostgresRateLimitBackend {
+    async fn take(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<RateLimitDecision, sqlx::Error> {
+        let now = sqlx::types::chrono::Utc::now().naive_utc();
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO rate_limit_buckets (key, tokens, updated_at, full_at)
+            VALUES ($1, $2, $3, $3)
+            ON CONFLICT (key) DO NOTHING
+            "#,
+            key,
+            policy.requests as f64,
+            now,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // the row lock makes concurrent requests from other instances wait for this one
+        let bucket = sqlx::query!(
+            "SELECT tokens, updated_at FROM rate_limit_buckets WHERE key = $1 FOR UPDATE",
+            key
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let elapsed = (now - bucket.updated_at).num_milliseconds() as f64 / 1000.0;
+        let (tokens, decision) = policy.take(bucket.tokens, elapsed);
+
+        sqlx::query!(
+            "UPDATE rate_limit_buckets SET tokens = $2, updated_at = $3, full_at = $4 WHERE key = $1",
+            key,
+            tokens,
+            now,
+            now + Duration::seconds(decision.reset_in_seconds as i64),
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(decision)
+    }
+}
@@ -0,0 +1,166 @@
+use super::error::{ApiError, ErrorCode};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use std::{future::IntoFuture, sync::Arc, time::Duration};
+use tokio::{net::TcpListener, sync::watch};
+
+// set once the server is asked to stop, handlers and background jobs that run for a long time
+// wait on `triggered` to finish early
+#[derive(Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Shutdown {
+            sender: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    // resolves right away when the shutdown already started
+    pub async fn triggered(&self) {
+        let mut receiver = self.sender.subscribe();
+        // the sender lives as long as `self`, so this cannot fail
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+}
+
+// resolves on ctrl-c, or on SIGTERM which is what docker and kubernetes send
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("could not listen for ctrl-c: {:?}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("could not listen for SIGTERM: {:?}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("received ctrl-c"),
+        _ = terminate => tracing::info!("received SIGTERM"),
+    }
+}
+
+// how long the cancelled requests get to send their response before the server stops anyway
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(1);
+
+// serves until `shutdown` is triggered, then stops accepting connections and waits up to
+// `drain_timeout` for the requests in flight. the requests that are still running then are
+// cancelled and answered with `shutting_down`, returns false when that happened
+pub async fn serve_until(
+    listener: TcpListener,
+    app: axum::Router,
+    shutdown: Shutdown,
+    drain_timeout: Duration,
+) -> Result<bool, std::io::Error> {
+    // axum runs every connection in its own task, dropping the server future does not stop them,
+    // so every request waits on this as well
+    let cancel = Shutdown::new();
+    let app = app.layer(middleware::from_fn_with_state(
+        cancel.clone(),
+        cancel_middleware,
+    ));
+
+    let signal = shutdown.clone();
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move { signal.triggered().await })
+    .into_future();
+    tokio::pin!(server);
+
+    let deadline = async {
+        shutdown.triggered().await;
+        tracing::info!(
+            "shutting down, waiting up to {:?} for requests in flight",
+            drain_timeout
+        );
+        tokio::time::sleep(drain_timeout).await;
+    };
+
+    tokio::select! {
+        res = &mut server => return res.map(|_| true),
+        _ = deadline => {}
+    }
+
+    tracing::warn!(
+        "requests were still running after {:?}, cancelling them",
+        drain_timeout
+    );
+    cancel.trigger();
+    // the cancelled requests are answered right away, a client that does not read its response
+    // does not keep the server from stopping
+    match tokio::time::timeout(CANCEL_TIMEOUT, server).await {
+        Ok(res) => res.map(|_| false),
+        Err(_) => Ok(false),
+    }
+}
+
+// drops the handler when `cancel` is triggered, e.g. one that waits on a slow query
+async fn cancel_middleware(
+    State(cancel): State<Shutdown>,
+    request: Request,
+    next: Next,
+) -> Response {
+    tokio::select! {
+        response = next.run(request) => response,
+        _ = cancel.triggered() => ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::ShuttingDown,
+            "The server stopped before the request finished, please try again.",
+        )
+        .into_response(),
+    }
+}
+
+// while the server drains, responses ask the client to open a new connection, which the load
+// balancer sends to another instance. polling clients like `message_stream` reconnect this way
+pub async fn shutdown_middleware(
+    State(shutdown): State<Shutdown>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+
+    if shutdown.is_triggered() {
+        response
+            .headers_mut()
+            .insert(header::CONNECTION, HeaderValue::from_static("close"));
+    }
+
+    response
+}
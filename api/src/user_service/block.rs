@@ -0,0 +1,136 @@
+use super::error::BlockError;
+use crate::auth_service::user::{PublicUserData, PublicUserRecord};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+// blocks are one sided, but `is_blocked_between` is checked in both directions so that
+// neither user can reach the other while the block exists
+pub struct UserBlock;
+
+impl UserBlock {
+    // blocking someone twice is not an error, the users are removed from each others contacts
+    pub async fn block(
+        pool: &PgPool,
+        blocker_id: Uuid,
+        blocked_id: Uuid,
+    ) -> Result<(), BlockError> {
+        if blocker_id == blocked_id {
+            return Err(BlockError::CannotBlockSelf);
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let res = sqlx::query!(
+            r#"
+            INSERT INTO user_blocks (blocker_id, blocked_id, created_at)
+            SELECT $1, id, $3 FROM users WHERE id = $2
+            ON CONFLICT (blocker_id, blocked_id) DO NOTHING
+            "#,
+            blocker_id,
+            blocked_id,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if res.rows_affected() == 0 && !Self::has_blocked(&mut *tx, blocker_id, blocked_id).await? {
+            return Err(BlockError::UserNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+            DELETE FROM contacts
+            WHERE (owner_id = $1 AND contact_id = $2) OR (owner_id = $2 AND contact_id = $1)
+            "#,
+            blocker_id,
+            blocked_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn unblock(
+        pool: &PgPool,
+        blocker_id: Uuid,
+        blocked_id: Uuid,
+    ) -> Result<(), BlockError> {
+        sqlx::query!(
+            "DELETE FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2",
+            blocker_id,
+            blocked_id,
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // the users blocked by `blocker_id`, most recently blocked first
+    pub async fn list(pool: &PgPool, blocker_id: Uuid) -> Result<Vec<PublicUserData>, BlockError> {
+        let blocked_users = sqlx::query_as!(
+            PublicUserRecord,
+            r#"
+            SELECT
+                users.id,
+                users.handle,
+                users.display_name,
+                user_avatars.user_id IS NOT NULL AS "has_avatar!",
+                users.status_text
+            FROM user_blocks
+            JOIN users ON users.id = user_blocks.blocked_id
+            LEFT JOIN user_avatars ON user_avatars.user_id = users.id
+            WHERE user_blocks.blocker_id = $1
+            ORDER BY user_blocks.created_at DESC
+            "#,
+            blocker_id,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(blocked_users
+            .into_iter()
+            .map(PublicUserData::from)
+            .collect())
+    }
+
+    pub async fn has_blocked(
+        executor: impl PgExecutor<'_>,
+        blocker_id: Uuid,
+        blocked_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let rec = sqlx::query!(
+            "SELECT blocker_id FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2",
+            blocker_id,
+            blocked_id,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(rec.is_some())
+    }
+
+    // true if either user blocked the other
+    pub async fn is_blocked_between(
+        executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        other_user_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let rec = sqlx::query!(
+            r#"
+            SELECT blocker_id FROM user_blocks
+            WHERE (blocker_id = $1 AND blocked_id = $2) OR (blocker_id = $2 AND blocked_id = $1)
+            LIMIT 1
+            "#,
+            user_id,
+            other_user_id,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(rec.is_some())
+    }
+}
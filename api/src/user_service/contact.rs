@@ -0,0 +1,98 @@
+use super::block::UserBlock;
+use super::error::ContactError;
+use crate::auth_service::user::{PublicUserData, PublicUserRecord};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+// contacts are one sided like an address book, being in someones contacts lets you
+// start conversations with them without going through a message request
+pub struct Contact;
+
+impl Contact {
+    // adding someone twice is not an error
+    pub async fn add(pool: &PgPool, owner_id: Uuid, contact_id: Uuid) -> Result<(), ContactError> {
+        if owner_id == contact_id {
+            return Err(ContactError::CannotAddSelf);
+        }
+
+        if UserBlock::is_blocked_between(pool, owner_id, contact_id).await? {
+            return Err(ContactError::Blocked);
+        }
+
+        let res = sqlx::query!(
+            r#"
+            INSERT INTO contacts (owner_id, contact_id, created_at)
+            SELECT $1, id, $3 FROM users WHERE id = $2
+            ON CONFLICT (owner_id, contact_id) DO NOTHING
+            "#,
+            owner_id,
+            contact_id,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(pool)
+        .await?;
+
+        if res.rows_affected() == 0 && !Self::is_contact(pool, owner_id, contact_id).await? {
+            return Err(ContactError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    pub async fn remove(
+        executor: impl PgExecutor<'_>,
+        owner_id: Uuid,
+        contact_id: Uuid,
+    ) -> Result<(), ContactError> {
+        sqlx::query!(
+            "DELETE FROM contacts WHERE owner_id = $1 AND contact_id = $2",
+            owner_id,
+            contact_id,
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    // sorted by display name, then handle, users without a profile come last
+    pub async fn list(pool: &PgPool, owner_id: Uuid) -> Result<Vec<PublicUserData>, ContactError> {
+        let contacts = sqlx::query_as!(
+            PublicUserRecord,
+            r#"
+            SELECT
+                users.id,
+                users.handle,
+                users.display_name,
+                user_avatars.user_id IS NOT NULL AS "has_avatar!",
+                users.status_text
+            FROM contacts
+            JOIN users ON users.id = contacts.contact_id
+            LEFT JOIN user_avatars ON user_avatars.user_id = users.id
+            WHERE contacts.owner_id = $1
+            ORDER BY LOWER(users.display_name), users.handle, users.id
+            "#,
+            owner_id,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(contacts.into_iter().map(PublicUserData::from).collect())
+    }
+
+    pub async fn is_contact(
+        executor: impl PgExecutor<'_>,
+        owner_id: Uuid,
+        contact_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let rec = sqlx::query!(
+            "SELECT owner_id FROM contacts WHERE owner_id = $1 AND contact_id = $2",
+            owner_id,
+            contact_id,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(rec.is_some())
+    }
+}
@@ -0,0 +1,252 @@
+use crate::server::error::{ApiError, ErrorCode};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use derive_more::From;
+use serde_json::json;
+
+#[derive(Debug, From)]
+pub enum ProfileError {
+    UserNotFound,
+    AvatarNotFound,
+
+    InvalidHandle {
+        requested_handle: String,
+        min_length: usize,
+        max_length: usize,
+    },
+    HandleTaken {
+        requested_handle: String,
+    },
+    InvalidDisplayName {
+        max_length: usize,
+    },
+    BioTooLong {
+        max_length: usize,
+        actual_length: usize,
+    },
+    StatusTooLong {
+        max_length: usize,
+        actual_length: usize,
+    },
+
+    // only png, jpeg, gif and webp images are accepted
+    UnsupportedAvatarType,
+    AvatarTooLarge {
+        max_bytes: usize,
+        actual_bytes: usize,
+    },
+
+    #[from]
+    Database(sqlx::Error),
+}
+
+impl IntoResponse for ProfileError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::UserNotFound => {
+                ApiError::new(StatusCode::NOT_FOUND, ErrorCode::UserNotFound, "User not found.")
+                    .into_response()
+            }
+            Self::AvatarNotFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::AvatarNotFound,
+                "This user has no avatar.",
+            )
+            .into_response(),
+            Self::InvalidHandle {
+                requested_handle,
+                min_length,
+                max_length,
+            } => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidHandle,
+                format!(
+                    "Handle {} is not valid. Handles must be {} to {} characters long and can only contain lowercase letters, numbers and underscores.",
+                    requested_handle, min_length, max_length
+                ),
+            )
+            .with_details(json!({ "min_length": min_length, "max_length": max_length }))
+            .into_response(),
+            Self::HandleTaken { requested_handle } => ApiError::new(
+                StatusCode::CONFLICT,
+                ErrorCode::HandleTaken,
+                format!(
+                    "Handle {} is already taken. Please choose a different one.",
+                    requested_handle
+                ),
+            )
+            .into_response(),
+            Self::InvalidDisplayName { max_length } => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidDisplayName,
+                format!(
+                    "Display name must be at most {} characters long and cannot contain control characters.",
+                    max_length
+                ),
+            )
+            .with_details(json!({ "max_length": max_length }))
+            .into_response(),
+            Self::BioTooLong {
+                max_length,
+                actual_length,
+            } => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::BioTooLong,
+                format!(
+                    "Bio must be at most {} characters long. You provided {} characters.",
+                    max_length, actual_length
+                ),
+            )
+            .with_details(json!({ "max_length": max_length, "actual_length": actual_length }))
+            .into_response(),
+            Self::StatusTooLong {
+                max_length,
+                actual_length,
+            } => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::StatusTooLong,
+                format!(
+                    "Status must be at most {} characters long. You provided {} characters.",
+                    max_length, actual_length
+                ),
+            )
+            .with_details(json!({ "max_length": max_length, "actual_length": actual_length }))
+            .into_response(),
+            Self::UnsupportedAvatarType => ApiError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                ErrorCode::UnsupportedAvatarType,
+                "Avatar must be a png, jpeg, gif or webp image.",
+            )
+            .into_response(),
+            Self::AvatarTooLarge {
+                max_bytes,
+                actual_bytes,
+            } => ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ErrorCode::AvatarTooLarge,
+                format!(
+                    "Avatar must be at most {} bytes. You provided {} bytes.",
+                    max_bytes, actual_bytes
+                ),
+            )
+            .with_details(json!({ "max_bytes": max_bytes, "actual_bytes": actual_bytes }))
+            .into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error in user profile {:?}", e);
+                ApiError::internal().into_response()
+            }
+        }
+    }
+}
+
+#[derive(Debug, From)]
+pub enum UserSearchError {
+    NoUsersFound,
+    QueryTooShort {
+        min_length: usize,
+    },
+
+    #[from]
+    Database(sqlx::Error),
+}
+
+impl IntoResponse for UserSearchError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::NoUsersFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::NoUsersFound,
+                "No users found.",
+            )
+            .into_response(),
+            Self::QueryTooShort { min_length } => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::QueryTooShort,
+                format!(
+                    "Search query must be at least {} characters long.",
+                    min_length
+                ),
+            )
+            .with_details(json!({ "min_length": min_length }))
+            .into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error while searching users {:?}", e);
+                ApiError::internal().into_response()
+            }
+        }
+    }
+}
+
+#[derive(Debug, From)]
+pub enum BlockError {
+    CannotBlockSelf,
+    UserNotFound,
+
+    #[from]
+    Database(sqlx::Error),
+}
+
+impl IntoResponse for BlockError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::CannotBlockSelf => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::CannotBlockSelf,
+                "You cannot block yourself.",
+            )
+            .into_response(),
+            Self::UserNotFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::UserNotFound,
+                "User not found.",
+            )
+            .into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error while blocking user {:?}", e);
+                ApiError::internal().into_response()
+            }
+        }
+    }
+}
+
+#[derive(Debug, From)]
+pub enum ContactError {
+    CannotAddSelf,
+    UserNotFound,
+    // one of the users blocked the other
+    Blocked,
+
+    #[from]
+    Database(sqlx::Error),
+}
+
+impl IntoResponse for ContactError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::CannotAddSelf => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::CannotAddSelf,
+                "You cannot add yourself as a contact.",
+            )
+            .into_response(),
+            Self::UserNotFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::UserNotFound,
+                "User not found.",
+            )
+            .into_response(),
+            Self::Blocked => ApiError::new(
+                StatusCode::FORBIDDEN,
+                ErrorCode::Blocked,
+                "You cannot add this user as a contact.",
+            )
+            .into_response(),
+            Self::Database(e) => {
+                tracing::error!("Database error in contacts {:?}", e);
+                ApiError::internal().into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod block;
+pub mod contact;
+pub mod error;
+pub mod profile;
+pub mod router;
+pub mod search;
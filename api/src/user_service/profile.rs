@@ -0,0 +1,289 @@
+use super::block::UserBlock;
+use super::error::ProfileError;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// what everyone can see about a user, the email is only part of `OwnProfile`
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct UserProfile {
+    pub id: Uuid,
+    pub handle: Option<String>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    pub status_text: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+// the profile of the signed in user
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct OwnProfile {
+    pub email: String,
+    #[serde(flatten)]
+    pub profile: UserProfile,
+}
+
+// body of PATCH /users/me, fields that are left out are not changed
+// an empty string clears the display name, bio or status, a handle cannot be removed once set
+#[derive(Debug, Default, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ProfileUpdate {
+    pub handle: Option<String>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub status_text: Option<String>,
+}
+
+pub struct Avatar {
+    pub content_type: String,
+    pub data: Vec<u8>,
+    pub updated_at: NaiveDateTime,
+}
+
+impl UserProfile {
+    pub const MIN_HANDLE_LENGTH: usize = 3;
+    pub const MAX_HANDLE_LENGTH: usize = 30;
+    pub const MAX_DISPLAY_NAME_LENGTH: usize = 50;
+    pub const MAX_BIO_LENGTH: usize = 500;
+    pub const MAX_STATUS_LENGTH: usize = 100;
+    pub const MAX_AVATAR_BYTES: usize = 1024 * 1024;
+
+    // the url of the avatar route under /v1, see `user_service::router::get_avatar_service`
+    pub fn avatar_url(user_id: Uuid) -> String {
+        format!("/v1/users/{}/avatar", user_id)
+    }
+
+    pub async fn get(pool: &PgPool, user_id: Uuid) -> Result<UserProfile, ProfileError> {
+        let rec = sqlx::query!(
+            r#"
+            SELECT
+                users.id,
+                users.handle,
+                users.display_name,
+                users.bio,
+                user_avatars.user_id IS NOT NULL AS "has_avatar!",
+                users.status_text,
+                users.created_at
+            FROM users
+            LEFT JOIN user_avatars ON user_avatars.user_id = users.id
+            WHERE users.id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ProfileError::UserNotFound)?;
+
+        Ok(UserProfile {
+            id: rec.id,
+            handle: rec.handle,
+            display_name: rec.display_name,
+            bio: rec.bio,
+            avatar_url: rec.has_avatar.then(|| Self::avatar_url(rec.id)),
+            status_text: rec.status_text,
+            created_at: rec.created_at,
+        })
+    }
+
+    // someone that was blocked by the user cannot see their profile or status anymore
+    pub async fn get_for_viewer(
+        pool: &PgPool,
+        viewer_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<UserProfile, ProfileError> {
+        if UserBlock::has_blocked(pool, user_id, viewer_id).await? {
+            return Err(ProfileError::UserNotFound);
+        }
+
+        Self::get(pool, user_id).await
+    }
+
+    pub async fn get_own(pool: &PgPool, user_id: Uuid) -> Result<OwnProfile, ProfileError> {
+        let profile = Self::get(pool, user_id).await?;
+        let rec = sqlx::query!("SELECT email FROM users WHERE id = $1", user_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or(ProfileError::UserNotFound)?;
+
+        Ok(OwnProfile {
+            email: rec.email,
+            profile,
+        })
+    }
+
+    pub async fn update(
+        pool: &PgPool,
+        user_id: Uuid,
+        update: ProfileUpdate,
+    ) -> Result<OwnProfile, ProfileError> {
+        let current = Self::get(pool, user_id).await?;
+
+        let handle = match update.handle {
+            Some(handle) => Some(Self::validate_handle(&handle)?),
+            None => current.handle,
+        };
+        let display_name = match update.display_name {
+            Some(display_name) => Self::validate_display_name(&display_name)?,
+            None => current.display_name,
+        };
+        let bio = match update.bio {
+            Some(bio) => Self::validate_bio(&bio)?,
+            None => current.bio,
+        };
+        let status_text = match update.status_text {
+            Some(status_text) => Self::validate_status(&status_text)?,
+            None => current.status_text,
+        };
+
+        let res = sqlx::query!(
+            r#"
+            UPDATE users
+            SET handle = $2, display_name = $3, bio = $4, status_text = $5
+            WHERE id = $1
+            "#,
+            user_id,
+            handle,
+            display_name,
+            bio,
+            status_text,
+        )
+        .execute(pool)
+        .await;
+
+        match res {
+            Ok(_) => Self::get_own(pool, user_id).await,
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                Err(ProfileError::HandleTaken {
+                    requested_handle: handle.unwrap_or_default(),
+                })
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // the content type is detected from the image itself instead of trusting the request header
+    pub async fn set_avatar(pool: &PgPool, user_id: Uuid, data: &[u8]) -> Result<(), ProfileError> {
+        if data.len() > Self::MAX_AVATAR_BYTES {
+            return Err(ProfileError::AvatarTooLarge {
+                max_bytes: Self::MAX_AVATAR_BYTES,
+                actual_bytes: data.len(),
+            });
+        }
+
+        let content_type =
+            Self::detect_image_type(data).ok_or(ProfileError::UnsupportedAvatarType)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_avatars (user_id, content_type, data, updated_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id)
+            DO UPDATE SET content_type = $2, data = $3, updated_at = $4
+            "#,
+            user_id,
+            content_type,
+            data,
+            sqlx::types::chrono::Utc::now().naive_utc(),
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_avatar(pool: &PgPool, user_id: Uuid) -> Result<(), ProfileError> {
+        sqlx::query!("DELETE FROM user_avatars WHERE user_id = $1", user_id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_avatar(pool: &PgPool, user_id: Uuid) -> Result<Avatar, ProfileError> {
+        sqlx::query_as!(
+            Avatar,
+            "SELECT content_type, data, updated_at FROM user_avatars WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ProfileError::AvatarNotFound)
+    }
+
+    // validation helpers, they return the value that should be stored
+    pub fn validate_handle(handle: &str) -> Result<String, ProfileError> {
+        let handle = handle.trim().trim_start_matches('@').to_lowercase();
+        let length = handle.chars().count();
+
+        let valid_chars = handle
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+        if !valid_chars || !(Self::MIN_HANDLE_LENGTH..=Self::MAX_HANDLE_LENGTH).contains(&length) {
+            return Err(ProfileError::InvalidHandle {
+                requested_handle: handle,
+                min_length: Self::MIN_HANDLE_LENGTH,
+                max_length: Self::MAX_HANDLE_LENGTH,
+            });
+        }
+
+        Ok(handle)
+    }
+
+    pub fn validate_display_name(display_name: &str) -> Result<Option<String>, ProfileError> {
+        let display_name = display_name.trim();
+
+        if display_name.chars().count() > Self::MAX_DISPLAY_NAME_LENGTH
+            || display_name.chars().any(char::is_control)
+        {
+            return Err(ProfileError::InvalidDisplayName {
+                max_length: Self::MAX_DISPLAY_NAME_LENGTH,
+            });
+        }
+
+        Ok(Some(display_name.to_string()).filter(|name| !name.is_empty()))
+    }
+
+    pub fn validate_bio(bio: &str) -> Result<Option<String>, ProfileError> {
+        let bio = bio.trim();
+        let length = bio.chars().count();
+
+        if length > Self::MAX_BIO_LENGTH {
+            return Err(ProfileError::BioTooLong {
+                max_length: Self::MAX_BIO_LENGTH,
+                actual_length: length,
+            });
+        }
+
+        Ok(Some(bio.to_string()).filter(|bio| !bio.is_empty()))
+    }
+
+    pub fn validate_status(status_text: &str) -> Result<Option<String>, ProfileError> {
+        let status_text = status_text.trim();
+        let length = status_text.chars().count();
+
+        if length > Self::MAX_STATUS_LENGTH {
+            return Err(ProfileError::StatusTooLong {
+                max_length: Self::MAX_STATUS_LENGTH,
+                actual_length: length,
+            });
+        }
+
+        Ok(Some(status_text.to_string()).filter(|status| !status.is_empty()))
+    }
+
+    fn detect_image_type(data: &[u8]) -> Option<&'static str> {
+        if data.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+            Some("image/png")
+        } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some("image/jpeg")
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some("image/gif")
+        } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            Some("image/webp")
+        } else {
+            None
+        }
+    }
+}
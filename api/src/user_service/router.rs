@@ -0,0 +1,414 @@
+use super::block::UserBlock;
+use super::contact::Contact;
+use super::profile::{OwnProfile, ProfileUpdate, UserProfile};
+use super::search::{Discoverability, SearchPage, SearchQuery, UserDirectory};
+use crate::{
+    auth_service::{claims::JwtClaims, user::PublicUserData},
+    server::{
+        error::ErrorBody,
+        extract::{Json, Path, Query},
+        AppState,
+    },
+};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+pub fn user_routes(state: AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        // profile of the signed in user, PATCH only changes the fields that are sent
+        .routes(routes!(get_own_profile_service, update_profile_service))
+        // the body is the raw image, png, jpeg, gif and webp are accepted
+        .routes(routes!(upload_avatar_service, delete_avatar_service))
+        // who can find the signed in user through search and email lookup
+        .routes(routes!(
+            get_discoverability_service,
+            set_discoverability_service
+        ))
+        .routes(routes!(list_contacts_service))
+        .routes(routes!(add_contact_service, remove_contact_service))
+        .routes(routes!(list_blocks_service))
+        .routes(routes!(block_user_service, unblock_user_service))
+        .routes(routes!(search_service))
+        .routes(routes!(lookup_service))
+        .routes(routes!(get_profile_service))
+        .routes(routes!(get_avatar_service))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "users",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The profile of the signed in user", body = OwnProfile),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+    )
+)]
+pub async fn get_own_profile_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+) -> impl IntoResponse {
+    match UserProfile::get_own(&state.pool, claims.user_id).await {
+        Ok(profile) => (StatusCode::OK, Json(profile)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/me",
+    tag = "users",
+    security(("jwt" = [])),
+    request_body = ProfileUpdate,
+    responses(
+        (status = 200, description = "The updated profile", body = OwnProfile),
+        (status = 400, description = "invalid_handle, invalid_display_name, bio_too_long or status_too_long", body = ErrorBody),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 409, description = "handle_taken", body = ErrorBody),
+    )
+)]
+pub async fn update_profile_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Json(update): Json<ProfileUpdate>,
+) -> impl IntoResponse {
+    match UserProfile::update(&state.pool, claims.user_id, update).await {
+        Ok(profile) => (StatusCode::OK, Json(profile)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "users",
+    security(("jwt" = [])),
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Users whose handle or display name matches", body = SearchPage),
+        (status = 400, description = "query_too_short", body = ErrorBody),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+    )
+)]
+pub async fn search_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Query(query): Query<SearchQuery>,
+) -> impl IntoResponse {
+    match UserDirectory::search(&state.pool, claims.user_id, &query).await {
+        Ok(page) => (StatusCode::OK, Json(page)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Serialize, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LookupQuery {
+    pub email: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/lookup",
+    tag = "users",
+    security(("jwt" = [])),
+    params(LookupQuery),
+    responses(
+        (status = 200, description = "The user with exactly this email", body = PublicUserData),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 404, description = "no_users_found", body = ErrorBody),
+    )
+)]
+pub async fn lookup_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Query(query): Query<LookupQuery>,
+) -> impl IntoResponse {
+    match UserDirectory::find_by_email(&state.pool, claims.user_id, &query.email).await {
+        Ok(user) => (StatusCode::OK, Json(user)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/discoverability",
+    tag = "users",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, body = Discoverability),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+    )
+)]
+pub async fn get_discoverability_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+) -> impl IntoResponse {
+    match UserDirectory::get_discoverability(&state.pool, claims.user_id).await {
+        Ok(discoverability) => (StatusCode::OK, Json(discoverability)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/me/discoverability",
+    tag = "users",
+    security(("jwt" = [])),
+    request_body = Discoverability,
+    responses(
+        (status = 200, body = Discoverability),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+    )
+)]
+pub async fn set_discoverability_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Json(discoverability): Json<Discoverability>,
+) -> impl IntoResponse {
+    match UserDirectory::set_discoverability(&state.pool, claims.user_id, discoverability).await {
+        Ok(discoverability) => (StatusCode::OK, Json(discoverability)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/contacts",
+    tag = "users",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, body = Vec<PublicUserData>),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+    )
+)]
+pub async fn list_contacts_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+) -> impl IntoResponse {
+    match Contact::list(&state.pool, claims.user_id).await {
+        Ok(contacts) => (StatusCode::OK, Json(contacts)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/me/contacts/{id}",
+    tag = "users",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the user")),
+    responses(
+        (status = 200, description = "Contact added", body = String),
+        (status = 400, description = "cannot_add_self", body = ErrorBody),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 404, description = "user_not_found", body = ErrorBody),
+    )
+)]
+pub async fn add_contact_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match Contact::add(&state.pool, claims.user_id, id).await {
+        Ok(()) => (StatusCode::OK, "Contact added").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/me/contacts/{id}",
+    tag = "users",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the user")),
+    responses(
+        (status = 200, description = "Contact removed", body = String),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+    )
+)]
+pub async fn remove_contact_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match Contact::remove(&state.pool, claims.user_id, id).await {
+        Ok(()) => (StatusCode::OK, "Contact removed").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/blocks",
+    tag = "users",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, body = Vec<PublicUserData>),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+    )
+)]
+pub async fn list_blocks_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+) -> impl IntoResponse {
+    match UserBlock::list(&state.pool, claims.user_id).await {
+        Ok(blocked_users) => (StatusCode::OK, Json(blocked_users)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/{id}/block",
+    tag = "users",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the user")),
+    responses(
+        (status = 200, description = "User blocked", body = String),
+        (status = 400, description = "cannot_block_self", body = ErrorBody),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 404, description = "user_not_found", body = ErrorBody),
+    )
+)]
+pub async fn block_user_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match UserBlock::block(&state.pool, claims.user_id, id).await {
+        Ok(()) => (StatusCode::OK, "User blocked").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}/block",
+    tag = "users",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the user")),
+    responses(
+        (status = 200, description = "User unblocked", body = String),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+    )
+)]
+pub async fn unblock_user_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match UserBlock::unblock(&state.pool, claims.user_id, id).await {
+        Ok(()) => (StatusCode::OK, "User unblocked").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "users",
+    security(("jwt" = [])),
+    params(("id" = Uuid, Path, description = "The id of the user")),
+    responses(
+        (status = 200, body = UserProfile),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 404, description = "user_not_found, also when one of the users blocked the other", body = ErrorBody),
+    )
+)]
+pub async fn get_profile_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match UserProfile::get_for_viewer(&state.pool, claims.user_id, id).await {
+        Ok(profile) => (StatusCode::OK, Json(profile)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/me/avatar",
+    tag = "users",
+    security(("jwt" = [])),
+    request_body(content = Vec<u8>, content_type = "image/*"),
+    responses(
+        (status = 200, description = "The updated profile", body = OwnProfile),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+        (status = 413, description = "avatar_too_large", body = ErrorBody),
+        (status = 415, description = "unsupported_avatar_type", body = ErrorBody),
+    )
+)]
+pub async fn upload_avatar_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+    body: Bytes,
+) -> impl IntoResponse {
+    let pool = &state.pool;
+
+    match UserProfile::set_avatar(pool, claims.user_id, &body).await {
+        Ok(()) => match UserProfile::get_own(pool, claims.user_id).await {
+            Ok(profile) => (StatusCode::OK, Json(profile)).into_response(),
+            Err(e) => e.into_response(),
+        },
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/me/avatar",
+    tag = "users",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "Avatar removed", body = String),
+        (status = 401, description = "An invalid token", body = ErrorBody),
+    )
+)]
+pub async fn delete_avatar_service(
+    State(state): State<AppState>,
+    claims: JwtClaims,
+) -> impl IntoResponse {
+    match UserProfile::remove_avatar(&state.pool, claims.user_id).await {
+        Ok(()) => (StatusCode::OK, "Avatar removed").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}/avatar",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "The id of the user")),
+    responses(
+        (status = 200, description = "The image", body = Vec<u8>, content_type = "image/*"),
+        (status = 404, description = "avatar_not_found", body = ErrorBody),
+    )
+)]
+// not authenticated so the url can be used directly as an <img> src
+pub async fn get_avatar_service(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match UserProfile::get_avatar(&state.pool, id).await {
+        Ok(avatar) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, avatar.content_type),
+                (header::CACHE_CONTROL, "public, max-age=300".to_string()),
+            ],
+            avatar.data,
+        )
+            .into_response(),
+        Err(e) => e.into_response(),
+    }
+}
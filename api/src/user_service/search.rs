@@ -0,0 +1,188 @@
+use super::error::UserSearchError;
+use crate::auth_service::user::{PublicUserData, PublicUserRecord};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// query string of GET /users/search
+#[derive(Debug, Serialize, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct SearchPage {
+    pub results: Vec<PublicUserData>,
+    // pass this as `offset` to get the next page, missing on the last page
+    pub next_offset: Option<i64>,
+}
+
+// whether the user shows up in the directory search and whether others can find them by their exact email
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Discoverability {
+    pub searchable: bool,
+    pub discoverable_by_email: bool,
+}
+
+// the directory only searches handles and display names, emails can only be looked up exactly
+// so that nobody can enumerate the email addresses of other users
+pub struct UserDirectory;
+
+impl UserDirectory {
+    pub const MIN_QUERY_LENGTH: usize = 2;
+    pub const DEFAULT_LIMIT: i64 = 20;
+    pub const MAX_LIMIT: i64 = 50;
+
+    // matches handles and display names containing the query, the closest matches come first
+    // users that turned off `searchable`, inactive accounts, blocked users and the searching user themselves are left out
+    pub async fn search(
+        pool: &PgPool,
+        searcher_id: Uuid,
+        query: &SearchQuery,
+    ) -> Result<SearchPage, UserSearchError> {
+        let term = query.q.trim().trim_start_matches('@').to_lowercase();
+        if term.chars().count() < Self::MIN_QUERY_LENGTH {
+            return Err(UserSearchError::QueryTooShort {
+                min_length: Self::MIN_QUERY_LENGTH,
+            });
+        }
+
+        let limit = query
+            .limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT);
+        let offset = query.offset.unwrap_or(0).max(0);
+
+        // one more row than requested tells us if there is another page
+        let mut results = sqlx::query_as!(
+            PublicUserRecord,
+            r#"
+            SELECT
+                users.id,
+                users.handle,
+                users.display_name,
+                user_avatars.user_id IS NOT NULL AS "has_avatar!",
+                users.status_text
+            FROM users
+            LEFT JOIN user_avatars ON user_avatars.user_id = users.id
+            WHERE users.searchable
+                AND users.account_status = 'active'
+                AND users.id <> $1
+                AND NOT EXISTS (
+                    SELECT 1 FROM user_blocks
+                    WHERE (blocker_id = $1 AND blocked_id = users.id)
+                        OR (blocker_id = users.id AND blocked_id = $1)
+                )
+                AND (LOWER(users.handle) LIKE $2 OR LOWER(users.display_name) LIKE $2)
+            ORDER BY
+                GREATEST(
+                    similarity(LOWER(users.handle), $3),
+                    similarity(LOWER(users.display_name), $3)
+                ) DESC,
+                users.handle,
+                users.id
+            LIMIT $4 OFFSET $5
+            "#,
+            searcher_id,
+            format!("%{}%", Self::escape_like(&term)),
+            term,
+            limit + 1,
+            offset,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let next_offset = if results.len() as i64 > limit {
+            results.truncate(limit as usize);
+            Some(offset + limit)
+        } else {
+            None
+        };
+
+        Ok(SearchPage {
+            results: results.into_iter().map(PublicUserData::from).collect(),
+            next_offset,
+        })
+    }
+
+    // unknown emails, blocked users and users that turned off `discoverable_by_email` give the same error
+    pub async fn find_by_email(
+        pool: &PgPool,
+        searcher_id: Uuid,
+        email: &str,
+    ) -> Result<PublicUserData, UserSearchError> {
+        sqlx::query_as!(
+            PublicUserRecord,
+            r#"
+            SELECT
+                users.id,
+                users.handle,
+                users.display_name,
+                user_avatars.user_id IS NOT NULL AS "has_avatar!",
+                users.status_text
+            FROM users
+            LEFT JOIN user_avatars ON user_avatars.user_id = users.id
+            WHERE LOWER(users.email) = LOWER($2)
+                AND (users.discoverable_by_email OR users.id = $1)
+                AND users.account_status = 'active'
+                AND NOT EXISTS (
+                    SELECT 1 FROM user_blocks
+                    WHERE (blocker_id = $1 AND blocked_id = users.id)
+                        OR (blocker_id = users.id AND blocked_id = $1)
+                )
+            "#,
+            searcher_id,
+            email.trim(),
+        )
+        .fetch_optional(pool)
+        .await?
+        .map(PublicUserData::from)
+        .ok_or(UserSearchError::NoUsersFound)
+    }
+
+    pub async fn get_discoverability(
+        pool: &PgPool,
+        user_id: Uuid,
+    ) -> Result<Discoverability, UserSearchError> {
+        sqlx::query_as!(
+            Discoverability,
+            "SELECT searchable, discoverable_by_email FROM users WHERE id = $1",
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(UserSearchError::NoUsersFound)
+    }
+
+    pub async fn set_discoverability(
+        pool: &PgPool,
+        user_id: Uuid,
+        discoverability: Discoverability,
+    ) -> Result<Discoverability, UserSearchError> {
+        sqlx::query_as!(
+            Discoverability,
+            r#"
+            UPDATE users
+            SET searchable = $2, discoverable_by_email = $3
+            WHERE id = $1
+            RETURNING searchable, discoverable_by_email
+            "#,
+            user_id,
+            discoverability.searchable,
+            discoverability.discoverable_by_email,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(UserSearchError::NoUsersFound)
+    }
+
+    // `%` and `_` in the query are matched literally
+    fn escape_like(term: &str) -> String {
+        term.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    }
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use api::{
+    admin_service::{error::AdminError, router::admin_routes, Admin, UserFilter},
+    auth_service::{
+        claims::JwtClaims,
+        user::{error::SignInError, Role, User},
+    },
+    config::Config,
+    server::AppState,
+};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use common::{create_user, signin, TestMailer, TestUser, PASSWORD};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+async fn get_stats(app: &Router, token: &str) -> StatusCode {
+    let request = Request::builder()
+        .uri("/admin/stats")
+        .header(header::AUTHORIZATION, token)
+        .body(Body::empty())
+        .unwrap();
+
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+#[sqlx::test]
+async fn test_admin_routes_require_admin(pool: PgPool) {
+    let state = AppState::new(pool.clone(), Arc::new(TestMailer::default()));
+    let app = Router::new()
+        .nest("/admin", admin_routes(state.clone()).into())
+        .with_state(state);
+
+    let TestUser {
+        id: user_id, email, ..
+    } = create_user(&pool).await;
+    let user_token = signin(&pool, &email).await;
+    assert_eq!(
+        JwtClaims::decode(&user_token)
+            .expect("error decoding jwt")
+            .role,
+        Role::User
+    );
+    assert_eq!(get_stats(&app, &user_token).await, StatusCode::FORBIDDEN);
+
+    // the role is part of tokens issued after the change
+    User::set_role(&pool, user_id, Role::Admin)
+        .await
+        .expect("error making user an admin");
+    assert_eq!(get_stats(&app, &user_token).await, StatusCode::FORBIDDEN);
+    let admin_token = signin(&pool, &email).await;
+    assert_eq!(
+        JwtClaims::decode(&admin_token)
+            .expect("error decoding jwt")
+            .role,
+        Role::Admin
+    );
+    assert_eq!(get_stats(&app, &admin_token).await, StatusCode::OK);
+
+    // taking the role away works right away, even for tokens that still claim it
+    User::set_role(&pool, user_id, Role::User)
+        .await
+        .expect("error removing admin role");
+    assert_eq!(get_stats(&app, &admin_token).await, StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn test_admin_user_management(pool: PgPool) {
+    let mailer = TestMailer::default();
+    let admin_id = create_user(&pool).await.id;
+    let TestUser {
+        id: user_id, email, ..
+    } = create_user(&pool).await;
+    User::set_role(&pool, admin_id, Role::Admin)
+        .await
+        .expect("error making user an admin");
+    let token = signin(&pool, &email).await;
+
+    let users = Admin::list_users(
+        &pool,
+        &UserFilter {
+            q: Some(email.to_uppercase()),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("error listing users");
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].id, user_id);
+
+    let stats = Admin::stats(&pool).await.expect("error getting stats");
+    assert_eq!(stats.users, 2);
+    assert_eq!(stats.admins, 1);
+
+    // two sessions, one from signup and one from signin
+    let sessions = Admin::user_sessions(&pool, user_id)
+        .await
+        .expect("error listing sessions");
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions.iter().all(|session| session.revoked_at.is_none()));
+
+    match Admin::suspend_user(&pool, admin_id, admin_id, "oops", None).await {
+        Err(AdminError::CannotTargetSelf) => {}
+        res => panic!(
+            "unexpected result (should be cannot_target_self): {:?}",
+            res
+        ),
+    }
+    let user = Admin::suspend_user(&pool, admin_id, user_id, "spam", None)
+        .await
+        .expect("error suspending user");
+    assert!(user.suspended_at.is_some());
+    assert_eq!(user.suspension_reason.as_deref(), Some("spam"));
+    assert!(JwtClaims::authenticate(&pool, &token).await.is_err());
+    match User::signin(&pool, &email, PASSWORD, None).await {
+        Err(SignInError::AccountSuspended { .. }) => {}
+        res => panic!("unexpected result (should be account_suspended): {:?}", res),
+    }
+
+    let user = Admin::reactivate_user(&pool, user_id)
+        .await
+        .expect("error reactivating user");
+    assert!(user.suspended_at.is_none());
+    let token = signin(&pool, &email).await;
+
+    // a forced reset signs the user out, the old password stops working and a link is emailed
+    Admin::force_password_reset(&pool, &mailer, Config::DEFAULT_PASSWORD_RESET_URL, user_id)
+        .await
+        .expect("error forcing password reset");
+    assert!(JwtClaims::authenticate(&pool, &token).await.is_err());
+    match User::signin(&pool, &email, PASSWORD, None).await {
+        Err(SignInError::WrongPassword { .. }) => {}
+        res => panic!("unexpected result (should be wrong_password): {:?}", res),
+    }
+    {
+        let sent = mailer.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, email);
+        assert!(sent[0].body.contains("?token="));
+    }
+
+    match Admin::get_user(&pool, Uuid::new_v4()).await {
+        Err(AdminError::UserNotFound) => {}
+        res => panic!("unexpected result (should be user_not_found): {:?}", res),
+    }
+}
@@ -0,0 +1,242 @@
+mod common;
+
+use api::{
+    admin_service::router::admin_routes,
+    audit_service::{
+        AuditContext, AuditEvent, AuditEventKind, AuditFilter, AuditLog, NewAuditEvent,
+    },
+    auth_service::{
+        router::auth_routes,
+        user::{Role, User},
+    },
+    mail_service::LogMailer,
+    server::{shutdown::Shutdown, AppState},
+};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use common::{create_user, signin};
+use serde_json::json;
+use sqlx::PgPool;
+use std::{sync::Arc, time::Duration};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+const USER_AGENT: &str = "audit-test/1.0";
+
+async fn post_json(app: &Router, uri: &str, body: serde_json::Value) -> StatusCode {
+    let request = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::USER_AGENT, USER_AGENT)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+#[sqlx::test]
+async fn test_audit_events(pool: PgPool) {
+    let state = AppState::new(pool.clone(), Arc::new(LogMailer));
+    let app = Router::new()
+        .nest("/auth", auth_routes(state.clone()).into())
+        .nest("/admin", admin_routes(state.clone()).into())
+        .with_state(state);
+
+    // signups and signins are recorded with the user agent of the request
+    let email = format!("testuser{}@email.com", Uuid::new_v4());
+    let form = json!({ "email": email, "password": "Password123#" });
+    assert_eq!(
+        post_json(&app, "/auth/signup", form.clone()).await,
+        StatusCode::OK
+    );
+    let user_id = User::get_user_by_email(&pool, &email)
+        .await
+        .expect("error getting user")
+        .id;
+    let wrong_form = json!({ "email": email, "password": "Wrong123#" });
+    assert_eq!(
+        post_json(&app, "/auth/signin", wrong_form).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(post_json(&app, "/auth/signin", form).await, StatusCode::OK);
+
+    let events = AuditLog::query(
+        &pool,
+        &AuditFilter {
+            user_id: Some(user_id),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("error querying audit events");
+    let kinds: Vec<AuditEventKind> = events.iter().map(|event| event.event_type).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            AuditEventKind::SigninSucceeded,
+            AuditEventKind::SigninFailed,
+            AuditEventKind::Signup,
+        ]
+    );
+    assert!(events
+        .iter()
+        .all(|event| event.user_agent.as_deref() == Some(USER_AGENT)));
+    assert_eq!(events[1].details["reason"], "wrong_password");
+    assert_eq!(events[1].details["email"], email.as_str());
+
+    // the email of an unknown account is not kept, it may be a mistyped password
+    let unknown_form = json!({ "email": "Password123#", "password": "Password123#" });
+    assert_eq!(
+        post_json(&app, "/auth/signin", unknown_form).await,
+        StatusCode::UNAUTHORIZED
+    );
+    let events = AuditLog::query(
+        &pool,
+        &AuditFilter {
+            event_type: Some(AuditEventKind::SigninFailed),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("error querying audit events");
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].user_id, None);
+    assert_eq!(events[0].details["reason"], "email_not_found");
+    assert!(events[0].details.get("email").is_none());
+
+    // only admins can query the log, filtered by event type and time range
+    let admin = create_user(&pool).await;
+    let uri = format!(
+        "/admin/audit-events?user_id={}&event_type=signin_failed",
+        user_id
+    );
+    let request = Request::builder()
+        .uri(&uri)
+        .header(header::AUTHORIZATION, &admin.token)
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(
+        app.clone().oneshot(request).await.unwrap().status(),
+        StatusCode::FORBIDDEN
+    );
+
+    User::set_role(&pool, admin.id, Role::Admin)
+        .await
+        .expect("error making user an admin");
+    let admin_token = signin(&pool, &admin.email).await;
+    let request = Request::builder()
+        .uri(&uri)
+        .header(header::AUTHORIZATION, &admin_token)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("error reading body");
+    let events: Vec<AuditEvent> = serde_json::from_slice(&body).expect("error parsing events");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type, AuditEventKind::SigninFailed);
+
+    let future = sqlx::types::chrono::Utc::now().naive_utc() + chrono::Duration::hours(1);
+    let events = AuditLog::query(
+        &pool,
+        &AuditFilter {
+            user_id: Some(user_id),
+            from: Some(future),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("error querying audit events");
+    assert!(events.is_empty());
+
+    // events are kept after the account is deleted
+    User::delete_user_by_id(&pool, user_id)
+        .await
+        .expect("Error deleting test user");
+    User::delete_user_by_id(&pool, admin.id)
+        .await
+        .expect("Error deleting test user");
+    let events = AuditLog::query(
+        &pool,
+        &AuditFilter {
+            user_id: Some(user_id),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("error querying audit events");
+    assert_eq!(events.len(), 3);
+}
+
+#[sqlx::test]
+async fn test_audit_retention(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+
+    AuditLog::try_record(
+        &pool,
+        NewAuditEvent::new(AuditEventKind::Signup, &AuditContext::default()).user(user_id),
+    )
+    .await
+    .expect("error recording audit event");
+
+    // events cannot be changed once they are written
+    let res = sqlx::query!(
+        "UPDATE audit_events SET event_type = 'account_deleted' WHERE user_id = $1",
+        user_id
+    )
+    .execute(&pool)
+    .await;
+    assert!(res.is_err());
+
+    let old = sqlx::types::chrono::Utc::now().naive_utc() - chrono::Duration::days(40);
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_events (id, event_type, user_id, details, created_at)
+        VALUES ($1, 'signin_succeeded', $2, '{}', $3)
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        old,
+    )
+    .execute(&pool)
+    .await
+    .expect("error inserting old audit event");
+
+    let deleted = AuditLog::purge_older_than(&pool, 30)
+        .await
+        .expect("error purging audit events");
+    assert_eq!(deleted, 1);
+
+    let filter = AuditFilter {
+        user_id: Some(user_id),
+        ..Default::default()
+    };
+    let events = AuditLog::query(&pool, &filter)
+        .await
+        .expect("error querying audit events");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type, AuditEventKind::Signup);
+}
+
+#[sqlx::test]
+async fn test_retention_job_stops_on_shutdown(pool: PgPool) {
+    let shutdown = Shutdown::new();
+    let job = AuditLog::spawn_retention_job(pool.clone(), 30, shutdown.clone());
+
+    shutdown.trigger();
+    tokio::time::timeout(Duration::from_secs(5), job)
+        .await
+        .expect("the retention job should stop after the shutdown")
+        .expect("the retention job panicked");
+
+    // nothing uses the pool anymore, so closing it does not wait
+    tokio::time::timeout(Duration::from_secs(5), pool.close())
+        .await
+        .expect("the pool should close right away");
+}
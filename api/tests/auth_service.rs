@@ -1,20 +1,44 @@
+mod common;
+
 use api::{
     auth_service::{
-        claims::*,
-        user::{error::*, *},
+        claims::{error::ClaimsError, *},
+        mfa::{error::MfaError, Totp},
+        password_reset::{error::PasswordResetError, PasswordReset},
+        session::Session,
+        signin_throttle::SignInThrottle,
+        user::{account_state::AccountStatus, error::*, *},
     },
-    db_service::get_connection_pool,
+    conversation_service::{conversation::Conversation, error::ConversationError},
 };
 
+use axum::http::StatusCode;
 use chrono::Timelike;
+use common::{TestApp, TestMailer};
+use serde_json::json;
+use sqlx::PgPool;
+use totp_rs::{Algorithm, Secret, TOTP};
 use uuid::Uuid;
 
+const RESET_URL: &str = "https://chat.example.com/reset-password";
+
 pub fn truncate_created_at(user: &mut User) {
     // This is for testing the time of creation of the user
     // use it when comparing results so that the precision matches
     user.created_at = user.created_at.with_nanosecond(0).unwrap();
 }
 
+// unwraps the token of a signin that did not require 2fa
+pub fn authenticated_token(outcome: SignInOutcome) -> JwtTokenString {
+    match outcome {
+        SignInOutcome::Authenticated(token) => token,
+        outcome => panic!(
+            "unexpected signin outcome (should be authenticated): {:?}",
+            outcome
+        ),
+    }
+}
+
 #[test]
 fn test_jwt() {
     let user_id = Uuid::new_v4();
@@ -74,17 +98,15 @@ fn test_validate_password_strength() {
     }
 }
 
-#[tokio::test]
-async fn test_signup_signin() {
-    let pool = get_connection_pool().await.expect("error getting pg pool");
-
+#[sqlx::test]
+async fn test_signup_signin(pool: PgPool) {
     // test sign up signin and check that the db matches
     // test creating a user with the same email
     // test signing in user
     // delete the test user
 
     // test sign up
-    let email: String = format!("TestUser{}@email.com", Uuid::new_v4().to_string());
+    let email: String = format!("TestUser{}@email.com", Uuid::new_v4());
     let password = "Password123#";
     let token = User::signup(&pool, &email, password)
         .await
@@ -117,26 +139,29 @@ async fn test_signup_signin() {
     }
 
     //successful signin with correct credentials
-    let signin_jwt = User::signin(&pool, &user.email, password)
-        .await
-        .expect("error signing in user");
+    let signin_jwt = authenticated_token(
+        User::signin(&pool, &user.email, password, None)
+            .await
+            .expect("error signing in user"),
+    );
     let claims = JwtClaims::decode(&signin_jwt).expect("Error decoding jwt to claims");
 
     assert_eq!(user.id, claims.user_id);
 
     // test for wrong password
-    let wrong_password_signin_res = User::signin(&pool, &user.email, "WrongPassword").await;
+    let wrong_password_signin_res = User::signin(&pool, &user.email, "WrongPassword", None).await;
     assert!(wrong_password_signin_res.is_err());
     if let Err(err) = wrong_password_signin_res {
         match err {
-            SignInError::WrongPassword => {}
+            SignInError::WrongPassword { .. } => {}
             err => panic!("unexpected error (should be wrong_password): {:?}", err),
         }
     }
 
     // test for email that does not exist
-    let not_found_test_email = format!("invalid_email_{}@email.com", Uuid::new_v4().to_string());
-    let not_found_test_user = User::signin(&pool, &not_found_test_email, &user.password).await;
+    let not_found_test_email = format!("invalid_email_{}@email.com", Uuid::new_v4());
+    let not_found_test_user =
+        User::signin(&pool, &not_found_test_email, &user.password, None).await;
     assert!(not_found_test_user.is_err());
     if let Err(err) = not_found_test_user {
         match err {
@@ -153,3 +178,674 @@ async fn test_signup_signin() {
     truncate_created_at(&mut delete_user_res);
     assert_eq!(user, delete_user_res);
 }
+
+// refreshing keeps the session, a revoked session cannot be refreshed
+#[sqlx::test]
+async fn test_refresh_session(pool: PgPool) {
+    let email = format!("testuser{}@email.com", Uuid::new_v4());
+    let token = User::signup(&pool, &email, "Password123#")
+        .await
+        .expect("error signing up user");
+    let claims = JwtClaims::authenticate(&pool, &token)
+        .await
+        .expect("error authenticating token");
+
+    let refreshed = Session::refresh(&pool, &claims)
+        .await
+        .expect("error refreshing session");
+    let refreshed_claims = JwtClaims::authenticate(&pool, &refreshed)
+        .await
+        .expect("refreshed token is not accepted");
+    assert_eq!(refreshed_claims.session_id, claims.session_id);
+    assert!(refreshed_claims.exp() >= claims.exp());
+    let sessions = Session::list_for_user(&pool, claims.user_id)
+        .await
+        .expect("error listing sessions");
+    assert_eq!(sessions.len(), 1);
+
+    Session::revoke_all_for_user(&pool, claims.user_id)
+        .await
+        .expect("error revoking sessions");
+    match Session::refresh(&pool, &claims).await {
+        Err(ClaimsError::SessionRevoked) => {}
+        res => panic!("unexpected result (should be session_revoked): {:?}", res),
+    }
+}
+
+#[sqlx::test]
+async fn test_password_reset(pool: PgPool) {
+    let mailer = TestMailer::default();
+
+    let email = format!("TestUser{}@email.com", Uuid::new_v4());
+    let password = "Password123#";
+    let new_password = "NewPassword456$";
+    let old_token = User::signup(&pool, &email, password)
+        .await
+        .expect("error signing up user");
+    let user_id = JwtClaims::authenticate(&pool, &old_token)
+        .await
+        .expect("error authenticating new token")
+        .user_id;
+
+    // no email is sent for an unknown address, but it is not an error either
+    let unknown_email = format!("invalid_email_{}@email.com", Uuid::new_v4());
+    PasswordReset::request(&pool, &mailer, RESET_URL, &unknown_email)
+        .await
+        .expect("unknown email should not return an error");
+    assert!(mailer.sent.lock().unwrap().is_empty());
+
+    PasswordReset::request(&pool, &mailer, RESET_URL, &email)
+        .await
+        .expect("error requesting password reset");
+    let sent = mailer
+        .sent
+        .lock()
+        .unwrap()
+        .pop()
+        .expect("no email was sent");
+    assert_eq!(sent.to, email);
+    assert!(sent.body.contains(&format!("{}?token=", RESET_URL)));
+    let reset_token = sent
+        .body
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("reset link not found in email")
+        .to_string();
+
+    // the new password has to pass the same strength rules as signup
+    match PasswordReset::reset(&pool, &reset_token, "weak").await {
+        Err(PasswordResetError::InvalidPassword(SignUpError::PasswordTooShort { .. })) => {}
+        res => panic!(
+            "unexpected result (should be password_too_short): {:?}",
+            res
+        ),
+    }
+
+    let reset_user_id = PasswordReset::reset(&pool, &reset_token, new_password)
+        .await
+        .expect("error resetting password");
+    assert_eq!(reset_user_id, user_id);
+
+    // tokens are single use
+    match PasswordReset::reset(&pool, &reset_token, new_password).await {
+        Err(PasswordResetError::InvalidToken) => {}
+        res => panic!("unexpected result (should be invalid_token): {:?}", res),
+    }
+
+    // existing sessions are revoked
+    match JwtClaims::authenticate(&pool, &old_token).await {
+        Err(ClaimsError::SessionRevoked) => {}
+        res => panic!("unexpected result (should be session_revoked): {:?}", res),
+    }
+
+    match User::signin(&pool, &email, password, None).await {
+        Err(SignInError::WrongPassword { .. }) => {}
+        res => panic!("unexpected result (should be wrong_password): {:?}", res),
+    }
+    User::signin(&pool, &email, new_password, None)
+        .await
+        .expect("error signing in with the new password");
+}
+
+#[sqlx::test]
+async fn test_change_password_and_delete_account(pool: PgPool) {
+    let email = format!("TestUser{}@email.com", Uuid::new_v4());
+    let password = "Password123#";
+    let new_password = "NewPassword456$";
+    let first_token = User::signup(&pool, &email, password)
+        .await
+        .expect("error signing up user");
+    let second_token = authenticated_token(
+        User::signin(&pool, &email, password, None)
+            .await
+            .expect("error signing in user"),
+    );
+    let claims = JwtClaims::authenticate(&pool, &second_token)
+        .await
+        .expect("error authenticating token");
+
+    // the current password has to be correct
+    match User::change_password(
+        &pool,
+        claims.user_id,
+        claims.session_id,
+        "Wrong1!",
+        new_password,
+    )
+    .await
+    {
+        Err(AccountError::Verification(SignInError::WrongPassword { .. })) => {}
+        res => panic!("unexpected result (should be wrong_password): {:?}", res),
+    }
+
+    match User::change_password(&pool, claims.user_id, claims.session_id, password, "weak").await {
+        Err(AccountError::InvalidPassword(SignUpError::PasswordTooShort { .. })) => {}
+        res => panic!(
+            "unexpected result (should be password_too_short): {:?}",
+            res
+        ),
+    }
+
+    User::change_password(
+        &pool,
+        claims.user_id,
+        claims.session_id,
+        password,
+        new_password,
+    )
+    .await
+    .expect("error changing password");
+
+    // the session that changed the password stays signed in, the others are revoked
+    JwtClaims::authenticate(&pool, &second_token)
+        .await
+        .expect("current session should stay valid");
+    match JwtClaims::authenticate(&pool, &first_token).await {
+        Err(ClaimsError::SessionRevoked) => {}
+        res => panic!("unexpected result (should be session_revoked): {:?}", res),
+    }
+
+    // deleting the account also removes its conversations and messages
+    let other_user_id = JwtClaims::decode(
+        &User::signup(
+            &pool,
+            &format!("TestUser{}@email.com", Uuid::new_v4()),
+            password,
+        )
+        .await
+        .expect("error signing up user"),
+    )
+    .expect("error decoding jwt")
+    .user_id;
+    let conversation_id = Conversation::start(&pool, claims.user_id, other_user_id)
+        .await
+        .expect("error starting conversation");
+    Conversation::accept_request(&pool, other_user_id, conversation_id)
+        .await
+        .expect("error accepting message request");
+    Conversation::send_message(&pool, other_user_id, conversation_id, "hello")
+        .await
+        .expect("error sending message");
+
+    match User::delete_account(&pool, claims.user_id, password, None).await {
+        Err(AccountError::Verification(SignInError::WrongPassword { .. })) => {}
+        res => panic!("unexpected result (should be wrong_password): {:?}", res),
+    }
+
+    let deleted_user = User::delete_account(&pool, claims.user_id, new_password, None)
+        .await
+        .expect("error deleting account");
+    assert_eq!(deleted_user.email, email);
+
+    match User::get_user_by_id(&pool, claims.user_id).await {
+        Err(sqlx::Error::RowNotFound) => {}
+        res => panic!("unexpected result (should be row_not_found): {:?}", res),
+    }
+    let other_conversations = Conversation::get_conversations_with_user_id(&pool, other_user_id)
+        .await
+        .expect("error getting conversations");
+    assert!(other_conversations.is_empty());
+    let messages = Conversation::get_all_messages(&pool, conversation_id)
+        .await
+        .expect("error getting messages");
+    assert!(messages.is_empty());
+
+    match JwtClaims::authenticate(&pool, &second_token).await {
+        Err(ClaimsError::SessionRevoked) => {}
+        res => panic!("unexpected result (should be session_revoked): {:?}", res),
+    }
+}
+
+#[sqlx::test]
+async fn test_account_states(pool: PgPool) {
+    let email = format!("TestUser{}@email.com", Uuid::new_v4());
+    let password = "Password123#";
+    let token = User::signup(&pool, &email, password)
+        .await
+        .expect("error signing up user");
+    let user_id = JwtClaims::decode(&token)
+        .expect("error decoding jwt")
+        .user_id;
+    let other_token = User::signup(
+        &pool,
+        &format!("TestUser{}@email.com", Uuid::new_v4()),
+        password,
+    )
+    .await
+    .expect("error signing up user");
+    let other_user_id = JwtClaims::decode(&other_token)
+        .expect("error decoding jwt")
+        .user_id;
+    let conversation_id = Conversation::start(&pool, other_user_id, user_id)
+        .await
+        .expect("error starting conversation");
+
+    // deactivating needs the password and signs the user out everywhere
+    match User::deactivate(&pool, user_id, "Wrong1!").await {
+        Err(AccountError::Verification(SignInError::WrongPassword { .. })) => {}
+        res => panic!("unexpected result (should be wrong_password): {:?}", res),
+    }
+    User::deactivate(&pool, user_id, password)
+        .await
+        .expect("error deactivating account");
+    match JwtClaims::authenticate(&pool, &token).await {
+        Err(ClaimsError::SessionRevoked) => {}
+        res => panic!("unexpected result (should be session_revoked): {:?}", res),
+    }
+    match User::signin(&pool, &email, password, None).await {
+        Err(SignInError::AccountDeactivated { .. }) => {}
+        res => panic!(
+            "unexpected result (should be account_deactivated): {:?}",
+            res
+        ),
+    }
+
+    // nobody can message a deactivated account
+    match Conversation::send_message(&pool, other_user_id, conversation_id, "hello").await {
+        Err(ConversationError::RecipientUnavailable) => {}
+        res => panic!(
+            "unexpected result (should be recipient_unavailable): {:?}",
+            res
+        ),
+    }
+
+    // a wrong password does not reactivate the account
+    match User::reactivate_account(&pool, &email, "Wrong1!", None).await {
+        Err(SignInError::WrongPassword { .. }) => {}
+        res => panic!("unexpected result (should be wrong_password): {:?}", res),
+    }
+    let token = authenticated_token(
+        User::reactivate_account(&pool, &email, password, None)
+            .await
+            .expect("error reactivating account"),
+    );
+    JwtClaims::authenticate(&pool, &token)
+        .await
+        .expect("reactivated account should be signed in");
+    assert_eq!(
+        User::account_state(&pool, user_id)
+            .await
+            .expect("error getting account state")
+            .status,
+        AccountStatus::Active
+    );
+
+    // a suspension with an end date cannot be lifted by the user and tells them when it ends
+    let until = sqlx::types::chrono::Utc::now().naive_utc() + chrono::Duration::days(1);
+    let mut conn = pool.acquire().await.expect("error acquiring connection");
+    User::suspend(&mut conn, user_id, "spam", Some(until))
+        .await
+        .expect("error suspending user");
+    match User::reactivate_account(&pool, &email, password, None).await {
+        Err(SignInError::AccountSuspended {
+            reason,
+            until: Some(_),
+            ..
+        }) => assert_eq!(reason.as_deref(), Some("spam")),
+        res => panic!("unexpected result (should be account_suspended): {:?}", res),
+    }
+    match Conversation::send_message(&pool, other_user_id, conversation_id, "hello").await {
+        Err(ConversationError::RecipientUnavailable) => {}
+        res => panic!(
+            "unexpected result (should be recipient_unavailable): {:?}",
+            res
+        ),
+    }
+
+    // once the end date has passed the account is active again
+    let until = sqlx::types::chrono::Utc::now().naive_utc() - chrono::Duration::minutes(1);
+    User::suspend(&mut conn, user_id, "spam", Some(until))
+        .await
+        .expect("error suspending user");
+    drop(conn);
+    let token = authenticated_token(
+        User::signin(&pool, &email, password, None)
+            .await
+            .expect("error signing in after the suspension ended"),
+    );
+    let claims = JwtClaims::authenticate(&pool, &token)
+        .await
+        .expect("error authenticating token");
+    Conversation::accept_request(&pool, user_id, conversation_id)
+        .await
+        .expect("error accepting message request");
+    Conversation::send_message(&pool, claims.user_id, conversation_id, "hello")
+        .await
+        .expect("error sending message");
+}
+
+fn current_totp_code(secret: &str) -> String {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .expect("error decoding totp secret");
+    TOTP::new(Algorithm::SHA1, 6, 1, 30, secret, None, String::new())
+        .expect("error creating totp")
+        .generate_current()
+        .expect("error generating totp code")
+}
+
+#[sqlx::test]
+async fn test_totp_mfa(pool: PgPool) {
+    let email = format!("TestUser{}@email.com", Uuid::new_v4());
+    let password = "Password123#";
+    let token = User::signup(&pool, &email, password)
+        .await
+        .expect("error signing up user");
+    let user_id = JwtClaims::decode(&token)
+        .expect("error decoding jwt")
+        .user_id;
+
+    let enrollment = Totp::begin_enrollment(&pool, user_id)
+        .await
+        .expect("error starting enrollment");
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrollment.otpauth_uri.contains(&enrollment.secret));
+
+    // 2fa is not enforced until the enrollment is confirmed
+    authenticated_token(
+        User::signin(&pool, &email, password, None)
+            .await
+            .expect("error signing in user"),
+    );
+
+    match Totp::confirm_enrollment(&pool, user_id, "000000").await {
+        Err(MfaError::InvalidCode) => {}
+        res => panic!("unexpected result (should be invalid_code): {:?}", res),
+    }
+    let confirmation_code = current_totp_code(&enrollment.secret);
+    let recovery_codes = Totp::confirm_enrollment(&pool, user_id, &confirmation_code)
+        .await
+        .expect("error confirming enrollment")
+        .recovery_codes;
+    assert_eq!(recovery_codes.len(), 10);
+
+    match Totp::begin_enrollment(&pool, user_id).await {
+        Err(MfaError::AlreadyEnabled) => {}
+        res => panic!("unexpected result (should be already_enabled): {:?}", res),
+    }
+
+    // a correct password now only yields a pending token
+    let mfa_token = match User::signin(&pool, &email, password, None)
+        .await
+        .expect("error signing in user")
+    {
+        SignInOutcome::MfaRequired(mfa_token) => mfa_token,
+        outcome => panic!(
+            "unexpected signin outcome (should be mfa_required): {:?}",
+            outcome
+        ),
+    };
+    assert!(JwtClaims::decode(&mfa_token).is_err());
+
+    // the code used to confirm enrollment cannot be replayed
+    match Totp::complete_signin(&pool, &mfa_token, &confirmation_code, None).await {
+        Err(MfaError::InvalidCode) => {}
+        res => panic!("unexpected result (should be invalid_code): {:?}", res),
+    }
+
+    // recovery codes work once, with or without the dashes
+    let recovery_code = recovery_codes[0].replace('-', "").to_uppercase();
+    let session_token = Totp::complete_signin(&pool, &mfa_token, &recovery_code, None)
+        .await
+        .expect("error completing signin with recovery code");
+    let claims = JwtClaims::authenticate(&pool, &session_token)
+        .await
+        .expect("error authenticating session token");
+    assert_eq!(claims.user_id, user_id);
+
+    // the pending token can only be used once
+    match Totp::complete_signin(&pool, &mfa_token, &recovery_codes[1], None).await {
+        Err(MfaError::ChallengeExpired) => {}
+        res => panic!("unexpected result (should be challenge_expired): {:?}", res),
+    }
+
+    // a used recovery code is rejected with a new pending token
+    let mfa_token = match User::signin(&pool, &email, password, None)
+        .await
+        .expect("error signing in user")
+    {
+        SignInOutcome::MfaRequired(mfa_token) => mfa_token,
+        outcome => panic!(
+            "unexpected signin outcome (should be mfa_required): {:?}",
+            outcome
+        ),
+    };
+    match Totp::complete_signin(&pool, &mfa_token, &recovery_codes[0], None).await {
+        Err(MfaError::InvalidCode) => {}
+        res => panic!("unexpected result (should be invalid_code): {:?}", res),
+    }
+
+    // a token that was never issued by signin has no challenge
+    let forged_token = MfaPendingClaims::new(user_id)
+        .encode()
+        .expect("error encoding mfa token");
+    match Totp::complete_signin(&pool, &forged_token, &recovery_codes[2], None).await {
+        Err(MfaError::ChallengeExpired) => {}
+        res => panic!("unexpected result (should be challenge_expired): {:?}", res),
+    }
+
+    // deleting the account needs a code as well
+    match User::delete_account(&pool, user_id, password, None).await {
+        Err(AccountError::Mfa(MfaError::CodeRequired)) => {}
+        res => panic!("unexpected result (should be mfa_code_required): {:?}", res),
+    }
+    match User::delete_account(&pool, user_id, password, Some("000000")).await {
+        Err(AccountError::Mfa(MfaError::InvalidCode)) => {}
+        res => panic!("unexpected result (should be invalid_code): {:?}", res),
+    }
+    User::get_user_by_id(&pool, user_id)
+        .await
+        .expect("account should not be deleted without a code");
+
+    match Totp::disable(&pool, user_id, "WrongPassword1!", &recovery_codes[1]).await {
+        Err(MfaError::Verification(SignInError::WrongPassword { .. })) => {}
+        res => panic!("unexpected result (should be wrong_password): {:?}", res),
+    }
+    Totp::disable(&pool, user_id, password, &recovery_codes[1])
+        .await
+        .expect("error disabling 2fa");
+    authenticated_token(
+        User::signin(&pool, &email, password, None)
+            .await
+            .expect("error signing in user"),
+    );
+}
+
+// the password alone does not reactivate an account with 2fa, the code has to be verified first
+#[sqlx::test]
+async fn test_reactivate_account_with_mfa(pool: PgPool) {
+    let email = format!("TestUser{}@email.com", Uuid::new_v4());
+    let password = "Password123#";
+    let token = User::signup(&pool, &email, password)
+        .await
+        .expect("error signing up user");
+    let user_id = JwtClaims::decode(&token)
+        .expect("error decoding jwt")
+        .user_id;
+    let enrollment = Totp::begin_enrollment(&pool, user_id)
+        .await
+        .expect("error starting enrollment");
+    let recovery_codes =
+        Totp::confirm_enrollment(&pool, user_id, &current_totp_code(&enrollment.secret))
+            .await
+            .expect("error confirming enrollment")
+            .recovery_codes;
+    User::deactivate(&pool, user_id, password)
+        .await
+        .expect("error deactivating account");
+    let account_status = || async {
+        User::account_state(&pool, user_id)
+            .await
+            .expect("error getting account state")
+            .status
+    };
+
+    let mfa_token = match User::reactivate_account(&pool, &email, password, None)
+        .await
+        .expect("error reactivating account")
+    {
+        SignInOutcome::MfaRequired(mfa_token) => mfa_token,
+        outcome => panic!("unexpected outcome (should be mfa_required): {:?}", outcome),
+    };
+    assert_eq!(account_status().await, AccountStatus::Deactivated);
+    match User::signin(&pool, &email, password, None).await {
+        Err(SignInError::AccountDeactivated { .. }) => {}
+        res => panic!(
+            "unexpected result (should be account_deactivated): {:?}",
+            res
+        ),
+    }
+
+    // a wrong code leaves the account deactivated
+    match Totp::complete_signin(&pool, &mfa_token, "000000", None).await {
+        Err(MfaError::InvalidCode) => {}
+        res => panic!("unexpected result (should be invalid_code): {:?}", res),
+    }
+    assert_eq!(account_status().await, AccountStatus::Deactivated);
+
+    // a pending token of a normal signin cannot sign a deactivated account in
+    let signin_token = Totp::start_challenge(&pool, &MfaPendingClaims::new(user_id))
+        .await
+        .expect("error starting mfa challenge");
+    match Totp::complete_signin(&pool, &signin_token, &recovery_codes[0], None).await {
+        Err(MfaError::Verification(SignInError::AccountDeactivated { .. })) => {}
+        res => panic!(
+            "unexpected result (should be account_deactivated): {:?}",
+            res
+        ),
+    }
+    assert_eq!(account_status().await, AccountStatus::Deactivated);
+
+    let session_token = Totp::complete_signin(&pool, &mfa_token, &recovery_codes[1], None)
+        .await
+        .expect("error completing signin");
+    JwtClaims::authenticate(&pool, &session_token)
+        .await
+        .expect("reactivated account should be signed in");
+    assert_eq!(account_status().await, AccountStatus::Active);
+
+    // with 2fa the account is deleted with the password and a code
+    User::delete_account(&pool, user_id, password, Some(&recovery_codes[2]))
+        .await
+        .expect("error deleting account");
+}
+
+#[sqlx::test]
+async fn test_signin_throttle(pool: PgPool) {
+    let email = format!("TestUser{}@email.com", Uuid::new_v4());
+    let password = "Password123#";
+    User::signup(&pool, &email, password)
+        .await
+        .expect("error signing up user");
+
+    // a successful signin clears the failures of the account
+    for _ in 0..4 {
+        User::signin(&pool, &email, "WrongPassword1!", None)
+            .await
+            .expect_err("wrong password should not sign in");
+    }
+    authenticated_token(
+        User::signin(&pool, &email, password, None)
+            .await
+            .expect("error signing in user"),
+    );
+
+    for _ in 0..5 {
+        match User::signin(&pool, &email, "WrongPassword1!", None).await {
+            Err(SignInError::WrongPassword { .. }) => {}
+            res => panic!("unexpected result (should be wrong_password): {:?}", res),
+        }
+    }
+
+    // once locked even the correct password is rejected
+    match User::signin(&pool, &email, password, None).await {
+        Err(SignInError::TooManyAttempts {
+            retry_after_seconds,
+        }) => assert!(retry_after_seconds > 0),
+        res => panic!("unexpected result (should be too_many_attempts): {:?}", res),
+    }
+
+    // emails that do not exist are locked the same way
+    let not_found_email = format!("invalid_email_{}@email.com", Uuid::new_v4());
+    for _ in 0..5 {
+        match User::signin(&pool, &not_found_email, password, None).await {
+            Err(SignInError::EmailNotFound { .. }) => {}
+            res => panic!("unexpected result (should be email_not_found): {:?}", res),
+        }
+    }
+    match User::signin(&pool, &not_found_email, password, None).await {
+        Err(SignInError::TooManyAttempts { .. }) => {}
+        res => panic!("unexpected result (should be too_many_attempts): {:?}", res),
+    }
+
+    // an ip that fails for many different accounts is locked for every account
+    let uuid_bytes = Uuid::new_v4().into_bytes();
+    let client_ip = Some(std::net::IpAddr::from([
+        10,
+        uuid_bytes[0],
+        uuid_bytes[1],
+        uuid_bytes[2],
+    ]));
+    for _ in 0..20 {
+        let email = format!("invalid_email_{}@email.com", Uuid::new_v4());
+        SignInThrottle::record_failure(&pool, &email, client_ip)
+            .await
+            .expect("error recording failure");
+    }
+    let other_email = format!("invalid_email_{}@email.com", Uuid::new_v4());
+    match SignInThrottle::check(&pool, &other_email, client_ip).await {
+        Err(SignInError::TooManyAttempts { .. }) => {}
+        res => panic!("unexpected result (should be too_many_attempts): {:?}", res),
+    }
+    SignInThrottle::check(&pool, &other_email, None)
+        .await
+        .expect("account without the locked ip should not be throttled");
+}
+
+// a pending token only allows a few codes, and wrong codes lock the account like wrong passwords
+#[sqlx::test]
+async fn test_mfa_attempts_are_limited(pool: PgPool) {
+    let app = TestApp::new(pool.clone());
+    let user = common::create_user(&pool).await;
+    let enrollment = Totp::begin_enrollment(&pool, user.id)
+        .await
+        .expect("error starting enrollment");
+    let recovery_codes =
+        Totp::confirm_enrollment(&pool, user.id, &current_totp_code(&enrollment.secret))
+            .await
+            .expect("error confirming enrollment")
+            .recovery_codes;
+
+    let signin_form = json!({ "email": user.email, "password": common::PASSWORD });
+    let (status, body) = app.post("/v1/auth/signin", None, signin_form.clone()).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let mfa_token = body["mfa_token"].as_str().unwrap().to_string();
+
+    let wrong_code = json!({ "mfa_token": mfa_token, "code": "000000" });
+    for _ in 0..5 {
+        let (status, body) = app
+            .post("/v1/auth/mfa/verify", None, wrong_code.clone())
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["code"], "invalid_mfa_code");
+    }
+
+    // the account is locked, for the correct code and for the password
+    let right_code = json!({ "mfa_token": mfa_token, "code": recovery_codes[0] });
+    let (status, body) = app
+        .post("/v1/auth/mfa/verify", None, right_code.clone())
+        .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "too_many_attempts");
+    let (status, _) = app.post("/v1/auth/signin", None, signin_form).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // once the lock is gone the token is still used up
+    SignInThrottle::record_success(&pool, &user.email)
+        .await
+        .expect("error clearing failures");
+    let (status, body) = app.post("/v1/auth/mfa/verify", None, right_code).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "mfa_token_expired");
+}
@@ -0,0 +1,153 @@
+use api::{
+    mail_service::LogMailer,
+    server::{
+        app,
+        client_ip::{ClientIp, TrustedProxies},
+        rate_limit::{RateLimitConfig, RateLimitPolicy},
+        AppState,
+    },
+};
+use axum::{
+    body::Body,
+    extract::connect_info::MockConnectInfo,
+    http::{Request, StatusCode},
+    routing::get,
+    Extension, Router,
+};
+use sqlx::PgPool;
+use std::{net::SocketAddr, sync::Arc};
+use tower::ServiceExt;
+
+const PROXY: &str = "10.0.0.5:41000";
+
+fn client_ip_app(trusted_proxies: &str, peer: &str) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(|ClientIp(ip): ClientIp| async move {
+                ip.map(|ip| ip.to_string()).unwrap_or_default()
+            }),
+        )
+        .layer(Extension(trusted_proxies.parse::<TrustedProxies>().unwrap()))
+        .layer(MockConnectInfo(peer.parse::<SocketAddr>().unwrap()))
+}
+
+async fn client_ip(app: &Router, headers: &[(&str, &str)]) -> String {
+    let mut request = Request::builder().uri("/");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_forwarding_headers() {
+    let app = client_ip_app("10.0.0.0/8", PROXY);
+
+    assert_eq!(client_ip(&app, &[]).await, "10.0.0.5");
+    assert_eq!(
+        client_ip(&app, &[("x-forwarded-for", "203.0.113.7")]).await,
+        "203.0.113.7"
+    );
+    // the entries a client sent itself are before the ones of the trusted proxies
+    assert_eq!(
+        client_ip(
+            &app,
+            &[("x-forwarded-for", "1.2.3.4, 203.0.113.7, 10.0.0.9")]
+        )
+        .await,
+        "203.0.113.7"
+    );
+    assert_eq!(
+        client_ip(
+            &app,
+            &[
+                ("x-forwarded-for", "1.2.3.4"),
+                ("x-forwarded-for", "203.0.113.7")
+            ]
+        )
+        .await,
+        "203.0.113.7"
+    );
+    // `Forwarded` is used over `X-Forwarded-For`
+    assert_eq!(
+        client_ip(
+            &app,
+            &[
+                (
+                    "forwarded",
+                    r#"for=192.0.2.60;proto=https, For="[2001:db8:cafe::17]:4711""#
+                ),
+                ("x-forwarded-for", "203.0.113.7"),
+            ]
+        )
+        .await,
+        "2001:db8:cafe::17"
+    );
+    // an obfuscated hop is not the client, the proxy that added it is used instead
+    assert_eq!(
+        client_ip(&app, &[("forwarded", "for=unknown, for=10.0.0.9")]).await,
+        "10.0.0.9"
+    );
+    assert_eq!(
+        client_ip(&app, &[("x-forwarded-for", "not an ip")]).await,
+        "10.0.0.5"
+    );
+}
+
+#[tokio::test]
+async fn test_untrusted_peers_cannot_set_their_ip() {
+    let app = client_ip_app("10.0.0.0/8", "203.0.113.7:41000");
+    assert_eq!(
+        client_ip(&app, &[("x-forwarded-for", "1.2.3.4")]).await,
+        "203.0.113.7"
+    );
+
+    let app = client_ip_app("", PROXY);
+    assert_eq!(
+        client_ip(&app, &[("x-forwarded-for", "1.2.3.4")]).await,
+        "10.0.0.5"
+    );
+
+    assert!("10.0.0.0/33".parse::<TrustedProxies>().is_err());
+    assert!("10.0.0.1, not an ip".parse::<TrustedProxies>().is_err());
+}
+
+// clients behind the same load balancer are rate limited on their own
+#[sqlx::test]
+async fn test_rate_limit_behind_proxy(pool: PgPool) {
+    let mut rate_limit = RateLimitConfig::default();
+    rate_limit
+        .route_policies
+        .insert("GET /health/live".to_string(), RateLimitPolicy::new(1, 60));
+    let app_state = AppState {
+        trusted_proxies: "10.0.0.0/8".parse().unwrap(),
+        ..AppState::new(pool, Arc::new(LogMailer))
+    };
+    let app =
+        app(app_state, rate_limit).layer(MockConnectInfo(PROXY.parse::<SocketAddr>().unwrap()));
+
+    let live = |client: &'static str| {
+        Request::builder()
+            .uri("/health/live")
+            .header("x-forwarded-for", client)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(live("203.0.113.7")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(live("203.0.113.8")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(live("203.0.113.7")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
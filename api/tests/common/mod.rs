@@ -0,0 +1,187 @@
+// shared by the integration tests, each test file only uses part of it
+#![allow(dead_code)]
+
+// every test gets its own database from `#[sqlx::test]`: it is created from `migrations`
+// before the test and dropped after it passed, so tests do not need to clean up
+use api::{
+    auth_service::{
+        claims::JwtClaims,
+        user::{Role, SignInOutcome, User},
+    },
+    conversation_service::conversation::Conversation,
+    mail_service::{MailError, MailMessage, Mailer},
+    server::{app, rate_limit::RateLimitConfig, shutdown::Shutdown, AppState},
+};
+use async_trait::async_trait;
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    response::Response,
+    Router,
+};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+pub const PASSWORD: &str = "Password123#";
+
+// keeps every sent email so tests can read the links in them
+#[derive(Default)]
+pub struct TestMailer {
+    pub sent: Mutex<Vec<MailMessage>>,
+}
+
+#[async_trait]
+impl Mailer for TestMailer {
+    async fn send(&self, message: MailMessage) -> Result<(), MailError> {
+        self.sent.lock().unwrap().push(message);
+        Ok(())
+    }
+}
+
+impl TestMailer {
+    pub fn last_to(&self, to: &str) -> Option<MailMessage> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|message| message.to == to)
+            .cloned()
+    }
+}
+
+// the whole api with its middleware, requests are handled in process without a socket
+pub struct TestApp {
+    pub pool: PgPool,
+    pub mailer: Arc<TestMailer>,
+    pub shutdown: Shutdown,
+    pub router: Router,
+}
+
+impl TestApp {
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_rate_limit(pool, RateLimitConfig::default())
+    }
+
+    pub fn with_rate_limit(pool: PgPool, rate_limit: RateLimitConfig) -> Self {
+        let mailer = Arc::new(TestMailer::default());
+        let app_state = AppState::new(pool.clone(), mailer.clone());
+        let shutdown = app_state.shutdown.clone();
+        let router = app(app_state, rate_limit);
+
+        Self {
+            pool,
+            mailer,
+            shutdown,
+            router,
+        }
+    }
+
+    pub async fn send(&self, request: Request<Body>) -> Response {
+        self.router.clone().oneshot(request).await.unwrap()
+    }
+
+    // the status and the json body, `Value::Null` when the body is empty or not json
+    pub async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, token);
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        };
+
+        let response = self.send(request.unwrap()).await;
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("error reading body");
+
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    pub async fn get(&self, uri: &str, token: &str) -> (StatusCode, Value) {
+        self.request(Method::GET, uri, Some(token), None).await
+    }
+
+    pub async fn post(&self, uri: &str, token: Option<&str>, body: Value) -> (StatusCode, Value) {
+        self.request(Method::POST, uri, token, Some(body)).await
+    }
+}
+
+pub struct TestUser {
+    pub id: Uuid,
+    pub email: String,
+    pub token: String,
+}
+
+// a user with a unique email and `PASSWORD`, signed in
+pub async fn create_user(pool: &PgPool) -> TestUser {
+    let email = format!("TestUser{}@email.com", Uuid::new_v4());
+    let token = User::signup(pool, &email, PASSWORD)
+        .await
+        .expect("error signing up user");
+    let id = JwtClaims::decode(&token)
+        .expect("error decoding jwt")
+        .user_id;
+
+    TestUser { id, email, token }
+}
+
+// the role is read from the token, so the admin signs in again after the role is set
+pub async fn create_admin(pool: &PgPool) -> TestUser {
+    let user = create_user(pool).await;
+    User::set_role(pool, user.id, Role::Admin)
+        .await
+        .expect("error setting role");
+
+    TestUser {
+        token: signin(pool, &user.email).await,
+        ..user
+    }
+}
+
+pub async fn signin(pool: &PgPool, email: &str) -> String {
+    match User::signin(pool, email, PASSWORD, None)
+        .await
+        .expect("error signing in")
+    {
+        SignInOutcome::Authenticated(token) => token,
+        SignInOutcome::MfaRequired(_) => panic!("2fa should not be enabled"),
+    }
+}
+
+// an accepted conversation, so both users can send messages
+pub async fn create_conversation(pool: &PgPool, sender_id: Uuid, receiver_id: Uuid) -> Uuid {
+    let conversation_id = Conversation::start(pool, sender_id, receiver_id)
+        .await
+        .expect("error starting conversation");
+    Conversation::accept_request(pool, receiver_id, conversation_id)
+        .await
+        .expect("error accepting message request");
+
+    conversation_id
+}
+
+pub async fn create_message(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    sender_id: Uuid,
+    content: &str,
+) -> Uuid {
+    Conversation::send_message(pool, sender_id, conversation_id, content)
+        .await
+        .expect("error sending message")
+}
@@ -1,18 +1,19 @@
+mod common;
+
 use crate::auth_service::claims::JwtClaims;
 use crate::auth_service::user::*;
-use crate::db_service;
 use api::*;
-use conversation_service::conversation::Conversation;
+use common::{create_user, TestApp};
+use conversation_service::{conversation::Conversation, error::ConversationError};
+use sqlx::PgPool;
+use user_service::{contact::Contact, error::ContactError};
 use uuid::Uuid;
-#[tokio::test]
-async fn conversation_and_messaging() {
-    let pool = db_service::get_connection_pool()
-        .await
-        .expect("error gettign connection pool");
 
+#[sqlx::test]
+async fn conversation_and_messaging(pool: PgPool) {
     let jwt = &User::signup(
         &pool,
-        &format!("TestUser01{}@gmail.com", Uuid::new_v4().to_string()),
+        &format!("TestUser01{}@gmail.com", Uuid::new_v4()),
         "123456Ee!",
     )
     .await
@@ -24,7 +25,7 @@ async fn conversation_and_messaging() {
     let test_user_two_id = JwtClaims::decode(
         &User::signup(
             &pool,
-            &format!("TestUser01{}@gmail.com", Uuid::new_v4().to_string()),
+            &format!("TestUser01{}@gmail.com", Uuid::new_v4()),
             "123456Ee!",
         )
         .await
@@ -53,3 +54,427 @@ async fn conversation_and_messaging() {
 
     assert_eq!(messages.len(), 1)
 }
+
+#[sqlx::test]
+async fn mute_conversation(pool: PgPool) {
+    let mut user_ids = Vec::new();
+    for _ in 0..3 {
+        let jwt = User::signup(
+            &pool,
+            &format!("TestUser01{}@gmail.com", Uuid::new_v4()),
+            "123456Ee!",
+        )
+        .await
+        .expect("error creating test user");
+        user_ids.push(
+            JwtClaims::decode(&jwt)
+                .expect("error getting claims")
+                .user_id,
+        );
+    }
+
+    let conversation_id = Conversation::start(&pool, user_ids[0], user_ids[1])
+        .await
+        .expect("Error starting conversation");
+
+    Conversation::set_muted(&pool, user_ids[0], conversation_id, true)
+        .await
+        .expect("error muting conversation");
+    assert!(Conversation::is_muted(&pool, user_ids[0], conversation_id)
+        .await
+        .expect("error checking mute"));
+    // muting is per user
+    assert!(!Conversation::is_muted(&pool, user_ids[1], conversation_id)
+        .await
+        .expect("error checking mute"));
+
+    let conversations = Conversation::list_for_user(&pool, user_ids[0])
+        .await
+        .expect("error listing conversations");
+    assert!(conversations[0].muted);
+
+    // muted conversations can still be used
+    Conversation::accept_request(&pool, user_ids[1], conversation_id)
+        .await
+        .expect("error accepting message request");
+    Conversation::send_message(&pool, user_ids[1], conversation_id, "still here")
+        .await
+        .expect("error sending message");
+
+    Conversation::set_muted(&pool, user_ids[0], conversation_id, false)
+        .await
+        .expect("error unmuting conversation");
+    assert!(!Conversation::is_muted(&pool, user_ids[0], conversation_id)
+        .await
+        .expect("error checking mute"));
+
+    // only participants can mute a conversation
+    match Conversation::set_muted(&pool, user_ids[2], conversation_id, true).await {
+        Err(ConversationError::NotAParticipant) => {}
+        res => panic!("unexpected result (should be not_a_participant): {:?}", res),
+    }
+}
+
+#[sqlx::test]
+async fn contacts_and_message_requests(pool: PgPool) {
+    let mut user_ids = Vec::new();
+    for _ in 0..3 {
+        let jwt = User::signup(
+            &pool,
+            &format!("TestUser01{}@gmail.com", Uuid::new_v4()),
+            "123456Ee!",
+        )
+        .await
+        .expect("error creating test user");
+        user_ids.push(
+            JwtClaims::decode(&jwt)
+                .expect("error getting claims")
+                .user_id,
+        );
+    }
+    let (receiver_id, contact_id, stranger_id) = (user_ids[0], user_ids[1], user_ids[2]);
+
+    match Contact::add(&pool, receiver_id, receiver_id).await {
+        Err(ContactError::CannotAddSelf) => {}
+        res => panic!("unexpected result (should be cannot_add_self): {:?}", res),
+    }
+    Contact::add(&pool, receiver_id, contact_id)
+        .await
+        .expect("error adding contact");
+    let contacts = Contact::list(&pool, receiver_id)
+        .await
+        .expect("error listing contacts");
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].id, contact_id);
+    // contacts are one sided
+    assert!(Contact::list(&pool, contact_id)
+        .await
+        .expect("error listing contacts")
+        .is_empty());
+
+    // a contact goes straight to the inbox, a stranger sends a message request
+    let contact_conversation_id = Conversation::start(&pool, contact_id, receiver_id)
+        .await
+        .expect("Error starting conversation");
+    let request_id = Conversation::start(&pool, stranger_id, receiver_id)
+        .await
+        .expect("Error starting conversation");
+
+    let inbox = Conversation::list_for_user(&pool, receiver_id)
+        .await
+        .expect("error listing conversations");
+    assert_eq!(inbox.len(), 1);
+    assert_eq!(inbox[0].id, contact_conversation_id);
+
+    let requests = Conversation::list_requests(&pool, receiver_id)
+        .await
+        .expect("error listing message requests");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].id, request_id);
+    assert_eq!(requests[0].other_user.id, stranger_id);
+
+    // the sender sees their own request and can write in it, the receiver has to accept first
+    let sent = Conversation::list_for_user(&pool, stranger_id)
+        .await
+        .expect("error listing conversations");
+    assert_eq!(sent.len(), 1);
+    assert!(!sent[0].accepted);
+    Conversation::send_message(&pool, stranger_id, request_id, "hi, can we talk?")
+        .await
+        .expect("error sending message");
+    match Conversation::send_message(&pool, receiver_id, request_id, "hello").await {
+        Err(ConversationError::MessageRequestPending) => {}
+        res => panic!(
+            "unexpected result (should be message_request_pending): {:?}",
+            res
+        ),
+    }
+    match Conversation::accept_request(&pool, stranger_id, request_id).await {
+        Err(ConversationError::MessageRequestNotFound) => {}
+        res => panic!(
+            "unexpected result (should be message_request_not_found): {:?}",
+            res
+        ),
+    }
+
+    Conversation::accept_request(&pool, receiver_id, request_id)
+        .await
+        .expect("error accepting message request");
+    Conversation::send_message(&pool, receiver_id, request_id, "hello")
+        .await
+        .expect("error sending message");
+    assert_eq!(
+        Conversation::list_for_user(&pool, receiver_id)
+            .await
+            .expect("error listing conversations")
+            .len(),
+        2
+    );
+    assert!(Conversation::list_requests(&pool, receiver_id)
+        .await
+        .expect("error listing message requests")
+        .is_empty());
+
+    // declining removes the request, removing a contact sends the next conversation to the requests
+    Contact::remove(&pool, receiver_id, contact_id)
+        .await
+        .expect("error removing contact");
+    sqlx::query!(
+        "DELETE FROM conversations WHERE id = $1",
+        contact_conversation_id
+    )
+    .execute(&pool)
+    .await
+    .expect("error deleting conversation");
+    let request_id = Conversation::start(&pool, contact_id, receiver_id)
+        .await
+        .expect("Error starting conversation");
+    Conversation::decline_request(&pool, receiver_id, request_id)
+        .await
+        .expect("error declining message request");
+    assert!(Conversation::pair_exists(&pool, contact_id, receiver_id)
+        .await
+        .expect("error checking conversation")
+        .is_none());
+}
+
+// the routes are requested under /v1 from the whole app
+async fn conversation_request(
+    app: &TestApp,
+    method: &str,
+    uri: &str,
+    token: &str,
+    body: serde_json::Value,
+) -> (axum::http::StatusCode, serde_json::Value) {
+    let body = (!body.is_null()).then_some(body);
+
+    app.request(
+        method.parse().unwrap(),
+        &format!("/v1{}", uri),
+        Some(token),
+        body,
+    )
+    .await
+}
+
+#[sqlx::test]
+async fn conversation_routes(pool: PgPool) {
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    let app = TestApp::new(pool.clone());
+
+    let mut tokens = Vec::new();
+    let mut user_ids = Vec::new();
+    for _ in 0..3 {
+        let user = create_user(&pool).await;
+        user_ids.push(user.id);
+        tokens.push(user.token);
+    }
+
+    let (status, body) = conversation_request(
+        &app,
+        "POST",
+        "/conversations",
+        &tokens[0],
+        json!({ "receiver_id": user_ids[1] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let conversation_id = body["conversation_id"].clone();
+    let messages_uri = format!(
+        "/conversations/{}/messages",
+        conversation_id.as_str().unwrap()
+    );
+
+    // starting a conversation with yourself is a bad request
+    let (status, body) = conversation_request(
+        &app,
+        "POST",
+        "/conversations",
+        &tokens[0],
+        json!({ "receiver_id": user_ids[0] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "same_sender_and_receiver");
+
+    // the existing conversation is returned so the client can open it
+    let (status, body) = conversation_request(
+        &app,
+        "POST",
+        "/conversations",
+        &tokens[0],
+        json!({ "receiver_id": user_ids[1] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "conversation_already_exists");
+    assert_eq!(body["details"]["conversation_id"], conversation_id);
+
+    // a receiver that does not exist is not found, one that blocked the sender is forbidden
+    let (status, body) = conversation_request(
+        &app,
+        "POST",
+        "/conversations",
+        &tokens[0],
+        json!({ "receiver_id": Uuid::new_v4() }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "user_not_found");
+    let block_uri = format!("/v1/users/{}/block", user_ids[0]);
+    let (status, _) = app
+        .request("PUT".parse().unwrap(), &block_uri, Some(&tokens[2]), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = conversation_request(
+        &app,
+        "POST",
+        "/conversations",
+        &tokens[0],
+        json!({ "receiver_id": user_ids[2] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "blocked");
+
+    // messages are sent and listed as json
+    let (status, body) = conversation_request(
+        &app,
+        "POST",
+        &messages_uri,
+        &tokens[0],
+        json!({ "content": "hello" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["content"], "hello");
+    assert_eq!(body["conversation_id"], conversation_id);
+    let sent_at = body["sent_at"].as_str().unwrap().to_string();
+    let (status, body) =
+        conversation_request(&app, "GET", &messages_uri, &tokens[1], json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().map(Vec::len), Some(1));
+
+    // with `after` only newer messages are listed
+    let after_uri = format!("{}?after={}", messages_uri, sent_at);
+    let (status, body) =
+        conversation_request(&app, "GET", &after_uri, &tokens[1], json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().map(Vec::len), Some(0));
+    let after_uri = format!("{}?after=2000-01-01T00:00:00", messages_uri);
+    let (status, body) =
+        conversation_request(&app, "GET", &after_uri, &tokens[1], json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().map(Vec::len), Some(1));
+    // messages sent at the same time are told apart by their id
+    let sent_at = chrono::NaiveDate::from_ymd_opt(2030, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    let mut same_time_ids = [Uuid::new_v4(), Uuid::new_v4()];
+    same_time_ids.sort();
+    for id in same_time_ids {
+        sqlx::query!(
+            r#"
+            INSERT INTO messages (id, conversation_id, content, sent_at, sender_id)
+            VALUES ($1, $2, 'same time', $3, $4)
+            "#,
+            id,
+            Uuid::parse_str(conversation_id.as_str().unwrap()).unwrap(),
+            sent_at,
+            user_ids[0],
+        )
+        .execute(&pool)
+        .await
+        .expect("error inserting message");
+    }
+    let after_uri = format!(
+        "{}?after=2030-01-01T00:00:00&after_id={}",
+        messages_uri, same_time_ids[0]
+    );
+    let (status, body) =
+        conversation_request(&app, "GET", &after_uri, &tokens[1], json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().map(Vec::len), Some(1));
+    assert_eq!(body[0]["id"], json!(same_time_ids[1]));
+    let after_uri = format!(
+        "{}?after=2030-01-01T00:00:00&after_id={}",
+        messages_uri, same_time_ids[1]
+    );
+    let (status, body) =
+        conversation_request(&app, "GET", &after_uri, &tokens[1], json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().map(Vec::len), Some(0));
+
+    let after_uri = format!("{}?after=yesterday", messages_uri);
+    let (status, body) =
+        conversation_request(&app, "GET", &after_uri, &tokens[1], json!(null)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_query");
+
+    // conversations that do not exist are not found
+    let missing_uri = format!("/conversations/{}/messages", Uuid::new_v4());
+    let (status, body) =
+        conversation_request(&app, "GET", &missing_uri, &tokens[0], json!(null)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "conversation_not_found");
+    let (status, _) = conversation_request(
+        &app,
+        "POST",
+        &missing_uri,
+        &tokens[0],
+        json!({ "content": "hello" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, body) = conversation_request(
+        &app,
+        "GET",
+        "/conversations/not-a-uuid/messages",
+        &tokens[0],
+        json!(null),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_id");
+
+    // other users cannot read, write to or mute the conversation
+    let (status, body) =
+        conversation_request(&app, "GET", &messages_uri, &tokens[2], json!(null)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "not_a_participant");
+    let (status, body) = conversation_request(
+        &app,
+        "POST",
+        &messages_uri,
+        &tokens[2],
+        json!({ "content": "hello" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "not_a_participant");
+    let (status, body) = conversation_request(
+        &app,
+        "PUT",
+        &format!("/conversations/{}/mute", conversation_id.as_str().unwrap()),
+        &tokens[2],
+        json!(null),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "not_a_participant");
+
+    // the receiver has to accept the message request before replying
+    let (status, body) = conversation_request(
+        &app,
+        "POST",
+        &messages_uri,
+        &tokens[1],
+        json!({ "content": "hello" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "message_request_pending");
+}
@@ -0,0 +1,152 @@
+use api::{
+    auth_service::router::auth_routes,
+    mail_service::LogMailer,
+    server::{
+        error::{ErrorBody, ErrorCode},
+        request_id::{request_id_middleware, REQUEST_ID_HEADER},
+        AppState,
+    },
+    user_service::router::user_routes,
+};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    middleware, Router,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+fn app(pool: PgPool) -> Router {
+    let state = AppState::new(pool, Arc::new(LogMailer));
+
+    Router::new()
+        .nest("/auth", auth_routes(state.clone()).into())
+        .nest("/users", user_routes(state.clone()).into())
+        .with_state(state)
+        .layer(middleware::from_fn(request_id_middleware))
+}
+
+async fn error_body(
+    app: &Router,
+    request: Request<Body>,
+) -> (StatusCode, Option<String>, ErrorBody) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let request_id = response
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|header_value| header_value.to_str().ok())
+        .map(str::to_string);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("error reading body");
+    let body: ErrorBody = serde_json::from_slice(&body).expect("error body is not an envelope");
+
+    (status, request_id, body)
+}
+
+fn json_request(uri: &str, body: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn test_error_envelope(pool: PgPool) {
+    let app = app(pool);
+
+    // the request id is generated when the client does not send one
+    let email = format!("testuser{}@email.com", Uuid::new_v4());
+    let request = json_request(
+        "/auth/signin",
+        json!({ "email": email, "password": "Password123#" }),
+    );
+    let (status, request_id, body) = error_body(&app, request).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body.code, ErrorCode::InvalidCredentials);
+    assert!(request_id.is_some());
+    assert_eq!(body.request_id, request_id);
+
+    // validation errors carry their limits in the details
+    let request = json_request(
+        "/auth/signup",
+        json!({ "email": email, "password": "password123" }),
+    );
+    let (status, _, body) = error_body(&app, request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body.code, ErrorCode::PasswordTooWeak);
+    let details = body.details.expect("password_too_weak should have details");
+    assert_eq!(details["lowercase"], true);
+    assert_eq!(details["uppercase"], false);
+
+    // a request id sent by the client is kept
+    let request = Request::builder()
+        .uri("/users/me")
+        .header(&REQUEST_ID_HEADER, "client-request-1")
+        .body(Body::empty())
+        .unwrap();
+    let (status, request_id, body) = error_body(&app, request).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body.code, ErrorCode::MissingToken);
+    assert_eq!(request_id.as_deref(), Some("client-request-1"));
+    assert_eq!(body.request_id.as_deref(), Some("client-request-1"));
+
+    // tokens that cannot be decoded are rejected as invalid instead of a server error
+    let request = Request::builder()
+        .uri("/users/me")
+        .header(header::AUTHORIZATION, "not-a-jwt")
+        .body(Body::empty())
+        .unwrap();
+    let (status, _, body) = error_body(&app, request).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body.code, ErrorCode::InvalidToken);
+
+    // bodies and ids that cannot be extracted are rejected with the same envelope
+    let request = Request::builder()
+        .method("POST")
+        .uri("/auth/signin")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from("email=a&password=b"))
+        .unwrap();
+    let (status, _, body) = error_body(&app, request).await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(body.code, ErrorCode::InvalidBody);
+    let request = json_request("/auth/signin", json!({ "email": email }));
+    let (status, _, body) = error_body(&app, request).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body.code, ErrorCode::InvalidBody);
+    let request = Request::builder()
+        .uri("/users/not-a-uuid/avatar")
+        .body(Body::empty())
+        .unwrap();
+    let (status, _, body) = error_body(&app, request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body.code, ErrorCode::InvalidId);
+}
+
+// every code in the documented catalog has to exist
+#[test]
+fn test_error_code_catalog() {
+    let catalog = include_str!("../docs/error-codes.md");
+    let codes: Vec<&str> = catalog
+        .lines()
+        .filter_map(|line| line.strip_prefix("| `"))
+        .filter_map(|line| line.split('`').next())
+        .collect();
+    assert!(codes.len() > 50);
+
+    for code in codes {
+        let parsed: Result<ErrorCode, _> = serde_json::from_value(serde_json::json!(code));
+        assert!(
+            parsed.is_ok(),
+            "{} is documented but not an ErrorCode",
+            code
+        );
+    }
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use api::server::request_id::REQUEST_ID_HEADER;
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+};
+use common::{create_conversation, create_message, create_user, TestApp};
+use sqlx::PgPool;
+
+// every test starts from an empty database with every migration applied
+#[sqlx::test]
+async fn test_database_is_isolated(pool: PgPool) {
+    let users = sqlx::query_scalar!("SELECT COUNT(*) FROM users")
+        .fetch_one(&pool)
+        .await
+        .expect("error counting users");
+    assert_eq!(users, Some(0));
+
+    create_user(&pool).await;
+    let users = sqlx::query_scalar!("SELECT COUNT(*) FROM users")
+        .fetch_one(&pool)
+        .await
+        .expect("error counting users");
+    assert_eq!(users, Some(1));
+}
+
+#[sqlx::test]
+async fn test_app_and_factories(pool: PgPool) {
+    let app = TestApp::new(pool.clone());
+    let sender = create_user(&pool).await;
+    let receiver = create_user(&pool).await;
+    let conversation_id = create_conversation(&pool, sender.id, receiver.id).await;
+    create_message(&pool, conversation_id, sender.id, "hello").await;
+    create_message(&pool, conversation_id, receiver.id, "hi").await;
+
+    let uri = format!("/v1/conversations/{}/messages", conversation_id);
+    let (status, body) = app.get(&uri, &receiver.token).await;
+    assert_eq!(status, StatusCode::OK);
+    let contents: Vec<&str> = body
+        .as_array()
+        .expect("messages should be a list")
+        .iter()
+        .filter_map(|message| message["content"].as_str())
+        .collect();
+    assert_eq!(contents, ["hello", "hi"]);
+
+    // the middleware of the server runs too
+    let request = Request::builder()
+        .uri("/v1/users/me")
+        .header(header::AUTHORIZATION, &sender.token)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().contains_key(&REQUEST_ID_HEADER));
+    assert!(response.headers().contains_key("ratelimit-limit"));
+}
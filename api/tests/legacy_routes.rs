@@ -0,0 +1,107 @@
+mod common;
+
+use api::{
+    auth_service::user::User,
+    mail_service::LogMailer,
+    server::{routes, AppState},
+};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use common::create_user;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+async fn send(app: &Router, request: Request<Body>) -> (StatusCode, bool, String) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let deprecated = response.headers().contains_key("deprecation");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("error reading body");
+
+    (
+        status,
+        deprecated,
+        String::from_utf8_lossy(&body).to_string(),
+    )
+}
+
+// the routes from before /v1 keep working for one more release and say that they are deprecated
+#[sqlx::test]
+async fn test_legacy_routes(pool: PgPool) {
+    let app = routes(AppState::new(pool.clone(), Arc::new(LogMailer)));
+
+    let email = format!("testuser{}@email.com", Uuid::new_v4());
+    let form = format!("email={}&password=Password123%23", email);
+    let form_request = |uri: &str| {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(form.clone()))
+            .unwrap()
+    };
+
+    // form bodies still work on the old auth routes, /v1 only takes json
+    let (status, deprecated, _) = send(&app, form_request("/auth/signup")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(deprecated);
+    let (status, deprecated, _) = send(&app, form_request("/v1/auth/signin")).await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert!(!deprecated);
+    let (status, _, _) = send(&app, form_request("/auth/signin")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // the old health check answers like /health/live
+    let request = Request::builder()
+        .uri("/health")
+        .body(Body::empty())
+        .unwrap();
+    let (status, deprecated, body) = send(&app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(deprecated);
+    assert!(body.contains("\"status\":\"ok\""));
+
+    let token = create_user(&pool).await.token;
+    let other_id = User::get_user_by_email(&pool, &email)
+        .await
+        .expect("error getting user")
+        .id;
+
+    // the old conversation routes take string ids in the body
+    let request = Request::builder()
+        .method("POST")
+        .uri("/conversation")
+        .header(header::AUTHORIZATION, &token)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            serde_json::json!({ "receiver_id": other_id }).to_string(),
+        ))
+        .unwrap();
+    let (status, deprecated, conversation_id) = send(&app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(deprecated);
+    assert!(Uuid::parse_str(&conversation_id).is_ok());
+
+    // routes that were added with /v1 are not served without the prefix
+    for (uri, expected_status) in [
+        ("/v1/users/me", StatusCode::OK),
+        ("/users/me", StatusCode::NOT_FOUND),
+        ("/reports", StatusCode::NOT_FOUND),
+        ("/moderation/reports", StatusCode::NOT_FOUND),
+        ("/admin/stats", StatusCode::NOT_FOUND),
+    ] {
+        let request = Request::builder()
+            .uri(uri)
+            .header(header::AUTHORIZATION, &token)
+            .body(Body::empty())
+            .unwrap();
+        let (status, _, _) = send(&app, request).await;
+        assert_eq!(status, expected_status, "{}", uri);
+    }
+}
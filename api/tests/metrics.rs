@@ -0,0 +1,140 @@
+mod common;
+
+use axum::http::{header, Method, Request, StatusCode};
+use common::{create_conversation, create_user, TestApp, PASSWORD};
+use serde_json::json;
+use sqlx::PgPool;
+use std::time::Duration;
+use tower::ServiceExt;
+
+// the value of the sample with exactly these labels
+fn sample(metrics: &str, series: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .and_then(|value| value.parse().ok())
+}
+
+async fn scrape(app: &TestApp) -> String {
+    let request = Request::builder()
+        .uri("/metrics")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let response = app.send(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("error reading body");
+
+    String::from_utf8(body.to_vec()).expect("metrics are not utf-8")
+}
+
+#[sqlx::test]
+async fn test_metrics(pool: PgPool) {
+    let app = TestApp::new(pool.clone());
+    let sender = create_user(&pool).await;
+    let receiver = create_user(&pool).await;
+    let conversation_id = create_conversation(&pool, sender.id, receiver.id).await;
+
+    let (status, _) = app.get("/v1/users/me", &sender.token).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request(Method::GET, "/no/such/route", None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = app
+        .post(
+            "/v1/auth/signin",
+            None,
+            json!({ "email": sender.email, "password": "Wrong123#" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app
+        .post(
+            "/v1/auth/signin",
+            None,
+            json!({ "email": sender.email, "password": PASSWORD }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let uri = format!("/v1/conversations/{}/messages", conversation_id);
+    let (status, _) = app
+        .post(&uri, Some(&sender.token), json!({ "content": "hello" }))
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // the registry is shared by the tests of this file, so counts are at least what this test did
+    let metrics = scrape(&app).await;
+    let me = r#"{method="GET",route="/v1/users/me""#;
+    assert!(
+        sample(
+            &metrics,
+            &format!("chat_http_requests_total{},status=\"200\"}}", me)
+        ) >= Some(1.0)
+    );
+    assert!(
+        sample(
+            &metrics,
+            &format!(
+                "chat_http_request_duration_seconds_bucket{},le=\"+Inf\"}}",
+                me
+            )
+        ) >= Some(1.0)
+    );
+    assert!(
+        sample(
+            &metrics,
+            r#"chat_http_requests_total{method="GET",route="unmatched",status="404"}"#
+        ) >= Some(1.0)
+    );
+    assert!(
+        sample(
+            &metrics,
+            r#"chat_http_requests_total{method="POST",route="/v1/conversations/{id}/messages",status="201"}"#
+        ) >= Some(1.0)
+    );
+    assert!(
+        sample(
+            &metrics,
+            r#"chat_signins_failed_total{reason="wrong_password"}"#
+        ) >= Some(1.0)
+    );
+    assert!(sample(&metrics, "chat_signins_succeeded_total") >= Some(1.0));
+    assert!(sample(&metrics, "chat_messages_sent_total") >= Some(1.0));
+    assert_eq!(sample(&metrics, "chat_db_up"), Some(1.0));
+    assert!(sample(&metrics, "chat_db_pool_probe_acquire_seconds").is_some());
+    assert!(sample(&metrics, "chat_db_pool_max_connections") > Some(0.0));
+
+    // a poll for new messages is counted while it waits for the locked table
+    let mut lock = pool.begin().await.expect("error starting transaction");
+    sqlx::query("LOCK TABLE messages IN ACCESS EXCLUSIVE MODE")
+        .execute(&mut *lock)
+        .await
+        .expect("error locking messages");
+    let request = Request::builder()
+        .uri(&uri)
+        .header(header::AUTHORIZATION, &receiver.token)
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let poll = tokio::spawn(app.router.clone().oneshot(request));
+    let mut in_flight = None;
+    for _ in 0..50 {
+        in_flight = sample(&scrape(&app).await, "chat_message_polls_in_flight");
+        if in_flight == Some(1.0) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(in_flight, Some(1.0));
+
+    lock.commit().await.expect("error unlocking messages");
+    let response = poll.await.unwrap().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        sample(&scrape(&app).await, "chat_message_polls_in_flight"),
+        Some(0.0)
+    );
+}
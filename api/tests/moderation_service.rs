@@ -0,0 +1,249 @@
+mod common;
+
+use api::{
+    auth_service::user::{error::SignInError, Role, User},
+    conversation_service::conversation::Conversation,
+    moderation_service::{
+        error::ModerationError,
+        report::{
+            ModerationActionKind, ModerationRequest, NewReport, Report, ReportFilter, ReportReason,
+            ReportStatus,
+        },
+    },
+};
+use axum::http::StatusCode;
+use common::{create_admin, create_user, TestApp, TestUser, PASSWORD};
+use serde_json::json;
+use sqlx::PgPool;
+
+fn moderation_request(action: ModerationActionKind, note: &str) -> ModerationRequest {
+    ModerationRequest {
+        action,
+        note: Some(note.to_string()),
+    }
+}
+
+#[sqlx::test]
+async fn test_reports_and_moderation(pool: PgPool) {
+    let reporter_id = create_user(&pool).await.id;
+    let TestUser {
+        id: abuser_id,
+        email: abuser_email,
+        ..
+    } = create_user(&pool).await;
+    let outsider_id = create_user(&pool).await.id;
+    let moderator_id = create_user(&pool).await.id;
+    User::set_role(&pool, moderator_id, Role::Admin)
+        .await
+        .expect("error making user an admin");
+
+    let conversation_id = Conversation::start(&pool, abuser_id, reporter_id)
+        .await
+        .expect("error starting conversation");
+    let message_id = Conversation::send_message(&pool, abuser_id, conversation_id, "abusive")
+        .await
+        .expect("error sending message");
+
+    match Report::create(
+        &pool,
+        reporter_id,
+        NewReport {
+            message_id: None,
+            user_id: None,
+            reason: ReportReason::Spam,
+            details: None,
+        },
+    )
+    .await
+    {
+        Err(ModerationError::MissingTarget) => {}
+        res => panic!("unexpected result (should be missing_target): {:?}", res),
+    }
+
+    // only people in the conversation can report its messages
+    match Report::create(
+        &pool,
+        outsider_id,
+        NewReport {
+            message_id: Some(message_id),
+            user_id: None,
+            reason: ReportReason::Harassment,
+            details: None,
+        },
+    )
+    .await
+    {
+        Err(ModerationError::MessageNotFound) => {}
+        res => panic!("unexpected result (should be message_not_found): {:?}", res),
+    }
+
+    let message_report = Report::create(
+        &pool,
+        reporter_id,
+        NewReport {
+            message_id: Some(message_id),
+            user_id: None,
+            reason: ReportReason::Harassment,
+            details: Some("  keeps insulting me  ".to_string()),
+        },
+    )
+    .await
+    .expect("error reporting message");
+    assert_eq!(message_report.reported_user_id, Some(abuser_id));
+    assert_eq!(message_report.message_content.as_deref(), Some("abusive"));
+    assert_eq!(
+        message_report.details.as_deref(),
+        Some("keeps insulting me")
+    );
+    assert_eq!(message_report.status, ReportStatus::Open);
+
+    let user_report = Report::create(
+        &pool,
+        outsider_id,
+        NewReport {
+            message_id: None,
+            user_id: Some(abuser_id),
+            reason: ReportReason::Impersonation,
+            details: None,
+        },
+    )
+    .await
+    .expect("error reporting user");
+
+    let open_reports = Report::list(
+        &pool,
+        &ReportFilter {
+            status: Some(ReportStatus::Open),
+            limit: Some(200),
+            offset: None,
+        },
+    )
+    .await
+    .expect("error listing reports");
+    assert!(open_reports
+        .iter()
+        .any(|report| report.id == message_report.id));
+    assert!(open_reports
+        .iter()
+        .any(|report| report.id == user_report.id));
+
+    // triage, then delete the reported message
+    let details = Report::moderate(
+        &pool,
+        moderator_id,
+        message_report.id,
+        moderation_request(ModerationActionKind::Triage, "looking into it"),
+    )
+    .await
+    .expect("error triaging report");
+    assert_eq!(details.report.status, ReportStatus::Triaged);
+
+    match Report::moderate(
+        &pool,
+        moderator_id,
+        user_report.id,
+        moderation_request(ModerationActionKind::DeleteMessage, ""),
+    )
+    .await
+    {
+        Err(ModerationError::InvalidAction { .. }) => {}
+        res => panic!("unexpected result (should be invalid_action): {:?}", res),
+    }
+
+    let details = Report::moderate(
+        &pool,
+        moderator_id,
+        message_report.id,
+        moderation_request(ModerationActionKind::DeleteMessage, "removed"),
+    )
+    .await
+    .expect("error deleting reported message");
+    assert_eq!(details.report.status, ReportStatus::Resolved);
+    // the copy of the message is kept for the record
+    assert_eq!(details.report.message_content.as_deref(), Some("abusive"));
+    assert_eq!(details.actions.len(), 2);
+    assert_eq!(
+        details.actions[1].action,
+        ModerationActionKind::DeleteMessage
+    );
+    assert_eq!(details.actions[1].moderator_id, Some(moderator_id));
+    assert!(Conversation::get_all_messages(&pool, conversation_id)
+        .await
+        .expect("error getting messages")
+        .is_empty());
+
+    // suspending signs the user out and stops them from signing in again
+    let details = Report::moderate(
+        &pool,
+        moderator_id,
+        user_report.id,
+        moderation_request(ModerationActionKind::SuspendUser, "repeat offender"),
+    )
+    .await
+    .expect("error suspending user");
+    assert_eq!(details.actions[0].target_user_id, Some(abuser_id));
+    assert!(User::is_suspended(&pool, abuser_id)
+        .await
+        .expect("error checking suspension"));
+    match User::signin(&pool, &abuser_email, PASSWORD, None).await {
+        Err(SignInError::AccountSuspended { .. }) => {}
+        res => panic!("unexpected result (should be account_suspended): {:?}", res),
+    }
+
+    // reports outlive the users they are about
+    for user_id in [reporter_id, abuser_id, outsider_id, moderator_id] {
+        User::delete_user_by_id(&pool, user_id)
+            .await
+            .expect("Error deleting test user");
+    }
+    let details = Report::get(&pool, user_report.id)
+        .await
+        .expect("error getting report");
+    assert_eq!(details.report.reported_user_id, None);
+    assert_eq!(details.actions.len(), 1);
+
+    for report_id in [message_report.id, user_report.id] {
+        sqlx::query!("DELETE FROM reports WHERE id = $1", report_id)
+            .execute(&pool)
+            .await
+            .expect("error deleting test report");
+    }
+}
+
+// the admin check is a route layer, so every moderation route rejects normal users
+#[sqlx::test]
+async fn test_moderation_routes_require_admin(pool: PgPool) {
+    let app = TestApp::new(pool.clone());
+    let reporter = create_user(&pool).await;
+    let reported = create_user(&pool).await;
+    let admin = create_admin(&pool).await;
+
+    let (status, report) = app
+        .post(
+            "/v1/reports",
+            Some(&reporter.token),
+            json!({ "user_id": reported.id, "reason": "spam" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let report_uri = format!("/v1/moderation/reports/{}", report["id"].as_str().unwrap());
+    let actions_uri = format!("{}/actions", report_uri);
+    let dismiss = json!({ "action": "dismiss", "note": "not spam" });
+
+    for uri in ["/v1/moderation/reports", report_uri.as_str()] {
+        let (status, body) = app.get(uri, &reporter.token).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], "forbidden");
+    }
+    let (status, _) = app
+        .post(&actions_uri, Some(&reporter.token), dismiss.clone())
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, reports) = app.get("/v1/moderation/reports", &admin.token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(reports.as_array().unwrap().len(), 1);
+    let (status, details) = app.post(&actions_uri, Some(&admin.token), dismiss).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(details["actions"][0]["moderator_id"], json!(admin.id));
+}
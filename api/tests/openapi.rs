@@ -0,0 +1,117 @@
+use api::{
+    mail_service::LogMailer,
+    server::{routes, AppState},
+};
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use sqlx::PgPool;
+use std::{collections::BTreeSet, sync::Arc};
+use tower::ServiceExt;
+
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+// the document served by the router
+async fn openapi_json(app: &Router) -> serde_json::Value {
+    let request = Request::builder()
+        .uri("/openapi.json")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("error reading body");
+
+    serde_json::from_slice(&body).expect("error parsing spec")
+}
+
+// (method, path) of every operation in the spec
+fn spec_routes(spec: &serde_json::Value) -> BTreeSet<(String, String)> {
+    let mut routes = BTreeSet::new();
+
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in METHODS {
+            if item.get(method).is_some() {
+                routes.insert((method.to_string(), path.clone()));
+            }
+        }
+    }
+
+    routes
+}
+
+// the router answers every documented operation, even without a token or a body
+#[sqlx::test]
+async fn test_documented_routes_exist(pool: PgPool) {
+    let app = routes(AppState::new(pool, Arc::new(LogMailer)));
+    let spec = spec_routes(&openapi_json(&app).await);
+    assert!(spec.len() > 50);
+
+    for (method, path) in spec {
+        let uri = path.replace("{id}", &uuid::Uuid::nil().to_string());
+        let request = Request::builder()
+            .method(Method::from_bytes(method.to_uppercase().as_bytes()).unwrap())
+            .uri(&uri)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("error reading body");
+
+        // axum answers routes it does not know with an empty 404 and unknown methods with a 405
+        assert_ne!(
+            status,
+            StatusCode::METHOD_NOT_ALLOWED,
+            "{} {}",
+            method,
+            path
+        );
+        assert!(
+            status != StatusCode::NOT_FOUND || !body.is_empty(),
+            "{} {} is not routed",
+            method,
+            path
+        );
+    }
+}
+
+#[sqlx::test]
+async fn test_openapi_json(pool: PgPool) {
+    let app = routes(AppState::new(pool, Arc::new(LogMailer)));
+
+    let spec = openapi_json(&app).await;
+    assert!(spec["openapi"].as_str().unwrap().starts_with('3'));
+    for path in [
+        "/health/live",
+        "/openapi.json",
+        "/v1/conversations",
+        "/v1/reports",
+    ] {
+        assert!(spec["paths"].get(path).is_some(), "{} is missing", path);
+    }
+    assert!(spec["components"]["securitySchemes"].get("jwt").is_some());
+    for schema in [
+        "AuthForm",
+        "NewConversation",
+        "NewMessage",
+        "Message",
+        "PublicUserData",
+        "ErrorBody",
+        "ErrorCode",
+    ] {
+        assert!(
+            spec["components"]["schemas"].get(schema).is_some(),
+            "{} is missing from the spec",
+            schema
+        );
+    }
+
+    let request = Request::builder().uri("/docs").body(Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use api::{
+    auth_service::password_reset::PasswordReset,
+    auth_service::{
+        session::Session,
+        user::{Role, SignInOutcome, User},
+    },
+    config::Config,
+    db_service::{error::MigrationError, pending_migrations, run_migrations, MIGRATOR},
+    ops::{create_admin, promote_admin, purge_expired, AdminAccount},
+};
+use common::create_user;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[sqlx::test]
+async fn test_migrations_are_embedded(pool: PgPool) {
+    assert!(MIGRATOR.iter().count() > 0);
+    run_migrations(&pool, false)
+        .await
+        .expect("error applying migrations");
+    let pending = pending_migrations(&pool)
+        .await
+        .expect("error listing migrations");
+    assert!(pending.is_empty());
+
+    // a migration of a newer release
+    let version = 99991231000000_i64;
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+        VALUES ($1, 'from the future', true, '\\x00', 0)",
+    )
+    .bind(version)
+    .execute(&pool)
+    .await
+    .expect("error inserting migration");
+
+    let strict = run_migrations(&pool, false).await;
+    let lenient = run_migrations(&pool, true).await;
+    sqlx::query("DELETE FROM _sqlx_migrations WHERE version = $1")
+        .bind(version)
+        .execute(&pool)
+        .await
+        .expect("error deleting migration");
+
+    assert!(matches!(strict, Err(MigrationError::SchemaAhead(versions)) if versions == [version]));
+    assert!(lenient.expect("error checking migrations").is_empty());
+}
+
+#[sqlx::test]
+async fn test_create_admin_and_reset_password(pool: PgPool) {
+    let email = format!("testuser{}@email.com", Uuid::new_v4());
+
+    // a new account is created, an existing one keeps its password and is promoted
+    let user_id = match create_admin(&pool, &email, "Password123#").await {
+        Ok(AdminAccount::Created(user_id)) => user_id,
+        other => panic!("expected a new admin, got {:?}", other),
+    };
+    assert_eq!(
+        create_admin(&pool, &email, "Other123#").await.unwrap(),
+        AdminAccount::Promoted(user_id)
+    );
+    // the cli promotes existing accounts without asking for a password
+    assert_eq!(promote_admin(&pool, &email).await.unwrap(), Some(user_id));
+    let missing_email = format!("testuser{}@email.com", Uuid::new_v4());
+    assert_eq!(promote_admin(&pool, &missing_email).await.unwrap(), None);
+    assert_eq!(
+        User::get_role(&pool, user_id).await.unwrap(),
+        Some(Role::Admin)
+    );
+    assert!(matches!(
+        User::signin(&pool, &email, "Password123#", None).await,
+        Ok(SignInOutcome::Authenticated(_))
+    ));
+
+    // setting the password signs out every session
+    PasswordReset::set_password(&pool, user_id, "Changed123#")
+        .await
+        .expect("error setting password");
+    assert!(User::signin(&pool, &email, "Password123#", None)
+        .await
+        .is_err());
+    let sessions = Session::list_for_user(&pool, user_id).await.unwrap();
+    assert!(sessions.iter().all(|session| session.revoked_at.is_some()));
+    assert!(PasswordReset::set_password(&pool, user_id, "weak")
+        .await
+        .is_err());
+}
+
+#[sqlx::test]
+async fn test_purge_expired(pool: PgPool) {
+    let user_id = create_user(&pool).await.id;
+
+    let expired = sqlx::types::chrono::Utc::now().naive_utc() - chrono::Duration::days(2);
+    sqlx::query!(
+        "INSERT INTO sessions (id, user_id, created_at, expires_at) VALUES ($1, $2, $3, $3)",
+        Uuid::new_v4(),
+        user_id,
+        expired,
+    )
+    .execute(&pool)
+    .await
+    .expect("error inserting expired session");
+
+    let config = Config::from_env().expect("error reading config");
+    let purged = purge_expired(&pool, &config).await.expect("error purging");
+    assert_eq!(purged.sessions, 1);
+
+    // the session of the signup is still valid
+    let sessions = Session::list_for_user(&pool, user_id).await.unwrap();
+    assert_eq!(sessions.len(), 1);
+}
@@ -0,0 +1,127 @@
+use api::server::rate_limit::{
+    memory::InMemoryRateLimitBackend, postgres::PostgresRateLimitBackend, rate_limit_middleware,
+    RateLimitBackend, RateLimitConfig, RateLimitPolicy, RateLimiter,
+};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    middleware,
+    routing::get,
+    Router,
+};
+use sqlx::PgPool;
+use std::{sync::Arc, time::Duration};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_rate_limit_middleware() {
+    let mut config = RateLimitConfig::default();
+    config.route_policies.insert(
+        "GET /nested/limited".to_string(),
+        RateLimitPolicy::new(2, 60),
+    );
+    let rate_limiter = RateLimiter::new(Arc::new(InMemoryRateLimitBackend::new()), config);
+
+    let nested = Router::new()
+        .route("/limited", get(|| async { "limited" }))
+        .route("/other", get(|| async { "other" }));
+    let app = Router::new()
+        .nest("/nested", nested)
+        .layer(middleware::from_fn_with_state(
+            rate_limiter,
+            rate_limit_middleware,
+        ));
+
+    let request = || {
+        Request::builder()
+            .uri("/nested/limited")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["ratelimit-limit"], "2");
+    assert_eq!(response.headers()["ratelimit-remaining"], "1");
+
+    let response = app.clone().oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["ratelimit-remaining"], "0");
+
+    let response = app.clone().oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+    assert_eq!(response.headers()["ratelimit-remaining"], "0");
+
+    // other routes have their own bucket and fall back to the default policy
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/nested/other")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["ratelimit-limit"],
+        RateLimitConfig::default()
+            .default_policy
+            .requests
+            .to_string()
+    );
+}
+
+#[sqlx::test]
+async fn test_postgres_rate_limit_backend(pool: PgPool) {
+    let backend = PostgresRateLimitBackend::new(pool.clone());
+    let policy = RateLimitPolicy::new(2, 60);
+    let key = format!("test:{}", Uuid::new_v4());
+
+    assert!(backend.take(&key, &policy).await.unwrap().allowed);
+    assert!(backend.take(&key, &policy).await.unwrap().allowed);
+    let decision = backend.take(&key, &policy).await.unwrap();
+    assert!(!decision.allowed);
+    assert!(decision.retry_after_seconds > 0);
+
+    // a second backend, like another instance of the api, shares the same bucket
+    let other_instance = PostgresRateLimitBackend::new(pool.clone());
+    assert!(!other_instance.take(&key, &policy).await.unwrap().allowed);
+
+    // only buckets that are full again are purged
+    let full_key = format!("test:{}", Uuid::new_v4());
+    backend.take(&full_key, &policy).await.unwrap();
+    sqlx::query!(
+        "UPDATE rate_limit_buckets SET full_at = full_at - INTERVAL '1 hour' WHERE key = $1",
+        full_key
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        PostgresRateLimitBackend::purge_expired(&pool)
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(!backend.take(&key, &policy).await.unwrap().allowed);
+}
+
+#[tokio::test]
+async fn test_memory_backend_cleanup() {
+    let backend = InMemoryRateLimitBackend::with_cleanup_interval(Duration::ZERO);
+    let slow = RateLimitPolicy::new(1, 600);
+    let fast = RateLimitPolicy::new(10, 1);
+
+    assert!(backend.take("slow", &slow).await.unwrap().allowed);
+    assert!(backend.take("fast", &fast).await.unwrap().allowed);
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    // a request with a short policy only drops the buckets that are full again
+    assert!(backend.take("other", &fast).await.unwrap().allowed);
+    assert_eq!(backend.bucket_count(), 2);
+    assert!(!backend.take("slow", &slow).await.unwrap().allowed);
+}
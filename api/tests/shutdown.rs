@@ -0,0 +1,149 @@
+use api::server::shutdown::{serve_until, shutdown_middleware, Shutdown};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    middleware,
+    routing::get,
+    Router,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tower::ServiceExt;
+
+fn slow_app(delay: Duration) -> Router {
+    Router::new().route(
+        "/slow",
+        get(move || async move {
+            tokio::time::sleep(delay).await;
+            "done"
+        }),
+    )
+}
+
+async fn get_slow(addr: std::net::SocketAddr) -> String {
+    let mut stream = TcpStream::connect(addr).await.expect("error connecting");
+    stream
+        .write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await
+        .expect("error writing request");
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .await
+        .expect("error reading response");
+
+    response
+}
+
+#[tokio::test]
+async fn test_requests_in_flight_finish() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let shutdown = Shutdown::new();
+    let server = tokio::spawn(serve_until(
+        listener,
+        slow_app(Duration::from_millis(300)),
+        shutdown.clone(),
+        Duration::from_secs(10),
+    ));
+
+    let request = tokio::spawn(get_slow(addr));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    shutdown.trigger();
+
+    let response = request.await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.ends_with("done"));
+    assert!(server.await.unwrap().expect("error serving"));
+
+    // the listener is closed once the server is done
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+// dropped with the handler, the handler below only ends early when it is cancelled
+struct Cancelled(Arc<AtomicBool>);
+
+impl Drop for Cancelled {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn test_drain_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let shutdown = Shutdown::new();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let handler_cancelled = cancelled.clone();
+    let app = Router::new().route(
+        "/slow",
+        get(move || async move {
+            let _guard = Cancelled(handler_cancelled);
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            "done"
+        }),
+    );
+    let server = tokio::spawn(serve_until(
+        listener,
+        app,
+        shutdown.clone(),
+        Duration::from_millis(200),
+    ));
+
+    let request = tokio::spawn(get_slow(addr));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    shutdown.trigger();
+
+    let drained = tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("the server should stop after the drain timeout")
+        .unwrap()
+        .expect("error serving");
+    assert!(!drained);
+
+    // the handler does not keep running in its connection task
+    assert!(cancelled.load(Ordering::SeqCst));
+    let response = tokio::time::timeout(Duration::from_secs(5), request)
+        .await
+        .expect("the cancelled request should be answered")
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 503"));
+    assert!(response.contains("shutting_down"));
+}
+
+#[tokio::test]
+async fn test_connections_are_closed_while_draining() {
+    let shutdown = Shutdown::new();
+    let app =
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(
+                shutdown.clone(),
+                shutdown_middleware,
+            ));
+    let request = || Request::builder().uri("/").body(Body::empty()).unwrap();
+
+    let response = app.clone().oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key(header::CONNECTION));
+
+    shutdown.trigger();
+    assert!(shutdown.is_triggered());
+    // waiting after the fact does not block
+    tokio::time::timeout(Duration::from_secs(1), shutdown.triggered())
+        .await
+        .expect("triggered should resolve right away");
+    let response = app.oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONNECTION], "close");
+}
@@ -0,0 +1,372 @@
+mod common;
+
+use api::{
+    conversation_service::{conversation::Conversation, error::ConversationError},
+    user_service::{
+        block::UserBlock,
+        error::{BlockError, ProfileError, UserSearchError},
+        profile::{ProfileUpdate, UserProfile},
+        search::{Discoverability, SearchQuery, UserDirectory},
+    },
+};
+use common::{create_user, TestUser};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+fn unique_handle() -> String {
+    format!("user_{}", &Uuid::new_v4().simple().to_string()[..12])
+}
+
+#[sqlx::test]
+async fn test_update_profile(pool: PgPool) {
+    let TestUser {
+        id: user_id, email, ..
+    } = create_user(&pool).await;
+    let other_user_id = create_user(&pool).await.id;
+
+    let profile = UserProfile::get_own(&pool, user_id)
+        .await
+        .expect("error getting profile");
+    assert_eq!(profile.email, email);
+    assert_eq!(profile.profile.handle, None);
+
+    let handle = unique_handle();
+    let profile = UserProfile::update(
+        &pool,
+        user_id,
+        ProfileUpdate {
+            handle: Some(format!("@{}", handle.to_uppercase())),
+            display_name: Some("  Test User  ".to_string()),
+            bio: Some("hello".to_string()),
+            status_text: Some("busy".to_string()),
+        },
+    )
+    .await
+    .expect("error updating profile");
+    assert_eq!(profile.profile.handle.as_deref(), Some(handle.as_str()));
+    assert_eq!(profile.profile.display_name.as_deref(), Some("Test User"));
+
+    // fields that are left out stay the same, empty strings clear them
+    let profile = UserProfile::update(
+        &pool,
+        user_id,
+        ProfileUpdate {
+            status_text: Some(String::new()),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("error updating profile");
+    assert_eq!(profile.profile.bio.as_deref(), Some("hello"));
+    assert_eq!(profile.profile.status_text, None);
+
+    // handles are unique regardless of casing
+    match UserProfile::update(
+        &pool,
+        other_user_id,
+        ProfileUpdate {
+            handle: Some(handle.to_uppercase()),
+            ..Default::default()
+        },
+    )
+    .await
+    {
+        Err(ProfileError::HandleTaken { requested_handle }) => {
+            assert_eq!(requested_handle, handle)
+        }
+        res => panic!("unexpected result (should be handle_taken): {:?}", res),
+    }
+
+    for invalid_handle in ["ab", "has space", "dash-es"] {
+        match UserProfile::validate_handle(invalid_handle) {
+            Err(ProfileError::InvalidHandle { .. }) => {}
+            res => panic!("unexpected result (should be invalid_handle): {:?}", res),
+        }
+    }
+    match UserProfile::validate_bio(&"a".repeat(UserProfile::MAX_BIO_LENGTH + 1)) {
+        Err(ProfileError::BioTooLong { .. }) => {}
+        res => panic!("unexpected result (should be bio_too_long): {:?}", res),
+    }
+
+    // search results and conversation lists show the profile instead of only the email
+    let found = UserDirectory::find_by_email(&pool, other_user_id, &email)
+        .await
+        .expect("error looking up user");
+    assert_eq!(found.display_name.as_deref(), Some("Test User"));
+
+    Conversation::start(&pool, other_user_id, user_id)
+        .await
+        .expect("error starting conversation");
+    let conversations = Conversation::list_for_user(&pool, other_user_id)
+        .await
+        .expect("error listing conversations");
+    assert_eq!(conversations.len(), 1);
+    assert_eq!(conversations[0].other_user.id, user_id);
+    assert_eq!(
+        conversations[0].other_user.handle.as_deref(),
+        Some(handle.as_str())
+    );
+}
+
+async fn set_handle(pool: &PgPool, user_id: Uuid, handle: &str, display_name: &str) {
+    UserProfile::update(
+        pool,
+        user_id,
+        ProfileUpdate {
+            handle: Some(handle.to_string()),
+            display_name: Some(display_name.to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("error updating profile");
+}
+
+fn search_query(q: &str, limit: Option<i64>, offset: Option<i64>) -> SearchQuery {
+    SearchQuery {
+        q: q.to_string(),
+        limit,
+        offset,
+    }
+}
+
+#[sqlx::test]
+async fn test_user_search(pool: PgPool) {
+    let TestUser {
+        id: searcher_id,
+        email: searcher_email,
+        ..
+    } = create_user(&pool).await;
+    let TestUser {
+        id: first_id,
+        email: first_email,
+        ..
+    } = create_user(&pool).await;
+    let second_id = create_user(&pool).await.id;
+
+    // a random part that only these users have so other data in the database does not interfere
+    let marker = Uuid::new_v4().simple().to_string()[..10].to_string();
+    set_handle(&pool, searcher_id, &format!("me_{}", marker), "Searcher").await;
+    set_handle(&pool, first_id, &format!("a_{}", marker), "First").await;
+    set_handle(&pool, second_id, "b_other", &format!("Second {}", marker)).await;
+
+    match UserDirectory::search(&pool, searcher_id, &search_query(" a ", None, None)).await {
+        Err(UserSearchError::QueryTooShort { .. }) => {}
+        res => panic!("unexpected result (should be query_too_short): {:?}", res),
+    }
+
+    // handles and display names are matched regardless of casing, the searcher is left out
+    let page = UserDirectory::search(
+        &pool,
+        searcher_id,
+        &search_query(&marker.to_uppercase(), None, None),
+    )
+    .await
+    .expect("error searching users");
+    let mut ids: Vec<Uuid> = page.results.iter().map(|user| user.id).collect();
+    ids.sort();
+    let mut expected = vec![first_id, second_id];
+    expected.sort();
+    assert_eq!(ids, expected);
+    assert_eq!(page.next_offset, None);
+
+    // pagination
+    let first_page =
+        UserDirectory::search(&pool, searcher_id, &search_query(&marker, Some(1), None))
+            .await
+            .expect("error searching users");
+    assert_eq!(first_page.results.len(), 1);
+    assert_eq!(first_page.next_offset, Some(1));
+    let second_page = UserDirectory::search(
+        &pool,
+        searcher_id,
+        &search_query(&marker, Some(1), first_page.next_offset),
+    )
+    .await
+    .expect("error searching users");
+    assert_eq!(second_page.results.len(), 1);
+    assert_eq!(second_page.next_offset, None);
+    assert_ne!(first_page.results[0].id, second_page.results[0].id);
+
+    // emails are never matched partially
+    let page = UserDirectory::search(&pool, searcher_id, &search_query("TestUser", None, None))
+        .await
+        .expect("error searching users");
+    assert!(page.results.iter().all(|user| user.id != first_id));
+
+    // users can hide from the search and from email lookups
+    UserDirectory::find_by_email(&pool, searcher_id, &first_email.to_uppercase())
+        .await
+        .expect("error looking up user");
+    let hidden = Discoverability {
+        searchable: false,
+        discoverable_by_email: false,
+    };
+    UserDirectory::set_discoverability(&pool, first_id, hidden)
+        .await
+        .expect("error setting discoverability");
+    assert_eq!(
+        UserDirectory::get_discoverability(&pool, first_id)
+            .await
+            .expect("error getting discoverability"),
+        hidden
+    );
+
+    let page = UserDirectory::search(&pool, searcher_id, &search_query(&marker, None, None))
+        .await
+        .expect("error searching users");
+    assert_eq!(page.results.len(), 1);
+    assert_eq!(page.results[0].id, second_id);
+
+    match UserDirectory::find_by_email(&pool, searcher_id, &first_email).await {
+        Err(UserSearchError::NoUsersFound) => {}
+        res => panic!("unexpected result (should be no_users_found): {:?}", res),
+    }
+    match UserDirectory::find_by_email(&pool, searcher_id, "missing@email.com").await {
+        Err(UserSearchError::NoUsersFound) => {}
+        res => panic!("unexpected result (should be no_users_found): {:?}", res),
+    }
+    UserDirectory::find_by_email(&pool, searcher_id, &searcher_email)
+        .await
+        .expect("users should always find themselves");
+}
+
+#[sqlx::test]
+async fn test_block_user(pool: PgPool) {
+    let user_id = create_user(&pool).await.id;
+    let TestUser {
+        id: blocked_id,
+        email: blocked_email,
+        ..
+    } = create_user(&pool).await;
+
+    let marker = Uuid::new_v4().simple().to_string()[..10].to_string();
+    set_handle(&pool, user_id, &format!("a_{}", marker), "Blocker").await;
+    set_handle(&pool, blocked_id, &format!("b_{}", marker), "Blocked").await;
+
+    let conversation_id = Conversation::start(&pool, user_id, blocked_id)
+        .await
+        .expect("error starting conversation");
+
+    match UserBlock::block(&pool, user_id, user_id).await {
+        Err(BlockError::CannotBlockSelf) => {}
+        res => panic!("unexpected result (should be cannot_block_self): {:?}", res),
+    }
+    match UserBlock::block(&pool, user_id, Uuid::new_v4()).await {
+        Err(BlockError::UserNotFound) => {}
+        res => panic!("unexpected result (should be user_not_found): {:?}", res),
+    }
+
+    UserBlock::block(&pool, user_id, blocked_id)
+        .await
+        .expect("error blocking user");
+    // blocking twice is fine
+    UserBlock::block(&pool, user_id, blocked_id)
+        .await
+        .expect("error blocking user");
+
+    let blocked_users = UserBlock::list(&pool, user_id)
+        .await
+        .expect("error listing blocked users");
+    assert_eq!(blocked_users.len(), 1);
+    assert_eq!(blocked_users[0].id, blocked_id);
+
+    // neither side can send messages
+    for sender_id in [user_id, blocked_id] {
+        match Conversation::send_message(&pool, sender_id, conversation_id, "hello").await {
+            Err(ConversationError::Blocked) => {}
+            res => panic!("unexpected result (should be blocked): {:?}", res),
+        }
+    }
+
+    // the blocked user cannot find or look at the blocker anymore
+    let page = UserDirectory::search(&pool, blocked_id, &search_query(&marker, None, None))
+        .await
+        .expect("error searching users");
+    assert!(page.results.is_empty());
+    let page = UserDirectory::search(&pool, user_id, &search_query(&marker, None, None))
+        .await
+        .expect("error searching users");
+    assert!(page.results.is_empty());
+    match UserDirectory::find_by_email(&pool, user_id, &blocked_email).await {
+        Err(UserSearchError::NoUsersFound) => {}
+        res => panic!("unexpected result (should be no_users_found): {:?}", res),
+    }
+    match UserProfile::get_for_viewer(&pool, blocked_id, user_id).await {
+        Err(ProfileError::UserNotFound) => {}
+        res => panic!("unexpected result (should be user_not_found): {:?}", res),
+    }
+    UserProfile::get_for_viewer(&pool, user_id, blocked_id)
+        .await
+        .expect("the blocker can still see the blocked profile");
+
+    UserBlock::unblock(&pool, user_id, blocked_id)
+        .await
+        .expect("error unblocking user");
+    Conversation::accept_request(&pool, blocked_id, conversation_id)
+        .await
+        .expect("error accepting message request");
+    Conversation::send_message(&pool, blocked_id, conversation_id, "hello")
+        .await
+        .expect("error sending message after unblocking");
+
+    // a block also stops new conversations
+    UserBlock::block(&pool, blocked_id, user_id)
+        .await
+        .expect("error blocking user");
+    sqlx::query!("DELETE FROM conversations WHERE id = $1", conversation_id)
+        .execute(&pool)
+        .await
+        .expect("error deleting conversation");
+    match Conversation::start(&pool, user_id, blocked_id).await {
+        Err(ConversationError::Blocked) => {}
+        res => panic!("unexpected result (should be blocked): {:?}", res),
+    }
+}
+
+#[sqlx::test]
+async fn test_avatar(pool: PgPool) {
+    let user_id = create_user(&pool).await.id;
+
+    match UserProfile::set_avatar(&pool, user_id, b"not an image").await {
+        Err(ProfileError::UnsupportedAvatarType) => {}
+        res => panic!(
+            "unexpected result (should be unsupported_avatar_type): {:?}",
+            res
+        ),
+    }
+
+    let too_large = vec![0u8; UserProfile::MAX_AVATAR_BYTES + 1];
+    match UserProfile::set_avatar(&pool, user_id, &too_large).await {
+        Err(ProfileError::AvatarTooLarge { .. }) => {}
+        res => panic!("unexpected result (should be avatar_too_large): {:?}", res),
+    }
+
+    let png = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0];
+    UserProfile::set_avatar(&pool, user_id, &png)
+        .await
+        .expect("error setting avatar");
+
+    let avatar = UserProfile::get_avatar(&pool, user_id)
+        .await
+        .expect("error getting avatar");
+    assert_eq!(avatar.content_type, "image/png");
+    assert_eq!(avatar.data, png);
+
+    let profile = UserProfile::get(&pool, user_id)
+        .await
+        .expect("error getting profile");
+    assert_eq!(
+        profile.avatar_url,
+        Some(format!("/v1/users/{}/avatar", user_id))
+    );
+
+    UserProfile::remove_avatar(&pool, user_id)
+        .await
+        .expect("error removing avatar");
+    match UserProfile::get_avatar(&pool, user_id).await {
+        Err(ProfileError::AvatarNotFound) => {}
+        Err(e) => panic!("unexpected error (should be avatar_not_found): {:?}", e),
+        Ok(_) => panic!("avatar should have been removed"),
+    }
+}
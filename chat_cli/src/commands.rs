@@ -0,0 +1,215 @@
+use crate::{error::CliError, Command};
+use chat_client::{
+    Client, ClientError, ConversationSummary, ErrorCode, Message, PublicUserData, SearchQuery,
+    SignInOutcome,
+};
+use futures::StreamExt;
+use std::io::BufRead;
+use uuid::Uuid;
+
+pub async fn run(client: &Client, command: Command) -> Result<(), CliError> {
+    match command {
+        Command::Signup { email, password } => {
+            let password = password_or_prompt(password)?;
+            client.signup(&email, &password).await?;
+            println!("Signed up as {}", email);
+        }
+        Command::Signin {
+            email,
+            password,
+            code,
+        } => {
+            let password = password_or_prompt(password)?;
+            if let SignInOutcome::MfaRequired(mfa_token) = client.signin(&email, &password).await? {
+                let code = match code {
+                    Some(code) => code,
+                    None => prompt("Authentication code: ")?,
+                };
+                client.verify_mfa(&mfa_token, &code).await?;
+            }
+            println!("Signed in as {}", email);
+        }
+        Command::Signout => client.signout(),
+        Command::Whoami => {
+            require_token(client)?;
+            let own = client.own_profile().await?;
+            println!("{}\t{}", own.profile.id, own.email);
+        }
+        Command::Search { query, limit } => {
+            require_token(client)?;
+            let query = SearchQuery {
+                q: query,
+                limit,
+                offset: None,
+            };
+            for user in client.search_users(&query).await?.results {
+                println!("{}\t{}", user.id, name(&user));
+            }
+        }
+        Command::Start { user } => {
+            require_token(client)?;
+            let receiver_id = match Uuid::parse_str(&user) {
+                Ok(id) => id,
+                Err(_) if user.contains('@') => client.lookup_user(&user).await?.id,
+                Err(_) => return Err(CliError::InvalidUser(user)),
+            };
+            println!("{}", start(client, receiver_id).await?);
+        }
+        Command::Inbox { requests } => {
+            require_token(client)?;
+            let conversations = if requests {
+                client.list_requests().await?
+            } else {
+                client.list_conversations().await?
+            };
+            for conversation in conversations {
+                println!("{}", summary_line(&conversation));
+            }
+        }
+        Command::Accept { conversation_id } => {
+            require_token(client)?;
+            client.accept_request(conversation_id).await?;
+        }
+        Command::Decline { conversation_id } => {
+            require_token(client)?;
+            client.decline_request(conversation_id).await?;
+        }
+        Command::Messages { conversation_id } => {
+            require_token(client)?;
+            let names = Names::load(client, conversation_id).await?;
+            for message in client.list_messages(conversation_id).await? {
+                println!("{}", names.message_line(&message));
+            }
+        }
+        Command::Tail { conversation_id } => {
+            require_token(client)?;
+            let names = Names::load(client, conversation_id).await?;
+            let mut messages = Box::pin(client.message_stream(conversation_id));
+            while let Some(message) = messages.next().await {
+                match message {
+                    Ok(message) => println!("{}", names.message_line(&message)),
+                    // the stream polls again, a flaky connection should not end the tail
+                    Err(e) => eprintln!("error: {}", CliError::from(e)),
+                }
+            }
+        }
+        Command::Send {
+            conversation_id,
+            text,
+        } => {
+            require_token(client)?;
+            if !text.is_empty() {
+                client
+                    .send_message(conversation_id, &text.join(" "))
+                    .await?;
+                return Ok(());
+            }
+
+            for line in std::io::stdin().lock().lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    client.send_message(conversation_id, &line).await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// an existing conversation is not an error here, its id is printed like a new one
+async fn start(client: &Client, receiver_id: Uuid) -> Result<Uuid, CliError> {
+    match client.create_conversation(receiver_id).await {
+        Ok(conversation_id) => Ok(conversation_id),
+        Err(ClientError::Api { status, body })
+            if body.code == ErrorCode::ConversationAlreadyExists =>
+        {
+            let existing = body
+                .details
+                .as_ref()
+                .and_then(|details| details["conversation_id"].as_str())
+                .and_then(|id| Uuid::parse_str(id).ok());
+            existing.ok_or(CliError::Client(ClientError::Api { status, body }))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// the api would answer with missing_token, this says what to do about it
+fn require_token(client: &Client) -> Result<(), CliError> {
+    client.token().map(|_| ()).ok_or(CliError::NotSignedIn)
+}
+
+fn password_or_prompt(password: Option<String>) -> Result<String, CliError> {
+    match password {
+        Some(password) => Ok(password),
+        None => Ok(rpassword::prompt_password("Password: ")?),
+    }
+}
+
+fn prompt(text: &str) -> Result<String, CliError> {
+    eprint!("{}", text);
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+fn name(user: &PublicUserData) -> String {
+    user.display_name
+        .clone()
+        .or_else(|| user.handle.as_ref().map(|handle| format!("@{}", handle)))
+        .unwrap_or_else(|| user.id.to_string())
+}
+
+// tab separated so the output can be used with cut and awk
+fn summary_line(conversation: &ConversationSummary) -> String {
+    let mut flags = Vec::new();
+    if !conversation.accepted {
+        flags.push("request");
+    }
+    if conversation.muted {
+        flags.push("muted");
+    }
+
+    format!(
+        "{}\t{}\t{}",
+        conversation.id,
+        name(&conversation.other_user),
+        flags.join(",")
+    )
+}
+
+// the names shown next to the messages of one conversation
+struct Names {
+    own_id: Uuid,
+    other: Option<PublicUserData>,
+}
+
+impl Names {
+    async fn load(client: &Client, conversation_id: Uuid) -> Result<Self, CliError> {
+        let own_id = client.own_profile().await?.profile.id;
+        let mut conversations = client.list_conversations().await?;
+        conversations.extend(client.list_requests().await?);
+        let other = conversations
+            .into_iter()
+            .find(|conversation| conversation.id == conversation_id)
+            .map(|conversation| conversation.other_user);
+
+        Ok(Self { own_id, other })
+    }
+
+    fn message_line(&self, message: &Message) -> String {
+        let sender = match &self.other {
+            _ if message.sender_id == self.own_id => "you".to_string(),
+            Some(other) if other.id == message.sender_id => name(other),
+            _ => message.sender_id.to_string(),
+        };
+
+        format!(
+            "{}\t{}\t{}",
+            message.sent_at.format("%Y-%m-%d %H:%M"),
+            sender,
+            message.content
+        )
+    }
+}
@@ -0,0 +1,95 @@
+use crate::error::CliError;
+use chat_client::{JwtTokenString, TokenStore};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+// ~/.config/chat/config.toml, it holds the token so it is only readable by the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    pub server: String,
+    pub token: Option<JwtTokenString>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            server: "http://localhost:3000".to_string(),
+            token: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("chat").join("config.toml"))
+    }
+
+    // a missing file is the default config
+    pub fn load(path: &Path) -> Result<Self, CliError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CliError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        // a new file is created readable only by the user, so the token is never readable by others
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path)?;
+
+        // files from before this was created with the mode are fixed before the token is written
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+
+        file.write_all(toml::to_string(self)?.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+// keeps the token of the client in the config file, so the next command is still signed in
+pub struct FileTokenStore {
+    path: PathBuf,
+    config: Mutex<Config>,
+}
+
+impl FileTokenStore {
+    pub fn new(path: PathBuf, config: Config) -> Self {
+        Self {
+            path,
+            config: Mutex::new(config),
+        }
+    }
+}
+
+impl TokenStore for FileTokenStore {
+    fn load(&self) -> Option<JwtTokenString> {
+        self.config.lock().unwrap().token.clone()
+    }
+
+    fn save(&self, token: Option<JwtTokenString>) {
+        let mut config = self.config.lock().unwrap();
+        config.token = token;
+        if let Err(e) = config.save(&self.path) {
+            eprintln!("could not save {}: {}", self.path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use chat_client::ClientError;
+use derive_more::From;
+
+#[derive(Debug, From)]
+pub enum CliError {
+    // no config dir on this platform and no `--config`
+    NoConfigPath,
+    // `start` with something that is neither a user id nor an email
+    InvalidUser(String),
+    NotSignedIn,
+
+    #[from]
+    Client(ClientError),
+
+    #[from]
+    Io(std::io::Error),
+
+    #[from]
+    ConfigRead(toml::de::Error),
+
+    #[from]
+    ConfigWrite(toml::ser::Error),
+}
+
+// printed to stderr, api errors show the message of the envelope instead of the debug output
+impl core::fmt::Display for CliError {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        match self {
+            Self::NoConfigPath => write!(fmt, "no config directory found, pass --config"),
+            Self::InvalidUser(user) => write!(fmt, "{} is not a user id or an email", user),
+            Self::NotSignedIn => write!(fmt, "not signed in, run `chat signin <email>` first"),
+            Self::Client(ClientError::Api { body, .. }) => {
+                write!(fmt, "{} ({:?})", body.message, body.code)
+            }
+            Self::Client(e) => write!(fmt, "request failed: {}", e),
+            Self::Io(e) => write!(fmt, "{}", e),
+            Self::ConfigRead(e) => write!(fmt, "invalid config file: {}", e),
+            Self::ConfigWrite(e) => write!(fmt, "could not write the config file: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
@@ -0,0 +1,106 @@
+mod commands;
+mod config;
+mod error;
+
+use chat_client::Client;
+use clap::{Parser, Subcommand};
+use config::{Config, FileTokenStore};
+use error::CliError;
+use std::{path::PathBuf, process::ExitCode};
+use uuid::Uuid;
+
+/// A terminal client for the chat api, the token is kept in the config file between commands
+#[derive(Parser)]
+#[command(name = "chat")]
+struct Cli {
+    /// The config file, defaults to ~/.config/chat/config.toml
+    #[arg(long, env = "CHAT_CONFIG", global = true)]
+    config: Option<PathBuf>,
+
+    /// The url of the server, it is saved in the config file
+    #[arg(long, env = "CHAT_SERVER", global = true)]
+    server: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Create an account and sign in
+    Signup {
+        email: String,
+        /// Asked for when it is not set
+        #[arg(long, env = "CHAT_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+    },
+    /// Sign in, the code is asked for when two-factor authentication is enabled
+    Signin {
+        email: String,
+        #[arg(long, env = "CHAT_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// The code from the authenticator app
+        #[arg(long)]
+        code: Option<String>,
+    },
+    /// Forget the token
+    Signout,
+    /// Show the profile of the signed in user
+    Whoami,
+    /// Search users by handle or display name
+    Search {
+        query: String,
+        #[arg(long)]
+        limit: Option<i64>,
+    },
+    /// Start a conversation with a user id or email, prints the conversation id
+    Start { user: String },
+    /// List conversations, or message requests with --requests
+    Inbox {
+        #[arg(long)]
+        requests: bool,
+    },
+    /// Accept a message request
+    Accept { conversation_id: Uuid },
+    /// Decline a message request
+    Decline { conversation_id: Uuid },
+    /// Print the messages of a conversation
+    Messages { conversation_id: Uuid },
+    /// Print the messages of a conversation and follow new ones until interrupted
+    Tail { conversation_id: Uuid },
+    /// Send a message, without text every line from stdin is sent as a message
+    Send {
+        conversation_id: Uuid,
+        text: Vec<String>,
+    },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), CliError> {
+    let path = cli
+        .config
+        .or_else(Config::default_path)
+        .ok_or(CliError::NoConfigPath)?;
+    let mut config = Config::load(&path)?;
+    if let Some(server) = cli.server {
+        config.server = server;
+    }
+
+    let client = Client::builder(&config.server)
+        .token_store(FileTokenStore::new(path, config))
+        .build();
+
+    commands::run(&client, cli.command).await
+}
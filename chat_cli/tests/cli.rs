@@ -0,0 +1,171 @@
+use api::{
+    auth_service::user::User, db_service::get_connection_pool, mail_service::LogMailer,
+    server::routes, server::AppState,
+};
+use std::{
+    io::Write,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    sync::Arc,
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    net::TcpListener,
+};
+use uuid::Uuid;
+
+async fn spawn_server() -> String {
+    let pool = get_connection_pool().await.expect("error getting pg pool");
+    let app = routes(AppState::new(pool, Arc::new(LogMailer)));
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("error binding listener");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+
+    format!("http://{}", addr)
+}
+
+// one user of the cli, every user has their own config file
+struct CliUser {
+    config: PathBuf,
+    server: String,
+}
+
+impl CliUser {
+    fn new(dir: &Path, server: &str) -> Self {
+        Self {
+            config: dir.join(format!("{}.toml", Uuid::new_v4())),
+            server: server.to_string(),
+        }
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_chat"));
+        command
+            .args(args)
+            .env("CHAT_CONFIG", &self.config)
+            .env("CHAT_SERVER", &self.server)
+            .env("CHAT_PASSWORD", "Password123#");
+        command
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().expect("error running chat")
+    }
+
+    fn stdout(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "chat {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn id(&self) -> Uuid {
+        let whoami = self.stdout(&["whoami"]);
+        Uuid::parse_str(whoami.split('\t').next().unwrap()).unwrap()
+    }
+}
+
+// the cli runs as blocking child processes, the server needs threads of its own
+#[tokio::test(flavor = "multi_thread")]
+async fn test_chat_cli() {
+    let server = spawn_server().await;
+    let dir = std::env::temp_dir().join(format!("chat-cli-{}", Uuid::new_v4()));
+    let alice = CliUser::new(&dir, &server);
+    let bob = CliUser::new(&dir, &server);
+
+    // the token and the server are saved in the config file
+    for user in [&alice, &bob] {
+        let email = format!("testuser{}@email.com", Uuid::new_v4());
+        user.stdout(&["signup", &email]);
+    }
+    let config = std::fs::read_to_string(&alice.config).unwrap();
+    assert!(config.contains(&server));
+    assert!(config.contains("token"));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&alice.config)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let alice_id = alice.id();
+    let bob_id = bob.id();
+
+    // starting the same conversation again prints the existing id
+    let conversation_id = alice.stdout(&["start", &bob_id.to_string()]);
+    let conversation_id = conversation_id.trim();
+    assert_eq!(
+        alice.stdout(&["start", &bob_id.to_string()]).trim(),
+        conversation_id
+    );
+    let requests = bob.stdout(&["inbox", "--requests"]);
+    assert!(requests.contains(conversation_id));
+    bob.stdout(&["accept", conversation_id]);
+
+    // every line from stdin is a message, empty lines are skipped
+    let mut send = alice
+        .command(&["send", conversation_id])
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    send.stdin
+        .take()
+        .unwrap()
+        .write_all(b"hello\n\nhow are you?\n")
+        .unwrap();
+    assert!(send.wait().unwrap().success());
+    let messages = alice.stdout(&["messages", conversation_id]);
+    let lines: Vec<&str> = messages.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with("\tyou\thello"));
+
+    // tail prints the history and then follows the conversation
+    let mut tail = tokio::process::Command::from(bob.command(&["tail", conversation_id]))
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+    let mut tail_lines = BufReader::new(tail.stdout.take().unwrap()).lines();
+    for expected in ["hello", "how are you?"] {
+        let line = tail_lines.next_line().await.unwrap().unwrap();
+        assert!(line.ends_with(expected));
+    }
+    alice.stdout(&["send", conversation_id, "still", "there?"]);
+    let line = tokio::time::timeout(std::time::Duration::from_secs(10), tail_lines.next_line())
+        .await
+        .expect("tail did not print the new message")
+        .unwrap()
+        .unwrap();
+    assert!(line.ends_with("still there?"));
+    tail.kill().await.unwrap();
+
+    // signed out commands fail without calling the api
+    alice.stdout(&["signout"]);
+    let output = alice.run(&["inbox"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not signed in"));
+
+    let pool = get_connection_pool().await.expect("error getting pg pool");
+    for user_id in [alice_id, bob_id] {
+        User::delete_user_by_id(&pool, user_id)
+            .await
+            .expect("Error deleting test user");
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
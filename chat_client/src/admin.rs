@@ -0,0 +1,52 @@
+use crate::{Client, Result, NO_BODY};
+use api::{
+    admin_service::{router::SuspendRequest, AdminUserView, SystemStats, UserFilter},
+    audit_service::{AuditEvent, AuditFilter},
+    auth_service::session::Session,
+};
+use reqwest::Method;
+use uuid::Uuid;
+
+// /v1/admin, every method requires an admin
+impl Client {
+    pub async fn stats(&self) -> Result<SystemStats> {
+        self.request(Method::GET, "/admin/stats", NO_BODY).await
+    }
+
+    pub async fn list_users(&self, filter: &UserFilter) -> Result<Vec<AdminUserView>> {
+        self.query("/admin/users", filter).await
+    }
+
+    pub async fn user(&self, user_id: Uuid) -> Result<AdminUserView> {
+        let path = format!("/admin/users/{}", user_id);
+        self.request(Method::GET, &path, NO_BODY).await
+    }
+
+    pub async fn suspend_user(
+        &self,
+        user_id: Uuid,
+        request: &SuspendRequest,
+    ) -> Result<AdminUserView> {
+        let path = format!("/admin/users/{}/suspend", user_id);
+        self.request(Method::POST, &path, Some(request)).await
+    }
+
+    pub async fn reactivate_user(&self, user_id: Uuid) -> Result<AdminUserView> {
+        let path = format!("/admin/users/{}/reactivate", user_id);
+        self.request(Method::POST, &path, NO_BODY).await
+    }
+
+    pub async fn force_password_reset(&self, user_id: Uuid) -> Result<()> {
+        let path = format!("/admin/users/{}/password-reset", user_id);
+        self.request_empty(Method::POST, &path, NO_BODY).await
+    }
+
+    pub async fn user_sessions(&self, user_id: Uuid) -> Result<Vec<Session>> {
+        let path = format!("/admin/users/{}/sessions", user_id);
+        self.request(Method::GET, &path, NO_BODY).await
+    }
+
+    pub async fn audit_events(&self, filter: &AuditFilter) -> Result<Vec<AuditEvent>> {
+        self.query("/admin/audit-events", filter).await
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{Client, ClientError, Result, NO_BODY};
+use api::auth_service::{
+    claims::{JwtTokenString, MfaTokenString},
+    mfa::{RecoveryCodes, TotpEnrollment},
+    router::{
+        AuthForm, ChangePasswordForm, DeactivateAccountForm, DeleteAccountForm, ForgotPasswordForm,
+        MfaCodeForm, MfaDisableForm, MfaRequiredResponse, MfaVerifyForm, ResetPasswordForm,
+    },
+    user::SignInOutcome,
+};
+use reqwest::{header::AUTHORIZATION, Method, Response, StatusCode};
+use serde::Serialize;
+
+// /v1/auth, the token of signup and signin is saved in the token store
+impl Client {
+    pub async fn signup(&self, email: &str, password: &str) -> Result<()> {
+        let form = AuthForm {
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+        match self.authenticate("/auth/signup", &form).await? {
+            SignInOutcome::Authenticated(_) => Ok(()),
+            // a new account never has 2fa enabled
+            SignInOutcome::MfaRequired(_) => Err(ClientError::UnexpectedResponse {
+                status: StatusCode::ACCEPTED,
+                body: String::new(),
+            }),
+        }
+    }
+
+    // with two-factor authentication the returned mfa token has to be sent to `verify_mfa`
+    pub async fn signin(&self, email: &str, password: &str) -> Result<SignInOutcome> {
+        let form = AuthForm {
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+        self.authenticate("/auth/signin", &form).await
+    }
+
+    pub async fn verify_mfa(&self, mfa_token: &MfaTokenString, code: &str) -> Result<()> {
+        let form = MfaVerifyForm {
+            mfa_token: mfa_token.clone(),
+            code: code.to_string(),
+        };
+        self.authenticate("/auth/mfa/verify", &form).await?;
+        Ok(())
+    }
+
+    // deactivated accounts sign in again with this instead of `signin`
+    pub async fn reactivate_account(&self, email: &str, password: &str) -> Result<SignInOutcome> {
+        let form = AuthForm {
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+        self.authenticate("/auth/account/reactivate", &form).await
+    }
+
+    // only forgets the token, the session stays valid on the server until it expires
+    pub fn signout(&self) {
+        self.set_token(None);
+    }
+
+    // a new token for the same session, `Client` calls this itself when the token is about to
+    // expire, see `ClientBuilder::refresh_before`
+    pub async fn refresh_token(&self) -> Result<()> {
+        let mut request = self.http.post(self.url("/auth/refresh"));
+        if let Some(token) = self.tokens.load() {
+            request = request.header(AUTHORIZATION, token);
+        }
+
+        let response = request.send().await?;
+        if response.status() != StatusCode::OK {
+            return Err(ClientError::from_response(response).await);
+        }
+        self.set_token(Some(authorization_header(&response)?));
+        Ok(())
+    }
+
+    // not sent through `Client::send`, an expired token does not matter here
+    async fn authenticate(&self, path: &str, form: &impl Serialize) -> Result<SignInOutcome> {
+        let response = self.http.post(self.url(path)).json(form).send().await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let token = authorization_header(&response)?;
+                self.set_token(Some(token.clone()));
+                Ok(SignInOutcome::Authenticated(token))
+            }
+            StatusCode::ACCEPTED => {
+                let mfa: MfaRequiredResponse = response.json().await?;
+                Ok(SignInOutcome::MfaRequired(mfa.mfa_token))
+            }
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    pub async fn forgot_password(&self, email: &str) -> Result<()> {
+        let form = ForgotPasswordForm {
+            email: email.to_string(),
+        };
+        self.request_empty(Method::POST, "/auth/password/forgot", Some(&form))
+            .await
+    }
+
+    // every session is signed out, sign in again with the new password
+    pub async fn reset_password(&self, token: &str, password: &str) -> Result<()> {
+        let form = ResetPasswordForm {
+            token: token.to_string(),
+            password: password.to_string(),
+        };
+        self.request_empty(Method::POST, "/auth/password/reset", Some(&form))
+            .await
+    }
+
+    // the current session stays signed in, every other one is signed out
+    pub async fn change_password(&self, current_password: &str, new_password: &str) -> Result<()> {
+        let form = ChangePasswordForm {
+            current_password: current_password.to_string(),
+            new_password: new_password.to_string(),
+        };
+        self.request_empty(Method::POST, "/auth/password", Some(&form))
+            .await
+    }
+
+    // `code` is a totp or recovery code, the server asks for it when 2fa is enabled
+    pub async fn delete_account(&self, password: &str, code: Option<&str>) -> Result<()> {
+        let form = DeleteAccountForm {
+            password: password.to_string(),
+            code: code.map(str::to_string),
+        };
+        self.request_empty(Method::DELETE, "/auth/account", Some(&form))
+            .await?;
+        self.signout();
+        Ok(())
+    }
+
+    pub async fn deactivate_account(&self, password: &str) -> Result<()> {
+        let form = DeactivateAccountForm {
+            password: password.to_string(),
+        };
+        self.request_empty(Method::POST, "/auth/account/deactivate", Some(&form))
+            .await?;
+        self.signout();
+        Ok(())
+    }
+
+    // 2fa is only enabled after `confirm_mfa` with a code from the authenticator app
+    pub async fn enroll_mfa(&self) -> Result<TotpEnrollment> {
+        self.request(Method::POST, "/auth/mfa/enroll", NO_BODY)
+            .await
+    }
+
+    pub async fn confirm_mfa(&self, code: &str) -> Result<RecoveryCodes> {
+        let form = MfaCodeForm {
+            code: code.to_string(),
+        };
+        self.request(Method::POST, "/auth/mfa/confirm", Some(&form))
+            .await
+    }
+
+    pub async fn disable_mfa(&self, password: &str, code: &str) -> Result<()> {
+        let form = MfaDisableForm {
+            password: password.to_string(),
+            code: code.to_string(),
+        };
+        self.request_empty(Method::POST, "/auth/mfa/disable", Some(&form))
+            .await
+    }
+}
+
+// the token of signin, signup, mfa verify and refresh
+fn authorization_header(response: &Response) -> Result<JwtTokenString> {
+    response
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header_value| header_value.to_str().ok())
+        .map(str::to_string)
+        .ok_or(ClientError::UnexpectedResponse {
+            status: response.status(),
+            body: "no authorization header".to_string(),
+        })
+}
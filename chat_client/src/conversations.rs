@@ -0,0 +1,77 @@
+use crate::{Client, Result, NO_BODY};
+use api::conversation_service::{
+    conversation::ConversationSummary,
+    message::Message,
+    router::{CreatedConversation, MessagesQuery, NewConversation, NewMessage},
+};
+use reqwest::Method;
+use uuid::Uuid;
+
+// /v1/conversations
+impl Client {
+    pub async fn list_conversations(&self) -> Result<Vec<ConversationSummary>> {
+        self.request(Method::GET, "/conversations", NO_BODY).await
+    }
+
+    // for people that do not have the current user in their contacts this is a message request,
+    // an existing conversation is a `conversation_already_exists` error with its id in the details
+    pub async fn create_conversation(&self, receiver_id: Uuid) -> Result<Uuid> {
+        let created: CreatedConversation = self
+            .request(
+                Method::POST,
+                "/conversations",
+                Some(&NewConversation { receiver_id }),
+            )
+            .await?;
+        Ok(created.conversation_id)
+    }
+
+    pub async fn list_requests(&self) -> Result<Vec<ConversationSummary>> {
+        self.request(Method::GET, "/conversations/requests", NO_BODY)
+            .await
+    }
+
+    pub async fn accept_request(&self, conversation_id: Uuid) -> Result<()> {
+        let path = format!("/conversations/{}/accept", conversation_id);
+        self.request_empty(Method::POST, &path, NO_BODY).await
+    }
+
+    pub async fn decline_request(&self, conversation_id: Uuid) -> Result<()> {
+        let path = format!("/conversations/{}/decline", conversation_id);
+        self.request_empty(Method::POST, &path, NO_BODY).await
+    }
+
+    // oldest message first, see `Client::message_stream` to follow a conversation
+    pub async fn list_messages(&self, conversation_id: Uuid) -> Result<Vec<Message>> {
+        let path = format!("/conversations/{}/messages", conversation_id);
+        self.request(Method::GET, &path, NO_BODY).await
+    }
+
+    // the messages sent after `after`, oldest first, usually the last message the caller has
+    pub async fn list_messages_after(
+        &self,
+        conversation_id: Uuid,
+        after: &Message,
+    ) -> Result<Vec<Message>> {
+        let path = format!("/conversations/{}/messages", conversation_id);
+        let query = MessagesQuery {
+            after: Some(after.sent_at),
+            after_id: Some(after.id),
+        };
+        self.query(&path, &query).await
+    }
+
+    pub async fn send_message(&self, conversation_id: Uuid, content: &str) -> Result<Message> {
+        let path = format!("/conversations/{}/messages", conversation_id);
+        let message = NewMessage {
+            content: content.to_string(),
+        };
+        self.request(Method::POST, &path, Some(&message)).await
+    }
+
+    pub async fn set_muted(&self, conversation_id: Uuid, muted: bool) -> Result<()> {
+        let path = format!("/conversations/{}/mute", conversation_id);
+        let method = if muted { Method::PUT } else { Method::DELETE };
+        self.request_empty(method, &path, NO_BODY).await
+    }
+}
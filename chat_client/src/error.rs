@@ -0,0 +1,60 @@
+use api::server::error::{ErrorBody, ErrorCode};
+use derive_more::From;
+use reqwest::{Response, StatusCode};
+
+#[derive(Debug, From)]
+pub enum ClientError {
+    // the server could not be reached or the response was not the documented json
+    #[from]
+    Http(reqwest::Error),
+
+    // an error response of the api, match on `body.code`, see api/docs/error-codes.md
+    Api {
+        status: StatusCode,
+        body: ErrorBody,
+    },
+
+    // an error response without the json envelope, e.g. from a proxy in front of the api
+    UnexpectedResponse {
+        status: StatusCode,
+        body: String,
+    },
+}
+
+impl ClientError {
+    pub(crate) async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(e) => return Self::Http(e),
+        };
+
+        match serde_json::from_str::<ErrorBody>(&body) {
+            Ok(body) => Self::Api { status, body },
+            Err(_) => Self::UnexpectedResponse { status, body },
+        }
+    }
+
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::Api { body, .. } => Some(body.code),
+            _ => None,
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Http(e) => e.status(),
+            Self::Api { status, .. } | Self::UnexpectedResponse { status, .. } => Some(*status),
+        }
+    }
+}
+
+// same as `api::AppError`, the debug output has everything that is needed
+impl core::fmt::Display for ClientError {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for ClientError {}
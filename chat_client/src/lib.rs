@@ -0,0 +1,196 @@
+pub mod admin;
+pub mod auth;
+pub mod conversations;
+pub mod error;
+pub mod moderation;
+pub mod stream;
+pub mod token;
+pub mod users;
+
+pub use api::{
+    admin_service::{router::SuspendRequest, AdminUserView, SystemStats, UserFilter},
+    audit_service::{AuditEvent, AuditEventKind, AuditFilter},
+    auth_service::{
+        claims::{JwtTokenString, MfaTokenString},
+        mfa::{RecoveryCodes, TotpEnrollment},
+        session::Session,
+        user::{PublicUserData, Role, SignInOutcome},
+    },
+    conversation_service::{
+        conversation::ConversationSummary,
+        message::Message,
+        router::{CreatedConversation, NewConversation, NewMessage},
+    },
+    moderation_service::report::{
+        ModerationActionKind, ModerationRequest, NewReport, Report, ReportDetails, ReportFilter,
+        ReportReason, ReportStatus,
+    },
+    server::error::{ErrorBody, ErrorCode},
+    user_service::{
+        profile::{OwnProfile, ProfileUpdate, UserProfile},
+        search::{Discoverability, SearchPage, SearchQuery},
+    },
+};
+pub use error::ClientError;
+pub use token::{MemoryTokenStore, TokenStore};
+
+use reqwest::{header::AUTHORIZATION, Method, RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{sync::Arc, time::Duration};
+
+pub type Result<T> = core::result::Result<T, ClientError>;
+
+// for `Client::request` on routes without a body
+const NO_BODY: Option<&()> = None;
+
+// a client for the /v1 api, cheap to clone, every clone shares the token
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    tokens: Arc<dyn TokenStore>,
+    refresh_before: Duration,
+    poll_interval: Duration,
+}
+
+pub struct ClientBuilder {
+    base_url: String,
+    tokens: Arc<dyn TokenStore>,
+    refresh_before: Duration,
+    poll_interval: Duration,
+}
+
+impl ClientBuilder {
+    pub const DEFAULT_REFRESH_BEFORE: Duration = Duration::from_secs(12 * 60 * 60);
+    pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            tokens: Arc::new(MemoryTokenStore::default()),
+            refresh_before: Self::DEFAULT_REFRESH_BEFORE,
+            poll_interval: Self::DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    // where the token is kept between requests, e.g. a file for a command line tool
+    pub fn token_store(mut self, tokens: impl TokenStore + 'static) -> Self {
+        self.tokens = Arc::new(tokens);
+        self
+    }
+
+    // a token that expires within this is refreshed before the next request, see
+    // `Client::refresh_token`. a token that already expired cannot be refreshed, sign in again
+    pub fn refresh_before(mut self, refresh_before: Duration) -> Self {
+        self.refresh_before = refresh_before;
+        self
+    }
+
+    // how often `Client::message_stream` asks for new messages
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn build(self) -> Client {
+        Client {
+            http: reqwest::Client::new(),
+            base_url: self.base_url,
+            tokens: self.tokens,
+            refresh_before: self.refresh_before,
+            poll_interval: self.poll_interval,
+        }
+    }
+}
+
+impl Client {
+    // `base_url` is where the server runs, e.g. `http://localhost:3000`, without `/v1`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        ClientBuilder::new(base_url).build()
+    }
+
+    pub fn builder(base_url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(base_url)
+    }
+
+    pub fn token(&self) -> Option<JwtTokenString> {
+        self.tokens.load()
+    }
+
+    pub fn set_token(&self, token: Option<JwtTokenString>) {
+        self.tokens.save(token);
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/v1{}", self.base_url, path)
+    }
+
+    // sends the request with the current token, a token that is about to expire is refreshed first
+    async fn send(&self, build: impl FnOnce() -> RequestBuilder) -> Result<Response> {
+        if let Some(token) = self.tokens.load() {
+            if token::expires_within(&token, self.refresh_before) {
+                self.refresh_token().await?;
+            }
+        }
+
+        let mut request = build();
+        if let Some(token) = self.tokens.load() {
+            request = request.header(AUTHORIZATION, token);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(ClientError::from_response(response).await);
+        }
+
+        Ok(response)
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&(impl Serialize + ?Sized)>,
+    ) -> Result<T> {
+        let response = self.send_json(method, path, body).await?;
+        Ok(response.json().await?)
+    }
+
+    // for the routes that answer with a plain text confirmation
+    async fn request_empty(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&(impl Serialize + ?Sized)>,
+    ) -> Result<()> {
+        self.send_json(method, path, body).await?;
+        Ok(())
+    }
+
+    async fn send_json(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&(impl Serialize + ?Sized)>,
+    ) -> Result<Response> {
+        let url = self.url(path);
+        self.send(|| {
+            let request = self.http.request(method, &url);
+            match body {
+                Some(body) => request.json(body),
+                None => request,
+            }
+        })
+        .await
+    }
+
+    async fn query<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &(impl Serialize + ?Sized),
+    ) -> Result<T> {
+        let url = self.url(path);
+        let response = self.send(|| self.http.get(&url).query(query)).await?;
+        Ok(response.json().await?)
+    }
+}
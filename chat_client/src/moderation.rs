@@ -0,0 +1,32 @@
+use crate::{Client, Result, NO_BODY};
+use api::moderation_service::report::{
+    ModerationRequest, NewReport, Report, ReportDetails, ReportFilter,
+};
+use reqwest::Method;
+use uuid::Uuid;
+
+// /v1/reports and the /v1/moderation queue, the queue requires an admin
+impl Client {
+    pub async fn create_report(&self, new_report: &NewReport) -> Result<Report> {
+        self.request(Method::POST, "/reports", Some(new_report))
+            .await
+    }
+
+    pub async fn list_reports(&self, filter: &ReportFilter) -> Result<Vec<Report>> {
+        self.query("/moderation/reports", filter).await
+    }
+
+    pub async fn report(&self, report_id: Uuid) -> Result<ReportDetails> {
+        let path = format!("/moderation/reports/{}", report_id);
+        self.request(Method::GET, &path, NO_BODY).await
+    }
+
+    pub async fn moderate_report(
+        &self,
+        report_id: Uuid,
+        request: &ModerationRequest,
+    ) -> Result<ReportDetails> {
+        let path = format!("/moderation/reports/{}/actions", report_id);
+        self.request(Method::POST, &path, Some(request)).await
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{Client, Result};
+use api::conversation_service::message::Message;
+use futures::{stream, Stream};
+use std::collections::VecDeque;
+use uuid::Uuid;
+
+struct Poll {
+    client: Client,
+    conversation_id: Uuid,
+    // the newest message so far, the next poll only asks for what came after it
+    after: Option<Message>,
+    pending: VecDeque<Message>,
+    polled: bool,
+}
+
+impl Client {
+    // the messages already in the conversation, oldest first, then every new message as it arrives
+    // the server has no push endpoint yet, so this polls for new messages every `poll_interval`
+    // an error does not end the stream, the next poll tries again
+    pub fn message_stream(
+        &self,
+        conversation_id: Uuid,
+    ) -> impl Stream<Item = Result<Message>> + Send + 'static {
+        let poll = Poll {
+            client: self.clone(),
+            conversation_id,
+            after: None,
+            pending: VecDeque::new(),
+            polled: false,
+        };
+
+        stream::unfold(poll, |mut poll| async move {
+            loop {
+                if let Some(message) = poll.pending.pop_front() {
+                    return Some((Ok(message), poll));
+                }
+
+                if poll.polled {
+                    tokio::time::sleep(poll.client.poll_interval).await;
+                }
+                poll.polled = true;
+
+                let messages = match &poll.after {
+                    Some(after) => {
+                        poll.client
+                            .list_messages_after(poll.conversation_id, after)
+                            .await
+                    }
+                    None => poll.client.list_messages(poll.conversation_id).await,
+                };
+                match messages {
+                    Ok(messages) => {
+                        if let Some(newest) = messages.last() {
+                            poll.after = Some(newest.clone());
+                        }
+                        poll.pending.extend(messages);
+                    }
+                    Err(e) => return Some((Err(e), poll)),
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,54 @@
+use api::auth_service::claims::JwtTokenString;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// where a `Client` keeps its token, every clone of the client uses the same store
+pub trait TokenStore: Send + Sync {
+    fn load(&self) -> Option<JwtTokenString>;
+
+    // `None` when signing out
+    fn save(&self, token: Option<JwtTokenString>);
+}
+
+// the default store, the token is gone when the process exits
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    token: Mutex<Option<JwtTokenString>>,
+}
+
+impl TokenStore for MemoryTokenStore {
+    fn load(&self) -> Option<JwtTokenString> {
+        self.token.lock().unwrap().clone()
+    }
+
+    fn save(&self, token: Option<JwtTokenString>) {
+        *self.token.lock().unwrap() = token;
+    }
+}
+
+#[derive(Deserialize)]
+struct Expiry {
+    exp: u64,
+}
+
+// reads `exp` from the payload of the jwt without checking the signature, that is up to the server
+// a token that cannot be read is never refreshed, the server answers with its error instead
+pub(crate) fn expires_within(token: &JwtTokenString, duration: Duration) -> bool {
+    let Some(expiry) = token
+        .split('.')
+        .nth(1)
+        .and_then(|payload| URL_SAFE_NO_PAD.decode(payload).ok())
+        .and_then(|payload| serde_json::from_slice::<Expiry>(&payload).ok())
+    else {
+        return false;
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Duration::from_secs(expiry.exp) < now + duration
+}
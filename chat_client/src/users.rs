@@ -0,0 +1,107 @@
+use crate::{Client, Result, NO_BODY};
+use api::{
+    auth_service::user::PublicUserData,
+    user_service::{
+        profile::{OwnProfile, ProfileUpdate, UserProfile},
+        router::LookupQuery,
+        search::{Discoverability, SearchPage, SearchQuery},
+    },
+};
+use reqwest::Method;
+use uuid::Uuid;
+
+// /v1/users
+impl Client {
+    pub async fn own_profile(&self) -> Result<OwnProfile> {
+        self.request(Method::GET, "/users/me", NO_BODY).await
+    }
+
+    // fields that are `None` are left as they are
+    pub async fn update_profile(&self, update: &ProfileUpdate) -> Result<OwnProfile> {
+        self.request(Method::PATCH, "/users/me", Some(update)).await
+    }
+
+    pub async fn profile(&self, user_id: Uuid) -> Result<UserProfile> {
+        let path = format!("/users/{}", user_id);
+        self.request(Method::GET, &path, NO_BODY).await
+    }
+
+    // searches handles and display names, use `next_offset` of the page for the next one
+    pub async fn search_users(&self, query: &SearchQuery) -> Result<SearchPage> {
+        self.query("/users/search", query).await
+    }
+
+    // only finds users that can be found by their exact email
+    pub async fn lookup_user(&self, email: &str) -> Result<PublicUserData> {
+        let query = LookupQuery {
+            email: email.to_string(),
+        };
+        self.query("/users/lookup", &query).await
+    }
+
+    pub async fn discoverability(&self) -> Result<Discoverability> {
+        self.request(Method::GET, "/users/me/discoverability", NO_BODY)
+            .await
+    }
+
+    pub async fn set_discoverability(
+        &self,
+        discoverability: Discoverability,
+    ) -> Result<Discoverability> {
+        self.request(
+            Method::PUT,
+            "/users/me/discoverability",
+            Some(&discoverability),
+        )
+        .await
+    }
+
+    pub async fn list_contacts(&self) -> Result<Vec<PublicUserData>> {
+        self.request(Method::GET, "/users/me/contacts", NO_BODY)
+            .await
+    }
+
+    pub async fn add_contact(&self, user_id: Uuid) -> Result<()> {
+        let path = format!("/users/me/contacts/{}", user_id);
+        self.request_empty(Method::PUT, &path, NO_BODY).await
+    }
+
+    pub async fn remove_contact(&self, user_id: Uuid) -> Result<()> {
+        let path = format!("/users/me/contacts/{}", user_id);
+        self.request_empty(Method::DELETE, &path, NO_BODY).await
+    }
+
+    pub async fn list_blocks(&self) -> Result<Vec<PublicUserData>> {
+        self.request(Method::GET, "/users/me/blocks", NO_BODY).await
+    }
+
+    pub async fn block_user(&self, user_id: Uuid) -> Result<()> {
+        let path = format!("/users/{}/block", user_id);
+        self.request_empty(Method::PUT, &path, NO_BODY).await
+    }
+
+    pub async fn unblock_user(&self, user_id: Uuid) -> Result<()> {
+        let path = format!("/users/{}/block", user_id);
+        self.request_empty(Method::DELETE, &path, NO_BODY).await
+    }
+
+    // the server detects the image type from the bytes
+    pub async fn upload_avatar(&self, image: Vec<u8>) -> Result<OwnProfile> {
+        let url = self.url("/users/me/avatar");
+        let response = self
+            .send(|| self.http.put(&url).body(image.clone()))
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn delete_avatar(&self) -> Result<()> {
+        self.request_empty(Method::DELETE, "/users/me/avatar", NO_BODY)
+            .await
+    }
+
+    pub async fn avatar(&self, user_id: Uuid) -> Result<Vec<u8>> {
+        let url = self.url(&format!("/users/{}/avatar", user_id));
+        let response = self.send(|| self.http.get(&url)).await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+}
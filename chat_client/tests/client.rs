@@ -0,0 +1,224 @@
+use api::{
+    auth_service::{claims::JwtClaims, session::Session, user::User},
+    db_service::get_connection_pool,
+    mail_service::LogMailer,
+    server::{
+        error::{ApiError, ErrorCode},
+        routes, AppState,
+    },
+};
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use chat_client::{Client, ClientError, SearchQuery, SignInOutcome};
+use futures::StreamExt;
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+// serves `app` on a free local port and returns its base url
+async fn spawn(app: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("error binding listener");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+
+    format!("http://{}", addr)
+}
+
+async fn signed_up_client(base_url: &str) -> (Client, String) {
+    let client = Client::builder(base_url)
+        .poll_interval(Duration::from_millis(50))
+        .build();
+    let email = format!("testuser{}@email.com", Uuid::new_v4());
+    client
+        .signup(&email, "Password123#")
+        .await
+        .expect("error signing up");
+
+    (client, email)
+}
+
+#[tokio::test]
+async fn test_client_against_server() {
+    let pool = get_connection_pool().await.expect("error getting pg pool");
+    let base_url = spawn(routes(AppState::new(pool.clone(), Arc::new(LogMailer)))).await;
+
+    let (alice, _) = signed_up_client(&base_url).await;
+    let (bob, bob_email) = signed_up_client(&base_url).await;
+    let alice_id = alice
+        .own_profile()
+        .await
+        .expect("error getting profile")
+        .profile
+        .id;
+    let bob_id = bob
+        .own_profile()
+        .await
+        .expect("error getting profile")
+        .profile
+        .id;
+
+    // a second client signs in to the same account
+    let other = Client::new(&base_url);
+    match other.signin(&bob_email, "Password123#").await {
+        Ok(SignInOutcome::Authenticated(token)) => assert_eq!(other.token(), Some(token)),
+        other => panic!("unexpected signin outcome {:?}", other),
+    }
+
+    // bob does not have alice in his contacts, so it starts as a message request
+    let conversation_id = alice
+        .create_conversation(bob_id)
+        .await
+        .expect("error creating conversation");
+    let requests = bob.list_requests().await.expect("error listing requests");
+    assert!(requests.iter().any(|request| request.id == conversation_id));
+    bob.accept_request(conversation_id)
+        .await
+        .expect("error accepting request");
+
+    // api errors keep the code and details of the envelope
+    match alice.create_conversation(bob_id).await {
+        Err(ClientError::Api { status, body }) => {
+            assert_eq!(status, StatusCode::CONFLICT);
+            assert_eq!(body.code, ErrorCode::ConversationAlreadyExists);
+            assert_eq!(
+                body.details.unwrap()["conversation_id"],
+                conversation_id.to_string()
+            );
+        }
+        other => panic!("expected conversation_already_exists, got {:?}", other),
+    }
+
+    // the stream yields the messages that are already there and then the new ones
+    let first = alice
+        .send_message(conversation_id, "hello")
+        .await
+        .expect("error sending message");
+    assert_eq!(first.sender_id, alice_id);
+    let mut stream = Box::pin(bob.message_stream(conversation_id));
+    let message = stream.next().await.unwrap().expect("error polling");
+    assert_eq!(message.id, first.id);
+    alice
+        .send_message(conversation_id, "are you there?")
+        .await
+        .expect("error sending message");
+    let message = tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("no new message in the stream")
+        .unwrap()
+        .expect("error polling");
+    assert_eq!(message.content, "are you there?");
+
+    // the refreshed token belongs to the same session, no new one is created
+    let token = alice.token();
+    alice.refresh_token().await.expect("error refreshing token");
+    assert!(alice.token().is_some());
+    alice
+        .list_conversations()
+        .await
+        .expect("refreshed token is not accepted");
+    let sessions = Session::list_for_user(&pool, alice_id)
+        .await
+        .expect("error listing sessions");
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(
+        JwtClaims::decode(&alice.token().unwrap())
+            .unwrap()
+            .session_id,
+        JwtClaims::decode(&token.unwrap()).unwrap().session_id
+    );
+
+    // query strings are built from the shared query structs
+    let page = alice
+        .search_users(&SearchQuery {
+            q: "zz-nobody-has-this-handle".to_string(),
+            limit: None,
+            offset: None,
+        })
+        .await
+        .expect("error searching users");
+    assert!(page.results.is_empty());
+
+    // without a token the api answers with missing_token
+    alice.signout();
+    assert_eq!(alice.token(), None);
+    let error = alice.list_conversations().await.unwrap_err();
+    assert_eq!(error.code(), Some(ErrorCode::MissingToken));
+    assert_eq!(error.status(), Some(StatusCode::UNAUTHORIZED));
+
+    for user_id in [alice_id, bob_id] {
+        User::delete_user_by_id(&pool, user_id)
+            .await
+            .expect("Error deleting test user");
+    }
+}
+
+// a stand in for the api that counts the refreshes, only the refreshed token is accepted
+#[tokio::test]
+async fn test_token_is_refreshed_before_it_expires() {
+    let refreshes = Arc::new(AtomicUsize::new(0));
+    let counted_refreshes = refreshes.clone();
+    let app = Router::new()
+        .route(
+            "/v1/auth/refresh",
+            post(move || {
+                let refreshes = counted_refreshes.clone();
+                async move {
+                    refreshes.fetch_add(1, Ordering::SeqCst);
+                    (StatusCode::OK, [(header::AUTHORIZATION, "refreshed")], "ok")
+                }
+            }),
+        )
+        .route(
+            "/v1/users/me/contacts",
+            get(|headers: HeaderMap| async move {
+                if headers.get(header::AUTHORIZATION).unwrap() == "refreshed" {
+                    Json(Vec::<()>::new()).into_response()
+                } else {
+                    ApiError::new(StatusCode::UNAUTHORIZED, ErrorCode::TokenExpired, "expired")
+                        .into_response()
+                }
+            }),
+        );
+    let base_url = spawn(app).await;
+    // valid for one more day
+    let token = JwtClaims::new(Uuid::new_v4()).encode().unwrap();
+
+    let client = Client::builder(&base_url)
+        .refresh_before(Duration::from_secs(2 * 24 * 60 * 60))
+        .build();
+    client.set_token(Some(token.clone()));
+    let contacts = client
+        .list_contacts()
+        .await
+        .expect("token was not refreshed");
+    assert!(contacts.is_empty());
+    assert_eq!(client.token().as_deref(), Some("refreshed"));
+    assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+
+    // a token that is not about to expire is sent as it is
+    let client = Client::new(&base_url);
+    client.set_token(Some(token));
+    let error = client.list_contacts().await.unwrap_err();
+    assert_eq!(error.code(), Some(ErrorCode::TokenExpired));
+    assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,23 @@
+// the body of every error response from the api, see api/docs/error-codes.md
+export interface ApiErrorBody {
+	code: string;
+	message: string;
+	details: Record<string, unknown> | null;
+	request_id: string | null;
+}
+
+// reads the error envelope of a failed response, or `null` if the body is not one
+export async function readApiError(response: Response): Promise<ApiErrorBody | null> {
+	try {
+		const body = await response.json();
+		return typeof body?.code === 'string' && typeof body?.message === 'string' ? body : null;
+	} catch {
+		return null;
+	}
+}
+
+// the message to show for a failed response
+export async function errorMessageFor(response: Response, fallback: string): Promise<string> {
+	const error = await readApiError(response);
+	return error?.message || fallback;
+}